@@ -54,6 +54,7 @@ gen_mods_buildui!(
     ("../imag-link/src/ui.rs",      imaglink),
     ("../imag-notes/src/ui.rs",     imagnotes),
     ("../imag-ref/src/ui.rs",       imagref),
+    ("../imag-search/src/ui.rs",    imagsearch),
     ("../imag-store/src/ui.rs",     imagstore),
     ("../imag-tag/src/ui.rs",       imagtag),
     ("../imag-view/src/ui.rs",      imagview)
@@ -69,6 +70,7 @@ fn main() {
         .subcommand(build_subcommand!("link",       imaglink))
         .subcommand(build_subcommand!("notes",      imagnotes))
         .subcommand(build_subcommand!("ref",        imagref))
+        .subcommand(build_subcommand!("search",     imagsearch))
         .subcommand(build_subcommand!("store",      imagstore))
         .subcommand(build_subcommand!("tag",        imagtag))
         .subcommand(build_subcommand!("view",       imagview));
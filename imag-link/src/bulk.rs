@@ -0,0 +1,159 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Adding and removing many links at once, read from stdin
+//!
+//! Each line contains the fields of one link, separated by a tab. Empty lines and lines starting
+//! with '#' are ignored. All links are applied in the same store session, so the store is only
+//! opened once.
+
+use std::collections::BTreeMap;
+use std::io::{stdin, BufRead};
+use std::process::exit;
+
+use url::Url;
+
+use libimagrt::runtime::Runtime;
+use libimagstore::store::FileLockEntry;
+use libimagentrylink::internal::InternalLinker;
+use libimagentrylink::external::ExternalLinker;
+use libimagerror::trace::MapErrTrace;
+
+use get_entry_by_name;
+
+/// Whether links are added or removed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    Remove,
+}
+
+impl Operation {
+
+    fn past_tense(&self) -> &'static str {
+        match *self {
+            Operation::Add    => "added",
+            Operation::Remove => "removed",
+        }
+    }
+
+}
+
+/// Read `FROM<TAB>TO[<TAB>RELATION]` lines from stdin and add or remove the internal links
+pub fn bulk_internal(rt: &Runtime, op: Operation) {
+    let summary = for_each_line(|fields| {
+        let (from, to, relation) = match fields.len() {
+            2 => (fields[0], fields[1], None),
+            3 => (fields[0], fields[1], Some(fields[2])),
+            _ => return Err(String::from("expected 'FROM<TAB>TO' or 'FROM<TAB>TO<TAB>RELATION'")),
+        };
+
+        if from == to {
+            return Err(String::from("cannot link an entry to itself"));
+        }
+
+        let mut from = try!(get_entry(rt, from));
+        let mut to   = try!(get_entry(rt, to));
+
+        let res = match (op, relation) {
+            (Operation::Add, Some(rel))    => from.add_typed_link(&mut to, rel, BTreeMap::new()),
+            (Operation::Add, None)         => to.add_internal_link(&mut from),
+            (Operation::Remove, Some(rel)) => from.remove_typed_link(&mut to, rel),
+            (Operation::Remove, None)      => to.remove_internal_link(&mut from),
+        };
+
+        res.map_err(|e| format!("{}", e))
+    });
+
+    summary.report(op);
+}
+
+/// Read `ENTRY<TAB>URL` lines from stdin and add or remove the external links
+pub fn bulk_external(rt: &Runtime, op: Operation) {
+    let summary = for_each_line(|fields| {
+        if fields.len() != 2 {
+            return Err(String::from("expected 'ENTRY<TAB>URL'"));
+        }
+
+        let url       = try!(Url::parse(fields[1]).map_err(|e| format!("invalid URL: {}", e)));
+        let mut entry = try!(get_entry(rt, fields[0]));
+
+        let res = match op {
+            Operation::Add    => entry.add_external_link(rt.store(), url),
+            Operation::Remove => entry.remove_external_link(rt.store(), url),
+        };
+
+        res.map_err(|e| format!("{}", e))
+    });
+
+    summary.report(op);
+}
+
+struct Summary {
+    applied: usize,
+    failed: usize,
+}
+
+impl Summary {
+
+    fn report(&self, op: Operation) {
+        println!("{} links {}, {} lines failed", self.applied, op.past_tense(), self.failed);
+
+        if self.failed != 0 {
+            exit(1);
+        }
+    }
+
+}
+
+/// Call `f` with the tab separated fields of each line on stdin
+///
+/// A failing line is reported with its line number, and does not stop processing the other lines.
+fn for_each_line<F>(mut f: F) -> Summary
+    where F: FnMut(Vec<&str>) -> Result<(), String>
+{
+    let mut summary = Summary { applied: 0, failed: 0 };
+    let stdin       = stdin();
+
+    for (n, line) in stdin.lock().lines().enumerate() {
+        let line = line.map_err_trace_exit(1).unwrap();
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match f(line.split('\t').map(str::trim).collect()) {
+            Ok(()) => summary.applied += 1,
+            Err(e) => {
+                warn!("Line {}: {}", n + 1, e);
+                summary.failed += 1;
+            },
+        }
+    }
+
+    summary
+}
+
+fn get_entry<'a>(rt: &'a Runtime, name: &str) -> Result<FileLockEntry<'a>, String> {
+    match get_entry_by_name(rt, name) {
+        Ok(Some(entry)) => Ok(entry),
+        Ok(None)        => Err(format!("entry not found: {}", name)),
+        Err(e)          => Err(format!("could not get {}: {}", name, e)),
+    }
+}
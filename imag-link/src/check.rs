@@ -0,0 +1,80 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::process::exit;
+
+use libimagrt::runtime::Runtime;
+use libimagstore::store::StoreObject;
+use libimagstore::storeid::StoreId;
+use libimagentrylink::check::{LinkChecker, Repair};
+use libimagerror::trace::{trace_error, trace_error_exit};
+
+pub fn check(rt: &Runtime) {
+    let scmd    = rt.cli().subcommand_matches("check").unwrap();
+    let dry_run = scmd.is_present("dry-run");
+    let mode    = if scmd.is_present("remove") { Repair::Remove } else { Repair::Complete };
+
+    let modules = scmd
+        .values_of("module")
+        .map(|ms| ms.map(String::from).collect())
+        .unwrap_or_else(|| vec![String::new()]);
+
+    let ids = modules
+        .iter()
+        .flat_map(|m| rt.store().walk(m))
+        .filter_map(|obj| match obj {
+            StoreObject::Id(id)        => Some(id),
+            StoreObject::Collection(_) => None,
+        })
+        .filter(is_entry);
+
+    let problems = match LinkChecker::new(rt.store()).check_all(ids) {
+        Ok(problems) => problems,
+        Err(e)       => trace_error_exit(&e, 1),
+    };
+
+    let mut repaired = 0;
+    for problem in problems.iter() {
+        println!("{}", problem);
+
+        if !dry_run {
+            match problem.repair(rt.store(), mode) {
+                Ok(()) => repaired += 1,
+                Err(e) => trace_error(&e),
+            }
+        }
+    }
+
+    if dry_run {
+        println!("{} problems found, nothing repaired (dry run)", problems.len());
+    } else {
+        println!("{} problems found, {} repaired", problems.len(), repaired);
+    }
+
+    if problems.len() != repaired && !dry_run {
+        exit(1);
+    }
+}
+
+/// Whether the id is an entry, and not in a hidden directory like ".git"
+fn is_entry(id: &StoreId) -> bool {
+    !id.local()
+        .components()
+        .any(|c| c.as_os_str().to_str().map(|s| s.starts_with('.')).unwrap_or(true))
+}
@@ -0,0 +1,116 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::fs::File;
+use std::io::{stdin, stdout, BufRead, Write};
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+use toml::Value;
+
+use libimagrt::runtime::Runtime;
+use libimagstore::storeid::StoreId;
+use libimagentrylink::graph::export::{GraphExporter, GraphFormat};
+use libimagerror::trace::{MapErrTrace, trace_error_exit};
+
+pub fn export_graph(rt: &Runtime) {
+    let scmd   = rt.cli().subcommand_matches("export-graph").unwrap();
+    let format = scmd
+        .value_of("format")
+        .and_then(GraphFormat::from_name)
+        .unwrap_or(GraphFormat::Dot);
+
+    let ids = get_ids(rt, scmd);
+    debug!("Exporting graph of {} entries as {:?}", ids.len(), format);
+
+    let mut exporter = GraphExporter::new(rt.store())
+        .include_external_links(scmd.is_present("external"));
+    if let Some(label) = get_label(rt, scmd) {
+        exporter = exporter.label_with(label);
+    }
+
+    let mut out : Box<Write> = match scmd.value_of("output") {
+        None | Some("-") => Box::new(stdout()),
+        Some(path) => match File::create(path) {
+            Err(e) => trace_error_exit(&e, 1),
+            Ok(f)  => Box::new(f),
+        },
+    };
+
+    exporter.export(ids.into_iter(), format, &mut out).map_err_trace_exit(1).ok();
+}
+
+/// Get the header path to label the nodes with, from the commandline or the configuration
+fn get_label(rt: &Runtime, scmd: &ArgMatches) -> Option<String> {
+    scmd.value_of("label")
+        .map(String::from)
+        .or_else(|| {
+            rt.config().and_then(|cfg| match cfg.config().lookup("link.graph_label") {
+                Some(&Value::String(ref s)) => Some(s.clone()),
+                Some(_) => {
+                    warn!("'link.graph_label' in the configuration is not a String, ignoring");
+                    None
+                },
+                None => None,
+            })
+        })
+}
+
+/// Get the ids passed via `--id` and `--stdin` and the ids of the modules passed via `--module`
+fn get_ids(rt: &Runtime, scmd: &ArgMatches) -> Vec<StoreId> {
+    let mut ids = vec![];
+
+    if let Some(values) = scmd.values_of("id") {
+        for id in values {
+            ids.push(parse_id(id));
+        }
+    }
+
+    if scmd.is_present("stdin") {
+        let stdin = stdin();
+        for line in stdin.lock().lines() {
+            let line = line.map_err_trace_exit(1).unwrap();
+            let line = line.trim();
+            if !line.is_empty() {
+                ids.push(parse_id(line));
+            }
+        }
+    }
+
+    if let Some(modules) = scmd.values_of("module") {
+        for module in modules {
+            match rt.store().retrieve_for_module(module) {
+                Ok(iter) => ids.extend(iter.map(StoreId::without_base)),
+                Err(e)   => trace_error_exit(&e, 1),
+            }
+        }
+    }
+
+    ids
+}
+
+fn parse_id(s: &str) -> StoreId {
+    match StoreId::new_baseless(PathBuf::from(s)) {
+        Ok(id) => id,
+        Err(e) => {
+            warn!("Not a valid id: '{}'", s);
+            trace_error_exit(&e, 1)
+        },
+    }
+}
@@ -0,0 +1,307 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::process::exit;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::Client;
+use hyper::client::RedirectPolicy;
+use hyper::client::response::Response;
+use hyper::header::Location;
+use hyper::net::{HttpStream, HttpsConnector, NetworkConnector, Openssl};
+use url::Url;
+
+use libimagrt::runtime::Runtime;
+use libimagentrylink::error::LinkErrorKind as LEK;
+use libimagentrylink::error::MapErrInto;
+use libimagentrylink::external::check::{HttpClient, HttpResponse, all_external_links, check_links,
+                                        record_result};
+use libimagentrylink::result::Result;
+use libimagerror::trace::{trace_error, trace_error_exit};
+use libimagutil::warn_exit::warn_exit;
+
+/// A connector for hyper, which gives up connecting after a timeout
+struct TimeoutConnector {
+    timeout: Duration,
+}
+
+impl NetworkConnector for TimeoutConnector {
+    type Stream = HttpStream;
+
+    fn connect(&self, host: &str, port: u16, scheme: &str) -> ::hyper::Result<HttpStream> {
+        match scheme {
+            "http" | "https" => connect_timeout(host, port, self.timeout)
+                .map(HttpStream)
+                .map_err(From::from),
+            _ => {
+                let e = io::Error::new(io::ErrorKind::InvalidInput, "Invalid scheme for Http");
+                Err(From::from(e))
+            },
+        }
+    }
+}
+
+/// Connect to `host`, giving up on each of its addresses after `timeout`
+fn connect_timeout(host: &str, port: u16, timeout: Duration) -> io::Result<TcpStream> {
+    let mut err = io::Error::new(io::ErrorKind::InvalidInput, "Host has no addresses");
+    for addr in try!((host, port).to_socket_addrs()) {
+        match connect_addr_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e)     => err = e,
+        }
+    }
+    Err(err)
+}
+
+/// Connect to `addr` with a non-blocking connect, which is given up after `timeout`
+#[cfg(unix)]
+fn connect_addr_timeout(addr: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+    use std::cmp::min;
+    use std::os::unix::io::AsRawFd;
+
+    use libc::{EINPROGRESS, F_GETFL, F_SETFL, O_NONBLOCK, POLLOUT, c_int, fcntl, poll, pollfd};
+    use net2::TcpBuilder;
+
+    let builder = try!(match *addr {
+        SocketAddr::V4(_) => TcpBuilder::new_v4(),
+        SocketAddr::V6(_) => TcpBuilder::new_v6(),
+    });
+    let fd = builder.as_raw_fd();
+
+    let flags = unsafe { fcntl(fd, F_GETFL) };
+    if flags < 0 || unsafe { fcntl(fd, F_SETFL, flags | O_NONBLOCK) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    if let Err(e) = builder.connect(addr) {
+        if e.raw_os_error() != Some(EINPROGRESS) {
+            return Err(e);
+        }
+
+        let millis  = timeout.as_secs() * 1000 + (timeout.subsec_nanos() / 1_000_000) as u64;
+        let millis  = min(millis, c_int::max_value() as u64) as c_int;
+        let mut pfd = pollfd { fd: fd, events: POLLOUT, revents: 0 };
+        match unsafe { poll(&mut pfd, 1, millis) } {
+            n if n < 0 => return Err(io::Error::last_os_error()),
+            0          => {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Connecting timed out"));
+            },
+            _          => {},
+        }
+    }
+
+    // The connect finished, either successfully or with the error of the socket
+    let stream = try!(builder.to_tcp_stream());
+    if let Some(e) = try!(stream.take_error()) {
+        return Err(e);
+    }
+    try!(stream.set_nonblocking(false));
+    Ok(stream)
+}
+
+/// Connect to `addr`, there is no timeout for connecting on this platform
+#[cfg(not(unix))]
+fn connect_addr_timeout(addr: &SocketAddr, _: Duration) -> io::Result<TcpStream> {
+    TcpStream::connect(addr)
+}
+
+/// A `HttpClient` on top of hyper, with a timeout for connecting and for each request
+struct HyperClient {
+    timeout: Duration,
+}
+
+impl HyperClient {
+
+    fn client(&self) -> Client {
+        let connector = TimeoutConnector { timeout: self.timeout };
+        let mut client = Client::with_connector(HttpsConnector::with_connector(Openssl::default(),
+                                                                                connector));
+        client.set_read_timeout(Some(self.timeout));
+        client.set_write_timeout(Some(self.timeout));
+        client.set_redirect_policy(RedirectPolicy::FollowNone);
+        client
+    }
+
+    fn to_response(res: Response) -> HttpResponse {
+        HttpResponse {
+            status: res.status.to_u16(),
+            location: res.headers.get::<Location>().map(|l| l.to_string()),
+        }
+    }
+
+}
+
+impl HttpClient for HyperClient {
+
+    fn head(&self, url: &Url) -> Result<HttpResponse> {
+        self.client()
+            .head(url.as_str())
+            .send()
+            .map(HyperClient::to_response)
+            .map_err_into(LEK::HttpRequestError)
+    }
+
+    fn get(&self, url: &Url) -> Result<HttpResponse> {
+        self.client()
+            .get(url.as_str())
+            .send()
+            .map(HyperClient::to_response)
+            .map_err_into(LEK::HttpRequestError)
+    }
+
+}
+
+pub fn check_external_links(rt: &Runtime) {
+    let scmd = rt
+        .cli()
+        .subcommand_matches("external")
+        .and_then(|s| s.subcommand_matches("check"))
+        .unwrap();
+
+    let timeout = match scmd.value_of("timeout").unwrap_or("10").parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_)   => warn_exit("--timeout must be a number of seconds", 1),
+    };
+
+    let jobs = match scmd.value_of("jobs").unwrap_or("4").parse::<usize>() {
+        Ok(0) | Err(_) => warn_exit("--jobs must be a positive number", 1),
+        Ok(n)          => n,
+    };
+
+    let links = match all_external_links(rt.store()) {
+        Ok(links) => links,
+        Err(e)    => trace_error_exit(&e, 1),
+    };
+
+    info!("Checking {} external links", links.len());
+    let results = check_links(Arc::new(HyperClient { timeout: timeout }), links, jobs);
+
+    let mut broken = 0;
+    for result in results.iter() {
+        if result.status.is_broken() {
+            broken += 1;
+            println!("{}: {}", result.url, result.status);
+        } else {
+            debug!("{}: {}", result.url, result.status);
+        }
+
+        if let Err(e) = record_result(rt.store(), result) {
+            trace_error(&e);
+        }
+    }
+
+    println!("{} links checked, {} broken", results.len(), broken);
+
+    if broken != 0 {
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use url::Url;
+
+    use libimagentrylink::external::check::HttpClient;
+
+    use super::{HyperClient, connect_timeout};
+
+    /// Answer the first request on `listener` with `response`
+    fn serve_once(listener: TcpListener, response: &'static str) {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buf     = [0; 1024];
+            while !String::from_utf8_lossy(&request).contains("\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+    }
+
+    fn url(listener: &TcpListener, path: &str) -> Url {
+        let addr = listener.local_addr().unwrap();
+        Url::parse(&format!("http://127.0.0.1:{}{}", addr.port(), path)).unwrap()
+    }
+
+    #[test]
+    fn test_connect_timeout_connects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port     = listener.local_addr().unwrap().port();
+
+        assert!(connect_timeout("127.0.0.1", port, Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn test_connect_timeout_fails_for_closed_port() {
+        let port = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+
+        assert!(connect_timeout("127.0.0.1", port, Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn test_request_to_server_which_never_answers_times_out() {
+        // The connection is accepted by the kernel, but nothing is ever sent back
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client   = HyperClient { timeout: Duration::from_secs(1) };
+
+        let start = Instant::now();
+        assert!(client.head(&url(&listener, "/")).is_err());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_redirects_are_not_followed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url      = url(&listener, "/old");
+        serve_once(listener, concat!("HTTP/1.1 301 Moved Permanently\r\n",
+                                     "Location: /new\r\n",
+                                     "Content-Length: 0\r\n\r\n"));
+
+        let response = HyperClient { timeout: Duration::from_secs(5) }.head(&url).unwrap();
+        assert_eq!(response.status, 301);
+        assert_eq!(response.location, Some(String::from("/new")));
+    }
+
+    #[test]
+    fn test_status_of_answer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url      = url(&listener, "/missing");
+        serve_once(listener, "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+
+        let response = HyperClient { timeout: Duration::from_secs(5) }.get(&url).unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.location, None);
+    }
+
+}
@@ -37,6 +37,9 @@ extern crate clap;
 extern crate semver;
 extern crate toml;
 extern crate url;
+extern crate hyper;
+extern crate net2;
+extern crate libc;
 #[macro_use] extern crate version;
 
 extern crate libimagentrylink;
@@ -45,6 +48,7 @@ extern crate libimagstore;
 extern crate libimagerror;
 extern crate libimagutil;
 
+use std::collections::BTreeMap;
 use std::ops::Deref;
 
 use libimagrt::runtime::Runtime;
@@ -52,17 +56,28 @@ use libimagrt::setup::generate_runtime_setup;
 use libimagstore::error::StoreError;
 use libimagstore::store::Entry;
 use libimagstore::store::FileLockEntry;
-use libimagstore::store::Store;
+use libimagstore::access::StoreAccess;
+use libimagstore::storeid::StoreId;
 use libimagerror::trace::{MapErrTrace, trace_error, trace_error_exit};
-use libimagentrylink::external::ExternalLinker;
+use libimagentrylink::external::{ExternalLinker, LinkMetadata, get_external_link};
+use libimagentrylink::graph::LinkGraph;
 use libimagutil::warn_result::*;
 use libimagutil::warn_exit::warn_exit;
 use libimagutil::info_result::*;
 use clap::ArgMatches;
+use toml::Value;
 use url::Url;
 
+mod bulk;
+mod check;
+mod export_graph;
+mod external_check;
 mod ui;
 
+use bulk::{Operation, bulk_external, bulk_internal};
+use check::check;
+use export_graph::export_graph;
+use external_check::check_external_links;
 use ui::build_ui;
 
 fn main() {
@@ -75,9 +90,13 @@ fn main() {
         .subcommand_name()
         .map(|name| {
             match name {
-                "internal" => handle_internal_linking(&rt),
-                "external" => handle_external_linking(&rt),
-                _ => warn_exit("No commandline call", 1)
+                "internal"     => handle_internal_linking(&rt),
+                "external"     => handle_external_linking(&rt),
+                "path"         => handle_path(&rt),
+                "neighbours"   => handle_neighbours(&rt),
+                "export-graph" => export_graph(&rt),
+                "check"        => check(&rt),
+                _              => warn_exit("No commandline call", 1)
             }
         });
 }
@@ -96,23 +115,28 @@ fn handle_internal_linking(rt: &Runtime) {
                 debug!("Listing for '{}'", entry);
                 match get_entry_by_name(rt, entry) {
                     Ok(Some(e)) => {
-                        e.get_internal_links()
-                            .map(|iter| {
-                                iter.filter(move |id| {
-                                    cmd.is_present("list-externals-too") || !is_external_link_storeid(&id)
+                        e.get_typed_links()
+                            .map(|links| {
+                                links.into_iter().filter(move |l| {
+                                    cmd.is_present("list-externals-too") || !is_external_link_storeid(&l.link)
                                 })
                             })
                             .map(|links| {
                                 let i = links
                                     .filter_map(|l| {
-                                        l.to_str()
+                                        l.link
+                                            .to_str()
                                             .map_warn_err(|e| format!("Failed to convert StoreId to string: {:?}", e))
                                             .ok()
+                                            .map(|id| (id, l.relation))
                                     })
                                     .enumerate();
 
-                                for (i, link) in i {
-                                    println!("{: <3}: {}", i, link);
+                                for (i, (link, relation)) in i {
+                                    match relation {
+                                        Some(relation) => println!("{: <3}: {} ({})", i, link, relation),
+                                        None           => println!("{: <3}: {}", i, link),
+                                    }
                                 }
                             })
                             .map_err_trace()
@@ -133,20 +157,43 @@ fn handle_internal_linking(rt: &Runtime) {
             debug!("Listing ready!");
         },
         None => {
-            let mut from = match get_from_entry(&rt) {
+            let (name, scmd) = match cmd.subcommand() {
+                (name, Some(scmd)) => (name, scmd),
+                _ => warn_exit("No commandline call", 1),
+            };
+
+            if scmd.is_present("stdin") {
+                match name {
+                    "add"    => bulk_internal(rt, Operation::Add),
+                    "remove" => bulk_internal(rt, Operation::Remove),
+                    _        => unreachable!(),
+                }
+                return;
+            }
+
+            let mut from = match get_from_entry(&rt, scmd) {
                 None => warn_exit("No 'from' entry", 1),
                 Some(s) => s,
             };
             debug!("Link from = {:?}", from.deref());
 
-            let to = match get_to_entries(&rt) {
+            let to = match get_to_entries(&rt, scmd) {
                 None => warn_exit("No 'to' entry", 1),
                 Some(to) => to,
             };
             debug!("Link to = {:?}", to.iter().map(|f| f.deref()).collect::<Vec<&Entry>>());
 
-            match cmd.subcommand_name() {
-                Some("add") => {
+            match (name, scmd.value_of("relation")) {
+                ("add", Some(relation)) => {
+                    let attributes = get_attributes(scmd);
+                    for mut to_entry in to {
+                        if let Err(e) = from.add_typed_link(&mut to_entry, relation, attributes.clone()) {
+                            trace_error_exit(&e, 1);
+                        }
+                    }
+                },
+
+                ("add", None) => {
                     for mut to_entry in to {
                         if let Err(e) = to_entry.add_internal_link(&mut from) {
                             trace_error_exit(&e, 1);
@@ -154,7 +201,15 @@ fn handle_internal_linking(rt: &Runtime) {
                     }
                 },
 
-                Some("remove") => {
+                ("remove", Some(relation)) => {
+                    for mut to_entry in to {
+                        if let Err(e) = from.remove_typed_link(&mut to_entry, relation) {
+                            trace_error_exit(&e, 1);
+                        }
+                    }
+                },
+
+                ("remove", None) => {
                     for mut to_entry in to {
                         if let Err(e) = to_entry.remove_internal_link(&mut from) {
                             trace_error_exit(&e, 1);
@@ -168,13 +223,8 @@ fn handle_internal_linking(rt: &Runtime) {
     }
 }
 
-fn get_from_entry<'a>(rt: &'a Runtime) -> Option<FileLockEntry<'a>> {
-    rt.cli()
-        .subcommand_matches("internal")
-        .unwrap() // safe, we know there is an "internal" subcommand"
-        .subcommand_matches("add")
-        .unwrap() // safe, we know there is an "add" subcommand
-        .value_of("from")
+fn get_from_entry<'a>(rt: &'a Runtime, scmd: &ArgMatches) -> Option<FileLockEntry<'a>> {
+    scmd.value_of("from")
         .and_then(|from_name| {
             match get_entry_by_name(rt, from_name) {
                 Err(e) => {
@@ -188,13 +238,8 @@ fn get_from_entry<'a>(rt: &'a Runtime) -> Option<FileLockEntry<'a>> {
         })
 }
 
-fn get_to_entries<'a>(rt: &'a Runtime) -> Option<Vec<FileLockEntry<'a>>> {
-    rt.cli()
-        .subcommand_matches("internal")
-        .unwrap() // safe, we know there is an "internal" subcommand"
-        .subcommand_matches("add")
-        .unwrap() // safe, we know there is an "add" subcommand
-        .values_of("to")
+fn get_to_entries<'a>(rt: &'a Runtime, scmd: &ArgMatches) -> Option<Vec<FileLockEntry<'a>>> {
+    scmd.values_of("to")
         .map(|values| {
             let mut v = vec![];
             for entry in values.map(|v| get_entry_by_name(rt, v)) {
@@ -208,16 +253,106 @@ fn get_to_entries<'a>(rt: &'a Runtime) -> Option<Vec<FileLockEntry<'a>>> {
         })
 }
 
+/// Get the attributes for a relation, passed as "key=value"
+fn get_attributes(scmd: &ArgMatches) -> BTreeMap<String, Value> {
+    scmd.values_of("attribute")
+        .map(|attrs| {
+            attrs.filter_map(|attr| {
+                    let mut split = attr.splitn(2, '=');
+                    match (split.next(), split.next()) {
+                        (Some(key), Some(value)) => {
+                            Some((String::from(key), Value::String(String::from(value))))
+                        },
+                        _ => {
+                            warn!("Ignoring attribute '{}', not in the form 'key=value'", attr);
+                            None
+                        },
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_else(BTreeMap::new)
+}
+
 fn get_entry_by_name<'a>(rt: &'a Runtime, name: &str) -> Result<Option<FileLockEntry<'a>>, StoreError> {
     use std::path::PathBuf;
-    use libimagstore::storeid::StoreId;
 
-    StoreId::new(Some(rt.store().path().clone()), PathBuf::from(name))
+    StoreId::new(Some(rt.store().primary().path().clone()), PathBuf::from(name))
         .and_then(|id| rt.store().get(id))
 }
 
+fn get_id(rt: &Runtime, name: &str) -> StoreId {
+    use std::path::PathBuf;
+
+    let id = StoreId::new_baseless(PathBuf::from(name)).map_err_trace_exit(1).unwrap();
+    match rt.store().exists(id.clone()) {
+        Ok(true)  => id,
+        Ok(false) => warn_exit(&format!("Entry not found: {:?}", name), 1),
+        Err(e)    => trace_error_exit(&e, 1),
+    }
+}
+
+fn print_id(id: &StoreId) {
+    match id.to_str() {
+        Ok(s)  => println!("{}", s),
+        Err(e) => trace_error(&e),
+    }
+}
+
+fn handle_path(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("path").unwrap();
+    let from = get_id(rt, scmd.value_of("from").unwrap()); // enforced by clap
+    let to   = get_id(rt, scmd.value_of("to").unwrap()); // enforced by clap
+
+    match LinkGraph::new(rt.store()).shortest_path(&from, &to) {
+        Ok(Some(path)) => for id in path {
+            print_id(&id);
+        },
+        Ok(None) => warn_exit("Entries are not connected", 1),
+        Err(e)   => trace_error_exit(&e, 1),
+    }
+}
+
+fn handle_neighbours(rt: &Runtime) {
+    let scmd  = rt.cli().subcommand_matches("neighbours").unwrap();
+    let id    = get_id(rt, scmd.value_of("id").unwrap()); // enforced by clap
+    let depth = match scmd.value_of("depth").map(|d| d.parse::<usize>()) {
+        None         => 1,
+        Some(Ok(d))  => d,
+        Some(Err(_)) => warn_exit("Depth must be a number", 1),
+    };
+
+    let graph   = LinkGraph::new(rt.store());
+    let reached = if scmd.is_present("depth-first") {
+        graph.dfs(&id, depth)
+    } else {
+        graph.bfs(&id, depth)
+    };
+
+    match reached {
+        Ok(reached) => for (link, dist) in reached {
+            match link.to_str() {
+                Ok(s)  => println!("{: <3}: {}", dist, s),
+                Err(e) => trace_error(&e),
+            }
+        },
+        Err(e) => trace_error_exit(&e, 1),
+    }
+}
+
 fn handle_external_linking(rt: &Runtime) {
     let scmd       = rt.cli().subcommand_matches("external").unwrap();
+
+    if scmd.subcommand_matches("check").is_some() {
+        return check_external_links(rt);
+    }
+
+    match scmd.value_of("stdin") {
+        Some("add")    => return bulk_external(rt, Operation::Add),
+        Some("remove") => return bulk_external(rt, Operation::Remove),
+        _              => {},
+    }
+
     let entry_name = scmd.value_of("id").unwrap(); // enforced by clap
     let mut entry  = match get_entry_by_name(rt, entry_name) {
         Err(e) => trace_error_exit(&e, 1),
@@ -255,21 +390,44 @@ fn handle_external_linking(rt: &Runtime) {
     panic!("Clap failed to enforce one of 'add', 'remove', 'set' or 'list'");
 }
 
-fn add_link_to_entry(store: &Store, matches: &ArgMatches, entry: &mut FileLockEntry) {
-    Url::parse(matches.value_of("add").unwrap())
-        .map_err_trace_exit(1)
-        .map(|link| entry.add_external_link(store, link).map_err_trace().map_info_str("Ok"))
-        .ok();
+fn add_link_to_entry(store: &StoreAccess, matches: &ArgMatches, entry: &mut FileLockEntry) {
+    let link = Url::parse(matches.value_of("add").unwrap()).map_err_trace_exit(1).unwrap();
+
+    if let Err(e) = entry.add_external_link(store, link.clone()) {
+        trace_error_exit(&e, 1);
+    }
+
+    if matches.is_present("title") || matches.is_present("description") {
+        match get_external_link(store, &link) {
+            Ok(Some(mut l)) => {
+                let meta = l.get_metadata()
+                    .map(|meta| LinkMetadata {
+                        title: matches.value_of("title").map(String::from).or(meta.title),
+                        description: matches.value_of("description").map(String::from).or(meta.description),
+                        added: meta.added,
+                    })
+                    .and_then(|meta| l.set_metadata(meta));
+
+                if let Err(e) = meta {
+                    trace_error_exit(&e, 1);
+                }
+            },
+            Ok(None) => warn_exit("Link entry not found after adding the link", 1),
+            Err(e)   => trace_error_exit(&e, 1),
+        }
+    }
+
+    info!("Ok");
 }
 
-fn remove_link_from_entry(store: &Store, matches: &ArgMatches, entry: &mut FileLockEntry) {
+fn remove_link_from_entry(store: &StoreAccess, matches: &ArgMatches, entry: &mut FileLockEntry) {
     Url::parse(matches.value_of("remove").unwrap())
         .map_err_trace_exit(1)
         .map(|link| entry.remove_external_link(store, link).map_err_trace().map_info_str("Ok"))
         .ok();
 }
 
-fn set_links_for_entry(store: &Store, matches: &ArgMatches, entry: &mut FileLockEntry) {
+fn set_links_for_entry(store: &StoreAccess, matches: &ArgMatches, entry: &mut FileLockEntry) {
     let links = matches
         .value_of("links")
         .map(String::from)
@@ -294,7 +452,7 @@ fn set_links_for_entry(store: &Store, matches: &ArgMatches, entry: &mut FileLock
         .ok();
 }
 
-fn list_links_for_entry(store: &Store, entry: &mut FileLockEntry) {
+fn list_links_for_entry(store: &StoreAccess, entry: &mut FileLockEntry) {
     entry.get_external_links(store)
         .and_then(|links| {
             for (i, link) in links.enumerate() {
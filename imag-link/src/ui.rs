@@ -17,7 +17,7 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
-use clap::{Arg, ArgGroup, App, SubCommand};
+use clap::{Arg, ArgGroup, App, AppSettings, SubCommand};
 
 pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
     app
@@ -31,17 +31,39 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                                      .long("from")
                                      .short("f")
                                      .takes_value(true)
-                                     .required(true)
+                                     .required_unless("stdin")
                                      .help("Link from this entry")
                                      .value_name("ENTRY"))
                                 .arg(Arg::with_name("to")
                                      .long("to")
                                      .short("t")
                                      .takes_value(true)
-                                     .required(true)
+                                     .required_unless("stdin")
                                      .multiple(true)
                                      .help("Link to this entries")
                                      .value_name("ENTRIES"))
+                                .arg(Arg::with_name("relation")
+                                     .long("relation")
+                                     .short("r")
+                                     .takes_value(true)
+                                     .required(false)
+                                     .help("Relation of the 'from' entry to the 'to' entries, for example 'depends-on'. The 'to' entries get the reverse relation")
+                                     .value_name("RELATION"))
+                                .arg(Arg::with_name("attribute")
+                                     .long("attribute")
+                                     .short("a")
+                                     .takes_value(true)
+                                     .required(false)
+                                     .multiple(true)
+                                     .requires("relation")
+                                     .help("Attribute of the relation")
+                                     .value_name("KEY=VALUE"))
+                                .arg(Arg::with_name("stdin")
+                                     .long("stdin")
+                                     .takes_value(false)
+                                     .required(false)
+                                     .conflicts_with_all(&["from", "to", "relation", "attribute"])
+                                     .help("Read 'FROM<TAB>TO' or 'FROM<TAB>TO<TAB>RELATION' lines from stdin and add all these links"))
                                 )
 
                     .subcommand(SubCommand::with_name("remove")
@@ -51,17 +73,30 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                                  .long("from")
                                  .short("f")
                                  .takes_value(true)
-                                 .required(true)
+                                 .required_unless("stdin")
                                  .help("Remove Link from this entry")
                                  .value_name("ENTRY"))
                             .arg(Arg::with_name("to")
                                  .long("to")
                                  .short("t")
                                  .takes_value(true)
-                                 .required(true)
+                                 .required_unless("stdin")
                                  .multiple(true)
                                  .help("Remove links to these entries")
                                  .value_name("ENTRIES"))
+                            .arg(Arg::with_name("relation")
+                                 .long("relation")
+                                 .short("r")
+                                 .takes_value(true)
+                                 .required(false)
+                                 .help("Only remove this relation. The link is removed if no other relation is left")
+                                 .value_name("RELATION"))
+                            .arg(Arg::with_name("stdin")
+                                 .long("stdin")
+                                 .takes_value(false)
+                                 .required(false)
+                                 .conflicts_with_all(&["from", "to", "relation"])
+                                 .help("Read 'FROM<TAB>TO' or 'FROM<TAB>TO<TAB>RELATION' lines from stdin and remove all these links"))
                             )
 
                     .arg(Arg::with_name("list")
@@ -79,15 +114,135 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                          .required(false)
                          .help("If --list is provided, also list external links (debugging helper that might be removed at some point"))
                     )
+        .subcommand(SubCommand::with_name("path")
+                    .about("Print a shortest path of internal links between two entries")
+                    .version("0.1")
+                    .arg(Arg::with_name("from")
+                         .index(1)
+                         .takes_value(true)
+                         .required(true)
+                         .help("Path from this entry")
+                         .value_name("ENTRY"))
+                    .arg(Arg::with_name("to")
+                         .index(2)
+                         .takes_value(true)
+                         .required(true)
+                         .help("Path to this entry")
+                         .value_name("ENTRY"))
+                    )
+        .subcommand(SubCommand::with_name("neighbours")
+                    .about("Print the entries reachable from an entry via internal links")
+                    .version("0.1")
+                    .arg(Arg::with_name("id")
+                         .index(1)
+                         .takes_value(true)
+                         .required(true)
+                         .help("Start at this entry")
+                         .value_name("ENTRY"))
+                    .arg(Arg::with_name("depth")
+                         .long("depth")
+                         .short("d")
+                         .takes_value(true)
+                         .required(false)
+                         .help("Follow links up to this depth (default: 1)")
+                         .value_name("N"))
+                    .arg(Arg::with_name("depth-first")
+                         .long("depth-first")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Traverse depth-first instead of breadth-first"))
+                    )
+        .subcommand(SubCommand::with_name("export-graph")
+                    .about("Export the link graph of entries as Graphviz DOT or GraphML")
+                    .version("0.1")
+                    .arg(Arg::with_name("format")
+                         .long("format")
+                         .short("f")
+                         .takes_value(true)
+                         .required(false)
+                         .possible_values(&["dot", "graphml"])
+                         .help("Output format (default: dot)")
+                         .value_name("FORMAT"))
+                    .arg(Arg::with_name("output")
+                         .long("output")
+                         .short("o")
+                         .takes_value(true)
+                         .required(false)
+                         .help("Write to this file instead of stdout")
+                         .value_name("FILE"))
+                    .arg(Arg::with_name("module")
+                         .long("module")
+                         .short("m")
+                         .takes_value(true)
+                         .required(false)
+                         .multiple(true)
+                         .help("Export the entries of this module")
+                         .value_name("MODULE"))
+                    .arg(Arg::with_name("id")
+                         .long("id")
+                         .short("i")
+                         .takes_value(true)
+                         .required(false)
+                         .multiple(true)
+                         .help("Export this entry")
+                         .value_name("ENTRY"))
+                    .arg(Arg::with_name("stdin")
+                         .long("stdin")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Read the ids of the entries to export from stdin, one per line"))
+                    .arg(Arg::with_name("label")
+                         .long("label")
+                         .short("l")
+                         .takes_value(true)
+                         .required(false)
+                         .help("Label the nodes with this header field (default: 'link.graph_label' from the configuration)")
+                         .value_name("HEADER-PATH"))
+                    .arg(Arg::with_name("external")
+                         .long("external")
+                         .short("e")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Export external links as well"))
+                    .group(ArgGroup::with_name("export-graph-source")
+                           .args(&["module", "id", "stdin"])
+                           .multiple(true)
+                           .required(true))
+                    )
+        .subcommand(SubCommand::with_name("check")
+                    .about("Find and repair links which are not symmetric or point to entries which do not exist")
+                    .version("0.1")
+                    .arg(Arg::with_name("module")
+                         .long("module")
+                         .short("m")
+                         .takes_value(true)
+                         .required(false)
+                         .multiple(true)
+                         .help("Only check the entries of this module (default: all entries)")
+                         .value_name("MODULE"))
+                    .arg(Arg::with_name("dry-run")
+                         .long("dry-run")
+                         .short("n")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Only report the problems, do not repair them"))
+                    .arg(Arg::with_name("remove")
+                         .long("remove")
+                         .short("r")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Repair links and relations which are not symmetric by removing them, instead of adding them to the other entry"))
+                    )
         .subcommand(SubCommand::with_name("external")
                     .about("Add and remove external links")
                     .version("0.1")
+                    .setting(AppSettings::SubcommandsNegateReqs)
 
                     .arg(Arg::with_name("id")
                          .long("id")
                          .short("i")
                          .takes_value(true)
-                         .required(true)
+                         .required_unless("stdin")
                          .help("Modify external link of this entry")
                          .value_name("ENTRY"))
 
@@ -99,12 +254,29 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                          .help("Add this URI as external link")
                          .value_name("URI"))
 
+                    .arg(Arg::with_name("title")
+                         .long("title")
+                         .takes_value(true)
+                         .required(false)
+                         .requires("add")
+                         .help("Title of the added link")
+                         .value_name("TITLE"))
+
+                    .arg(Arg::with_name("description")
+                         .long("description")
+                         .takes_value(true)
+                         .required(false)
+                         .requires("add")
+                         .help("Description of the added link")
+                         .value_name("DESCRIPTION"))
+
                     .arg(Arg::with_name("remove")
                          .long("remove")
                          .short("r")
                          .takes_value(true)
-                         .required(true)
-                         .help("Remove one external link"))
+                         .required(false)
+                         .help("Remove one external link")
+                         .value_name("URI"))
 
                     .arg(Arg::with_name("set")
                          .long("set")
@@ -121,9 +293,37 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                          .required(false)
                          .help("List external links"))
 
+                    .arg(Arg::with_name("stdin")
+                         .long("stdin")
+                         .takes_value(true)
+                         .required(false)
+                         .conflicts_with("id")
+                         .possible_values(&["add", "remove"])
+                         .help("Read 'ENTRY<TAB>URI' lines from stdin and add or remove all these links")
+                         .value_name("OPERATION"))
+
                     .group(ArgGroup::with_name("external-link-group")
-                           .args(&["add", "remove", "set", "list"])
+                           .args(&["add", "remove", "set", "list", "stdin"])
                            .required(true))
 
+                    .subcommand(SubCommand::with_name("check")
+                                .about("Check whether the external links in the store are still reachable")
+                                .version("0.1")
+                                .arg(Arg::with_name("timeout")
+                                     .long("timeout")
+                                     .short("t")
+                                     .takes_value(true)
+                                     .required(false)
+                                     .help("Timeout for connecting and for each request in seconds (default: 10)")
+                                     .value_name("SECS"))
+                                .arg(Arg::with_name("jobs")
+                                     .long("jobs")
+                                     .short("j")
+                                     .takes_value(true)
+                                     .required(false)
+                                     .help("Number of requests to run at once (default: 4)")
+                                     .value_name("N"))
+                                )
+
                     )
 }
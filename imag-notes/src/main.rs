@@ -27,16 +27,23 @@ extern crate libimagnotes;
 extern crate libimagrt;
 extern crate libimagentryedit;
 extern crate libimagentrytag;
+extern crate libimagentrylist;
+extern crate libimagentrylistdata;
 extern crate libimagerror;
 extern crate libimagutil;
 
 use std::process::exit;
 
+use clap::ArgMatches;
 use itertools::Itertools;
 
 use libimagentryedit::edit::Edit;
+use libimagentrylist::cli::{get_format, list_entries_with_format};
+use libimagentrylist::format::ListFormat;
+use libimagentrylistdata::TagsAndLinks;
 use libimagrt::runtime::Runtime;
 use libimagrt::setup::generate_runtime_setup;
+use libimagentrytag::alias::load_aliases;
 use libimagnotes::note::Note;
 use libimagerror::trace::{MapErrTrace, trace_error};
 use libimagutil::info_result::*;
@@ -51,6 +58,9 @@ fn main() {
                                     "Note taking helper",
                                     build_ui);
 
+    // The tag aliases apply to the tags of all modules
+    load_aliases(rt.config().map(|c| &**c));
+
     rt.cli()
         .subcommand_name()
         .map(|name| {
@@ -112,6 +122,11 @@ fn edit_entry(rt: &Runtime, name: String) -> bool {
 fn list(rt: &Runtime) {
     use std::cmp::Ordering;
 
+    let scmd = rt.cli().subcommand_matches("list").unwrap(); // safe, we checked in main()
+    if get_format(scmd).is_some() {
+        return list_with_format(rt, scmd);
+    }
+
     Note::all_notes(rt.store())
         .map_err_trace_exit(1)
         .map(|iter| {
@@ -134,3 +149,21 @@ fn list(rt: &Runtime) {
         .ok();
 }
 
+/// List the notes in the format passed with `--format`, ordered by their id
+fn list_with_format(rt: &Runtime, scmd: &ArgMatches) {
+    let mut ids = rt.store()
+        .retrieve_for_module("notes")
+        .map_err_trace_exit(1)
+        .map(|ids| ids.collect::<Vec<_>>())
+        .unwrap_or_else(|_| vec![]);
+    ids.sort_by(|a, b| a.local().cmp(b.local()));
+
+    let entries = ids
+        .into_iter()
+        .filter_map(|id| rt.store().get(id).map_err_trace().ok().and_then(|e| e));
+
+    let config = rt.config().map(|c| c.config());
+    list_entries_with_format(scmd, config, &TagsAndLinks, ListFormat::Line, entries)
+        .map_err_trace_exit(1)
+        .ok();
+}
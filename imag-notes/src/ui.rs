@@ -20,6 +20,7 @@
 use clap::{Arg, App, SubCommand};
 
 use libimagentrytag::ui::tag_argument;
+use libimagentrylist::cli::{list_fields_arg, list_format_arg, list_with_content_arg};
 
 pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
     app
@@ -68,6 +69,10 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
 
         .subcommand(SubCommand::with_name("list")
                    .about("List Notes")
-                   .version("0.1"))
+                   .version("0.1")
+                   .arg(list_format_arg())
+                   .arg(list_fields_arg())
+                   .arg(list_with_content_arg())
+                   )
 
 }
@@ -39,9 +39,11 @@ extern crate clap;
 
 extern crate libimagstore;
 extern crate libimagrt;
+extern crate libimagentrytag;
 extern crate libimagref;
 extern crate libimagerror;
 extern crate libimagentrylist;
+extern crate libimagentrylistdata;
 extern crate libimaginteraction;
 extern crate libimagutil;
 
@@ -52,8 +54,9 @@ use std::path::PathBuf;
 
 use libimagref::reference::Ref;
 use libimagref::flags::RefFlags;
-use libimagerror::trace::trace_error;
+use libimagerror::trace::{MapErrTrace, trace_error};
 use libimagrt::setup::generate_runtime_setup;
+use libimagentrytag::alias::load_aliases;
 use libimagrt::runtime::Runtime;
 
 fn main() {
@@ -61,6 +64,9 @@ fn main() {
                                     &version!()[..],
                                     "Reference files outside of the store",
                                     build_ui);
+
+    // The tag aliases apply to the tags of all modules
+    load_aliases(rt.config().map(|c| &**c));
     rt.cli()
         .subcommand_name()
         .map(|name| {
@@ -117,7 +123,10 @@ fn remove(rt: &Runtime) {
 fn list(rt: &Runtime) {
     use std::process::exit;
 
+    use libimagentrylist::cli::{get_format, list_entries_with_format};
+    use libimagentrylist::format::ListFormat;
     use libimagentrylist::lister::Lister;
+    use libimagentrylistdata::TagsAndLinks;
     use libimagref::lister::RefLister;
 
     let cmd                      = rt.cli().subcommand_matches("list").unwrap();
@@ -142,6 +151,15 @@ fn list(rt: &Runtime) {
         }
     };
 
+    if get_format(cmd).is_some() {
+        let config = rt.config().map(|c| c.config());
+        let entries = iter.map(|e| e.into());
+        list_entries_with_format(cmd, config, &TagsAndLinks, ListFormat::Line, entries)
+            .map_err_trace_exit(1)
+            .ok();
+        return;
+    }
+
     RefLister::new()
         .check_dead(do_check_dead)
         .check_changed(do_check_changed)
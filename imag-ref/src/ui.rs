@@ -20,6 +20,7 @@
 use clap::{Arg, App, SubCommand};
 
 use libimagutil::cli_validators::is_existing_path;
+use libimagentrylist::cli::{list_fields_arg, list_format_arg, list_with_content_arg};
 
 pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
     app
@@ -89,5 +90,11 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                          .short("P")
                          .help("Check whether the permissions of the referenced file changed"))
 
+                    .arg(list_format_arg()
+                         .conflicts_with_all(&["check-dead", "check-changed", "check-changed-content",
+                                               "check-changed-permissions"]))
+                    .arg(list_fields_arg())
+                    .arg(list_with_content_arg())
+
                     )
 }
@@ -0,0 +1,205 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+extern crate clap;
+#[macro_use] extern crate log;
+extern crate semver;
+#[macro_use] extern crate version;
+
+extern crate libimagrt;
+extern crate libimagentrytag;
+extern crate libimagentrylist;
+extern crate libimagentrylistdata;
+extern crate libimagsearch;
+extern crate libimagstore;
+extern crate libimagerror;
+extern crate libimagutil;
+
+use libimagrt::runtime::Runtime;
+use libimagrt::setup::generate_runtime_setup;
+use libimagentrytag::alias::load_aliases;
+use libimagentrylist::cli::{get_fields, get_format, get_template, list_with_content};
+use libimagentrylist::listers::template::TemplateLister;
+use libimagentrylist::pipeline::Pipeline;
+use libimagentrylistdata::TagsAndLinks;
+use libimagsearch::search::{SavedSearch, SearchOptions};
+use libimagstore::toml_ext::TomlValueExt;
+use libimagerror::trace::{MapErrTrace, trace_error, trace_error_exit};
+use libimagutil::info_result::*;
+use libimagutil::warn_exit::warn_exit;
+
+mod ui;
+use ui::build_ui;
+
+fn main() {
+    let rt = generate_runtime_setup("imag-search",
+                                    &version!()[..],
+                                    "Save and run searches",
+                                    build_ui);
+
+    // The tag aliases apply to the tags of all modules
+    load_aliases(rt.config().map(|c| &**c));
+
+    rt.cli()
+        .subcommand_name()
+        .map(|name| {
+            debug!("Call: {}", name);
+            match name {
+                "save"   => save(&rt),
+                "run"    => run(&rt),
+                "show"   => show(&rt),
+                "delete" => delete(&rt),
+                "list"   => list(&rt),
+                _        => {
+                    debug!("Unknown command"); // More error handling
+                },
+            };
+        });
+}
+
+fn name_from_cli(rt: &Runtime, subcmd: &str) -> String {
+    rt.cli().subcommand_matches(subcmd).unwrap().value_of("name").map(String::from).unwrap()
+}
+
+fn get_search<'a>(rt: &'a Runtime, subcmd: &str) -> SavedSearch<'a> {
+    let name = name_from_cli(rt, subcmd);
+    match SavedSearch::get(rt.store(), name.clone()) {
+        Ok(Some(search)) => search,
+        Ok(None)         => warn_exit(&format!("No saved search '{}'", name), 1),
+        Err(e)           => trace_error_exit(&e, 1),
+    }
+}
+
+fn save(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("save").unwrap();
+
+    let limit = match scmd.value_of("limit").map(|l| l.parse::<usize>()) {
+        None         => None,
+        Some(Ok(l))  => Some(l),
+        Some(Err(_)) => warn_exit("--limit must be a number", 1),
+    };
+
+    let opts = SearchOptions {
+        module: scmd.value_of("module").map(String::from),
+        sort: scmd.value_of("sort").map(String::from),
+        reverse: scmd.is_present("reverse"),
+        limit: limit,
+        fields: scmd.values_of("field").map(|fs| fs.map(String::from).collect()).unwrap_or_else(Vec::new),
+    };
+
+    let name   = name_from_cli(rt, "save");
+    let filter = String::from(scmd.value_of("filter").unwrap()); // enforced by clap
+
+    SavedSearch::new(rt.store(), name, filter, opts)
+        .map_err_trace_exit(1)
+        .map_info_str("Ok")
+        .ok();
+}
+
+fn run(rt: &Runtime) {
+    let search  = get_search(rt, "run");
+    let opts    = search.get_options().map_err_trace_exit(1).unwrap();
+    let results = search.run(rt.store()).map_err_trace_exit(1).unwrap();
+    let scmd    = rt.cli().subcommand_matches("run").unwrap(); // safe, we checked in main()
+
+    let template = get_template(scmd, rt.config().map(|c| c.config())).map_err_trace_exit(1).unwrap();
+    let format   = get_format(scmd);
+
+    if template.is_some() || format.is_some() {
+        // The listers work on entries from the store, not on the copies the search returns. The
+        // search already sorted and limited the results, so the pipeline has nothing to do.
+        let mut fields = get_fields(scmd);
+        if fields.is_empty() {
+            fields = opts.fields.clone();
+        }
+
+        let entries = results
+            .iter()
+            .filter_map(|entry| match rt.store().get(entry.get_location().clone()) {
+                Ok(fle) => fle,
+                Err(e)  => {
+                    trace_error(&e);
+                    None
+                },
+            });
+
+        let with_content = scmd.is_present(list_with_content());
+        let listed = match (template, format) {
+            (Some(template), _)  => {
+                Pipeline::new().list(&TemplateLister::new(template, &TagsAndLinks), entries)
+            },
+            (None, Some(format)) => format.list(&Pipeline::new(), fields, with_content, entries),
+            (None, None)         => unreachable!(),
+        };
+        listed.map_err_trace_exit(1).ok();
+        return;
+    }
+
+    for entry in results {
+        let id = match entry.get_location().clone().without_base().to_str() {
+            Ok(id) => id,
+            Err(e) => {
+                trace_error(&e);
+                continue;
+            },
+        };
+
+        let fields = opts.fields
+            .iter()
+            .map(|f| match entry.get_header().read(f) {
+                Ok(Some(v)) => format!("{}", v),
+                _           => String::from("-"),
+            })
+            .collect::<Vec<_>>();
+
+        if fields.is_empty() {
+            println!("{}", id);
+        } else {
+            println!("{}\t{}", id, fields.join("\t"));
+        }
+    }
+}
+
+fn show(rt: &Runtime) {
+    let search = get_search(rt, "show");
+    let filter = search.get_filter().map_err_trace_exit(1).unwrap();
+    let opts   = search.get_options().map_err_trace_exit(1).unwrap();
+
+    println!("filter:  {}", filter);
+    println!("module:  {}", opts.module.unwrap_or_else(|| String::from("(all)")));
+    println!("sort:    {}{}", opts.sort.unwrap_or_else(|| String::from("-")),
+             if opts.reverse { " (reversed)" } else { "" });
+    println!("limit:   {}", opts.limit.map(|l| l.to_string()).unwrap_or_else(|| String::from("-")));
+    println!("fields:  {}", opts.fields.join(", "));
+}
+
+fn delete(rt: &Runtime) {
+    SavedSearch::delete(rt.store(), name_from_cli(rt, "delete"))
+        .map_err_trace()
+        .map_info_str("Ok")
+        .ok();
+}
+
+fn list(rt: &Runtime) {
+    let mut names = SavedSearch::all_names(rt.store()).map_err_trace_exit(1).unwrap();
+    names.sort();
+    for name in names {
+        println!("{}", name);
+    }
+}
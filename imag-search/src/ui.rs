@@ -0,0 +1,116 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use clap::{Arg, App, SubCommand};
+
+use libimagentrylist::cli::{list_fields_arg, list_format_arg, list_template_arg, list_with_content_arg};
+
+pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app
+        .subcommand(SubCommand::with_name("save")
+                   .about("Save a search")
+                   .version("0.1")
+                   .arg(Arg::with_name("name")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Save the search with this name")
+                        .value_name("NAME"))
+                   .arg(Arg::with_name("filter")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .help("The filter expression, for example 'tag:work and header.note.name ~ \"^meet\"'")
+                        .value_name("EXPR"))
+                   .arg(Arg::with_name("module")
+                        .long("module")
+                        .short("m")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Only search the entries of this module")
+                        .value_name("MODULE"))
+                   .arg(Arg::with_name("sort")
+                        .long("sort")
+                        .short("s")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Sort the results by this header field")
+                        .value_name("FIELD"))
+                   .arg(Arg::with_name("reverse")
+                        .long("reverse")
+                        .short("r")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Sort in descending order"))
+                   .arg(Arg::with_name("limit")
+                        .long("limit")
+                        .short("l")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Only show this many results")
+                        .value_name("N"))
+                   .arg(Arg::with_name("field")
+                        .long("field")
+                        .short("f")
+                        .takes_value(true)
+                        .required(false)
+                        .multiple(true)
+                        .help("Print this header field for each result")
+                        .value_name("FIELD"))
+                   )
+
+        .subcommand(SubCommand::with_name("run")
+                   .about("Run a saved search")
+                   .version("0.1")
+                   .arg(Arg::with_name("name")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Run the search with this name")
+                        .value_name("NAME"))
+                   .arg(list_format_arg())
+                   .arg(list_fields_arg())
+                   .arg(list_with_content_arg())
+                   .arg(list_template_arg()))
+
+        .subcommand(SubCommand::with_name("show")
+                   .about("Show the filter and options of a saved search")
+                   .version("0.1")
+                   .arg(Arg::with_name("name")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Show the search with this name")
+                        .value_name("NAME")))
+
+        .subcommand(SubCommand::with_name("delete")
+                   .about("Delete a saved search")
+                   .version("0.1")
+                   .arg(Arg::with_name("name")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Delete the search with this name")
+                        .value_name("NAME")))
+
+        .subcommand(SubCommand::with_name("list")
+                   .about("List saved searches")
+                   .version("0.1"))
+
+}
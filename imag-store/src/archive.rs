@@ -0,0 +1,340 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Reading and writing archives of store entries
+//!
+//! Two formats are supported:
+//!
+//! * JSON-lines, where each line is an object with the keys `id`, `header` and `content`. As JSON
+//!   has no datetime type, TOML datetimes are written as objects with the single key `$datetime`.
+//! * tar, where each entry is stored as a file at its id, in the same format it has in the store.
+//!
+//! Ids read from an archive must be relative paths without `..` components, so importing an
+//! archive cannot write outside of the store.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Component, PathBuf};
+use std::result::Result as RResult;
+
+use clap::ArgMatches;
+use rustc_serialize::json::Json;
+use tar::{Archive, Builder, Header};
+use toml::Value;
+
+use libimagstore::store::Entry;
+use libimagstore::storeid::StoreId;
+use libimagerror::into::IntoError;
+
+use error::StoreError;
+use error::StoreErrorKind as SEK;
+use error::MapErrInto;
+
+pub type Result<T> = RResult<T, StoreError>;
+
+/// The key of the JSON object a TOML datetime is written as
+const DATETIME_KEY : &'static str = "$datetime";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    JsonLines,
+    Tar,
+}
+
+impl Format {
+
+    /// Get the format from the "format" argument, defaults to JSON-lines
+    pub fn from_matches(m: &ArgMatches) -> Format {
+        match m.value_of("format") {
+            Some("tar") => Format::Tar,
+            _           => Format::JsonLines,
+        }
+    }
+
+}
+
+/// Write `entries` to `out`, returning the number of written entries
+pub fn write_entries<W, I>(format: Format, out: W, entries: I) -> Result<usize>
+    where W: Write,
+          I: Iterator<Item = Entry>
+{
+    match format {
+        Format::JsonLines => write_jsonl(out, entries),
+        Format::Tar       => write_tar(out, entries),
+    }
+}
+
+/// Read all entries from `input`
+///
+/// The returned entries are not associated with any store, their location is baseless.
+pub fn read_entries<R: Read>(format: Format, input: R) -> Result<Vec<Entry>> {
+    match format {
+        Format::JsonLines => read_jsonl(input),
+        Format::Tar       => read_tar(input),
+    }
+}
+
+/// Build the id of an entry read from an archive
+///
+/// Fails with `InvalidId` if the path is empty or has components other than plain names, like
+/// `..` or a root.
+pub fn archive_id(path: PathBuf) -> Result<StoreId> {
+    let is_normal = |c: Component| match c {
+        Component::Normal(_) => true,
+        _                    => false,
+    };
+
+    if path.components().next().is_none() || !path.components().all(is_normal) {
+        warn!("Refusing entry with id {:?}", path);
+        return Err(SEK::InvalidId.into_error());
+    }
+
+    StoreId::new_baseless(path).map_err_into(SEK::InvalidId)
+}
+
+fn write_jsonl<W: Write, I: Iterator<Item = Entry>>(mut out: W, entries: I) -> Result<usize> {
+    let mut n = 0;
+    for entry in entries {
+        let mut obj = BTreeMap::new();
+        obj.insert(String::from("id"), Json::String(format!("{}", entry.get_location())));
+        obj.insert(String::from("header"), toml_to_json(entry.get_header()));
+        obj.insert(String::from("content"), Json::String(entry.get_content().clone()));
+
+        try!(writeln!(out, "{}", Json::Object(obj)).map_err_into(SEK::IoError));
+        n += 1;
+    }
+    Ok(n)
+}
+
+fn write_tar<W: Write, I: Iterator<Item = Entry>>(out: W, entries: I) -> Result<usize> {
+    let mut builder = Builder::new(out);
+    let mut n = 0;
+
+    for entry in entries {
+        let data = entry.to_str();
+        let mut header = Header::new_gnu();
+        try!(header.set_path(entry.get_location().local()).map_err_into(SEK::IoError));
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+
+        try!(builder.append(&header, data.as_bytes()).map_err_into(SEK::IoError));
+        n += 1;
+    }
+
+    builder.finish().map_err_into(SEK::IoError).map(|_| n)
+}
+
+fn read_jsonl<R: Read>(input: R) -> Result<Vec<Entry>> {
+    let mut entries = vec![];
+
+    for line in BufReader::new(input).lines() {
+        let line = try!(line.map_err_into(SEK::IoError));
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let json = try!(Json::from_str(&line).map_err_into(SEK::FormatError));
+        let obj  = try!(json.as_object().ok_or(SEK::FormatError.into_error()));
+
+        let id = try!(obj.get("id")
+                      .and_then(Json::as_string)
+                      .ok_or(SEK::FormatError.into_error()));
+        let id = try!(archive_id(PathBuf::from(id)));
+
+        let header = match obj.get("header") {
+            Some(h) => try!(json_to_toml(h)),
+            None    => Entry::default_header(),
+        };
+
+        let content = obj.get("content").and_then(Json::as_string).unwrap_or("");
+
+        let mut entry = Entry::new(id);
+        *entry.get_header_mut()  = header;
+        *entry.get_content_mut() = String::from(content);
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+fn read_tar<R: Read>(input: R) -> Result<Vec<Entry>> {
+    let mut archive = Archive::new(input);
+    let mut entries = vec![];
+
+    for file in try!(archive.entries().map_err_into(SEK::IoError)) {
+        let mut file = try!(file.map_err_into(SEK::IoError));
+        if !file.header().entry_type().is_file() {
+            continue;
+        }
+
+        let id = try!(file.path().map(|p| p.into_owned()).map_err_into(SEK::IoError));
+        let id = try!(archive_id(id));
+
+        let mut s = String::new();
+        try!(file.read_to_string(&mut s).map_err_into(SEK::IoError));
+
+        entries.push(try!(Entry::from_str(id, &s).map_err_into(SEK::FormatError)));
+    }
+
+    Ok(entries)
+}
+
+fn toml_to_json(v: &Value) -> Json {
+    match *v {
+        Value::String(ref s)   => Json::String(s.clone()),
+        Value::Integer(i)      => Json::I64(i),
+        Value::Float(f)        => Json::F64(f),
+        Value::Boolean(b)      => Json::Boolean(b),
+        Value::Datetime(ref s) => {
+            let mut obj = BTreeMap::new();
+            obj.insert(String::from(DATETIME_KEY), Json::String(s.clone()));
+            Json::Object(obj)
+        },
+        Value::Array(ref a)    => Json::Array(a.iter().map(toml_to_json).collect()),
+        Value::Table(ref t)    => {
+            Json::Object(t.iter().map(|(k, v)| (k.clone(), toml_to_json(v))).collect())
+        },
+    }
+}
+
+fn json_to_toml(j: &Json) -> Result<Value> {
+    match *j {
+        Json::I64(i)         => Ok(Value::Integer(i)),
+        Json::U64(u)         => if u <= i64::max_value() as u64 {
+            Ok(Value::Integer(u as i64))
+        } else {
+            warn!("Integer {} is too large for a TOML integer", u);
+            Err(SEK::FormatError.into_error())
+        },
+        Json::F64(f)         => Ok(Value::Float(f)),
+        Json::String(ref s)  => Ok(Value::String(s.clone())),
+        Json::Boolean(b)     => Ok(Value::Boolean(b)),
+        Json::Array(ref a)   => {
+            a.iter().map(json_to_toml).collect::<Result<Vec<_>>>().map(Value::Array)
+        },
+        Json::Object(ref o)  => {
+            if o.len() == 1 {
+                if let Some(dt) = o.get(DATETIME_KEY).and_then(Json::as_string) {
+                    return Ok(Value::Datetime(String::from(dt)));
+                }
+            }
+
+            let mut table = BTreeMap::new();
+            for (k, v) in o.iter() {
+                table.insert(k.clone(), try!(json_to_toml(v)));
+            }
+            Ok(Value::Table(table))
+        },
+        Json::Null => Err(SEK::FormatError.into_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use toml::Parser;
+    use toml::Value;
+
+    use libimagstore::store::Entry;
+    use libimagstore::storeid::StoreId;
+
+    use error::StoreErrorKind as SEK;
+    use super::{Format, archive_id, read_entries, write_entries};
+
+    fn entry(id: &str, header: &str, content: &str) -> Entry {
+        let id        = StoreId::new_baseless(PathBuf::from(id)).unwrap();
+        let mut entry = Entry::new(id);
+        *entry.get_header_mut()  = Value::Table(Parser::new(header).parse().unwrap());
+        *entry.get_content_mut() = String::from(content);
+        entry
+    }
+
+    fn roundtrip(format: Format, entries: Vec<Entry>) -> Vec<Entry> {
+        let mut buf = vec![];
+        let n = write_entries(format, &mut buf, entries.into_iter()).unwrap();
+        let read = read_entries(format, &buf[..]).unwrap();
+        assert_eq!(n, read.len());
+        read
+    }
+
+    const HEADER : &'static str = "[imag]\nversion = \"0.2.0\"\nlinks = []\n\
+                                   [note]\nname = \"a\"\nprio = 1\nratio = 0.5\n\
+                                   due = 2016-10-01T12:00:00Z\ntags = [\"x\", \"y\"]";
+
+    #[test]
+    fn test_jsonl_roundtrip() {
+        let entries = vec![entry("note/a", HEADER, "content\nwith lines")];
+        let read    = roundtrip(Format::JsonLines, entries.clone());
+
+        assert_eq!(read[0].get_location(), entries[0].get_location());
+        assert_eq!(read[0].get_header(), entries[0].get_header());
+        assert_eq!(read[0].get_content(), entries[0].get_content());
+    }
+
+    #[test]
+    fn test_jsonl_roundtrip_keeps_datetimes() {
+        let read = roundtrip(Format::JsonLines, vec![entry("note/a", HEADER, "")]);
+
+        match read[0].get_header().lookup("note.due") {
+            Some(&Value::Datetime(ref s)) => assert_eq!(s, "2016-10-01T12:00:00Z"),
+            other => panic!("Expected datetime, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tar_roundtrip() {
+        let entries = vec![entry("note/a", HEADER, "a"), entry("note/sub/b", HEADER, "b")];
+        let read    = roundtrip(Format::Tar, entries.clone());
+
+        assert_eq!(read.len(), 2);
+        for (r, e) in read.iter().zip(entries.iter()) {
+            assert_eq!(r.get_location(), e.get_location());
+            assert_eq!(r.get_header(), e.get_header());
+            assert_eq!(r.get_content(), e.get_content());
+        }
+    }
+
+    #[test]
+    fn test_jsonl_too_large_integer_fails() {
+        let line = "{\"id\": \"note/a\", \"header\": {\"n\": 18446744073709551615}}";
+        let res  = read_entries(Format::JsonLines, line.as_bytes());
+        assert_eq!(res.unwrap_err().err_type(), SEK::FormatError);
+    }
+
+    #[test]
+    fn test_archive_id_rejects_escaping_paths() {
+        for id in &["../../.ssh/authorized_keys", "note/../../x", "/etc/passwd", "./note/a", ""] {
+            let res = archive_id(PathBuf::from(id));
+            assert!(res.is_err(), "Accepted {:?}", id);
+            assert_eq!(res.unwrap_err().err_type(), SEK::InvalidId);
+        }
+
+        assert!(archive_id(PathBuf::from("note/a")).is_ok());
+    }
+
+    #[test]
+    fn test_jsonl_rejects_escaping_id() {
+        let line = "{\"id\": \"../../.ssh/authorized_keys\", \"content\": \"key\"}";
+        let res  = read_entries(Format::JsonLines, line.as_bytes());
+        assert_eq!(res.unwrap_err().err_type(), SEK::InvalidId);
+    }
+
+}
@@ -56,7 +56,7 @@ pub fn create(rt: &Runtime) {
                 exit(1);
             }
 
-            let store_path = rt.store().path().clone();
+            let store_path = rt.store().primary().path().clone();
             let path = match StoreId::new(Some(store_path), PathBuf::from(path.unwrap())) {
                 Err(e) => trace_error_exit(&e, 1),
                 Ok(o) => o,
@@ -0,0 +1,100 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::env;
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+
+use libimagrt::runtime::Runtime;
+use libimagstore::crypt::{Encryption, Key, KeySource, generate_salt, rotate_keys};
+use libimagerror::trace::trace_error_exit;
+use libimagutil::warn_exit::warn_exit;
+
+pub fn crypt(rt: &Runtime) {
+    rt.cli()
+        .subcommand_matches("crypt")
+        .map(|scmd| {
+            match scmd.subcommand() {
+                ("rotate", Some(matches)) => rotate(rt, matches),
+                _ => warn_exit("No subcommand for 'crypt'. Will exit now", 1),
+            }
+        });
+}
+
+/// Re-encrypt all encrypted entries of the store with the configured key
+///
+/// The configuration has to contain the new key already, the old key is passed on the
+/// commandline. So the configuration always matches the key the store is encrypted with once the
+/// rotation finished. This also applies the list of encrypted modules from the configuration, so
+/// entries of modules which were added to the list are encrypted and entries of removed modules are
+/// decrypted.
+///
+/// An interrupted rotation is discarded or finished when this is run again with the same keys.
+fn rotate(rt: &Runtime, matches: &ArgMatches) {
+    let store_path = rt.store().primary().path();
+
+    let (source, modules) = match rt.store().primary().encryption() {
+        Some(enc) => match enc.source() {
+            Some(source) => (source.clone(), enc.modules().clone()),
+            None => warn_exit("Encryption of the store has no configured key. Will exit now", 1),
+        },
+        None => warn_exit("Encryption is not configured for the store. Will exit now", 1),
+    };
+
+    // If no old key is passed, the store must not contain encrypted entries yet
+    let old_source = match (matches.value_of("old-keyfile"), matches.value_of("old-passphrase-env")) {
+        (Some(path), _) => Some(KeySource::KeyFile(PathBuf::from(path))),
+        (None, Some(var)) => Some(KeySource::PassphraseEnv(String::from(var))),
+        (None, None) => None,
+    };
+    let old_key = old_source.map(|old| match old.load(store_path) {
+        Err(e) => trace_error_exit(&e, 1),
+        Ok(key) => key,
+    });
+
+    // For passphrases, the new key is derived with a new salt, which replaces the salt of the
+    // store once all entries are re-encrypted
+    let mut new_salt = None;
+    let new_key = match source {
+        KeySource::KeyFile(ref path) => match Key::from_keyfile(path) {
+            Err(e) => trace_error_exit(&e, 1),
+            Ok(key) => key,
+        },
+        KeySource::PassphraseEnv(ref var) => {
+            let passphrase = match env::var(var) {
+                Err(e) => trace_error_exit(&e, 1),
+                Ok(p) => p,
+            };
+            let salt = match generate_salt() {
+                Err(e) => trace_error_exit(&e, 1),
+                Ok(s) => s,
+            };
+            let key = Key::from_passphrase(&passphrase, &salt);
+            new_salt = Some(salt);
+            key
+        },
+    };
+
+    let new = Encryption::new(new_key, modules);
+    match rotate_keys(store_path, old_key.as_ref(), &new, new_salt.as_ref().map(|s| &s[..])) {
+        Err(e) => trace_error_exit(&e, 1),
+        Ok(n)  => info!("Re-wrote {} entries", n),
+    }
+}
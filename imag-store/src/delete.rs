@@ -32,7 +32,7 @@ pub fn delete(rt: &Runtime) {
             sub.value_of("id")
                 .map(|id| {
                     let path = PathBuf::from(id);
-                    let path = try!(StoreId::new(Some(rt.store().path().clone()), path)
+                    let path = try!(StoreId::new(Some(rt.store().primary().path().clone()), path)
                                     .map_err_trace_exit(1));
                     debug!("Deleting file at {:?}", id);
 
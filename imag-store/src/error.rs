@@ -20,10 +20,15 @@
 generate_error_module!(
     generate_error_types!(StoreError, StoreErrorKind,
         BackendError      => "Backend Error",
-        NoCommandlineCall => "No commandline call"
+        NoCommandlineCall => "No commandline call",
+        IoError           => "IO Error",
+        FormatError       => "Malformed import data",
+        InvalidId         => "Invalid entry id",
+        LinkedEntry       => "Entry with links cannot be renamed"
     );
 );
 
 pub use self::error::StoreError;
 pub use self::error::StoreErrorKind;
+pub use self::error::MapErrInto;
 
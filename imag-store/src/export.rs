@@ -0,0 +1,162 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::fs::{File, read_dir};
+use std::io::{stdin, stdout, BufRead, Write};
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+
+use libimagrt::runtime::Runtime;
+use libimagstore::storeid::StoreId;
+use libimagerror::trace::{trace_error, trace_error_exit};
+
+use archive::{Format, Result, write_entries};
+use error::StoreErrorKind as SEK;
+use error::MapErrInto;
+
+pub fn export(rt: &Runtime) {
+    rt.cli()
+        .subcommand_matches("export")
+        .map(|scmd| {
+            let format = Format::from_matches(scmd);
+            let ids = match ids_to_export(rt, scmd) {
+                Err(e) => trace_error_exit(&e, 1),
+                Ok(ids) => ids,
+            };
+
+            // Archives are not encrypted, so entries of encrypted modules are only exported if
+            // this is explicitely requested
+            let decrypt    = scmd.is_present("decrypt");
+            let encryption = rt.store().primary().encryption();
+            let ids : Vec<StoreId> = ids
+                .into_iter()
+                .filter(|id| match encryption {
+                    Some(enc) if !decrypt && enc.encrypts(id) => {
+                        warn!("Skipping encrypted entry {}, use --decrypt to export it in plain text", id);
+                        false
+                    },
+                    _ => true,
+                })
+                .collect();
+            debug!("Exporting {} entries as {:?}", ids.len(), format);
+
+            let out : Box<Write> = match scmd.value_of("file") {
+                None | Some("-") => Box::new(stdout()),
+                Some(path) => match File::create(path) {
+                    Err(e) => trace_error_exit(&e, 1),
+                    Ok(f) => Box::new(f),
+                },
+            };
+
+            let entries = ids
+                .into_iter()
+                .filter_map(|id| rt.store().retrieve_copy(id).map_err(|e| trace_error(&e)).ok());
+
+            match write_entries(format, out, entries) {
+                Ok(n)  => info!("Exported {} entries", n),
+                Err(e) => trace_error_exit(&e, 1),
+            }
+        });
+}
+
+/// Collect the ids to export
+///
+/// These are the ids passed via `--id` or `--stdin`, or, if there are none, all entries of the
+/// modules passed via `--module`, or of all modules in the store.
+fn ids_to_export(rt: &Runtime, scmd: &ArgMatches) -> Result<Vec<StoreId>> {
+    let mut ids = vec![];
+
+    if let Some(values) = scmd.values_of("id") {
+        for id in values {
+            ids.push(try!(parse_id(rt, id)));
+        }
+    }
+
+    if scmd.is_present("stdin") {
+        let stdin = stdin();
+        for line in stdin.lock().lines() {
+            let line = try!(line.map_err_into(SEK::IoError));
+            let line = line.trim();
+            if !line.is_empty() {
+                ids.push(try!(parse_id(rt, line)));
+            }
+        }
+    }
+
+    if !ids.is_empty() {
+        return Ok(ids);
+    }
+
+    let modules = match scmd.values_of("module") {
+        Some(modules) => modules.map(String::from).collect(),
+        None          => try!(all_modules(rt)),
+    };
+
+    for module in modules {
+        let iter = try!(rt.store().retrieve_for_module(&module).map_err_into(SEK::BackendError));
+        ids.extend(iter.filter(is_entry_file));
+    }
+
+    Ok(ids)
+}
+
+/// Parse an id, which may be relative to the store or a full path into the store
+fn parse_id(rt: &Runtime, s: &str) -> Result<StoreId> {
+    let path = PathBuf::from(s);
+    let id   = if path.is_absolute() {
+        StoreId::from_full_path(rt.store().primary().path(), path)
+    } else {
+        StoreId::new_baseless(path)
+    };
+
+    id.map_err_into(SEK::BackendError)
+}
+
+/// Get the names of all modules on all mounts of the store
+fn all_modules(rt: &Runtime) -> Result<Vec<String>> {
+    let mut modules : Vec<String> = vec![];
+
+    for mount in rt.store().mounts() {
+        for dent in try!(read_dir(mount.store().path()).map_err_into(SEK::IoError)) {
+            let dent = try!(dent.map_err_into(SEK::IoError));
+            if !dent.path().is_dir() {
+                continue;
+            }
+
+            if let Some(name) = dent.file_name().to_str() {
+                if !name.starts_with('.') && !modules.iter().any(|m| m == name) {
+                    modules.push(String::from(name));
+                }
+            }
+        }
+    }
+
+    Ok(modules)
+}
+
+/// Check whether the id points to an entry file, ignoring hidden files such as the ".git"
+/// directory
+fn is_entry_file(id: &StoreId) -> bool {
+    let hidden = id.local()
+        .components()
+        .any(|c| c.as_os_str().to_str().map(|s| s.starts_with('.')).unwrap_or(false));
+
+    !hidden && id.clone().into_pathbuf().map(|p| p.is_file()).unwrap_or(false)
+}
@@ -32,7 +32,7 @@ pub fn get(rt: &Runtime) {
             scmd.value_of("id")
                 .map(|id| {
                     let path = PathBuf::from(id);
-                    let path = match StoreId::new(Some(rt.store().path().clone()), path) {
+                    let path = match StoreId::new(Some(rt.store().primary().path().clone()), path) {
                         Err(e) => trace_error_exit(&e, 1),
                         Ok(p) => p,
                     };
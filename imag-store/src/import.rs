@@ -0,0 +1,148 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::fs::File;
+use std::io::{stdin, Read};
+use std::process::exit;
+
+use libimagrt::runtime::Runtime;
+use libimagstore::access::StoreAccess;
+use libimagstore::store::Entry;
+use libimagstore::storeid::StoreId;
+use libimagentrylink::internal::InternalLinker;
+use libimagerror::into::IntoError;
+use libimagerror::trace::{trace_error, trace_error_exit};
+
+use archive::{Format, Result, read_entries};
+use error::StoreErrorKind as SEK;
+use error::MapErrInto;
+
+/// What to do if an imported entry exists in the store already
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ConflictPolicy {
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+pub fn import(rt: &Runtime) {
+    rt.cli()
+        .subcommand_matches("import")
+        .map(|scmd| {
+            let format = Format::from_matches(scmd);
+            let policy = match scmd.value_of("on-conflict") {
+                Some("overwrite") => ConflictPolicy::Overwrite,
+                Some("rename")    => ConflictPolicy::Rename,
+                _                 => ConflictPolicy::Skip,
+            };
+
+            let input : Box<Read> = match scmd.value_of("file") {
+                None | Some("-") => Box::new(stdin()),
+                Some(path) => match File::open(path) {
+                    Err(e) => trace_error_exit(&e, 1),
+                    Ok(f) => Box::new(f),
+                },
+            };
+
+            let entries = match read_entries(format, input) {
+                Err(e) => trace_error_exit(&e, 1),
+                Ok(entries) => entries,
+            };
+            debug!("Importing {} entries with policy {:?}", entries.len(), policy);
+
+            let (mut imported, mut skipped, mut failed) = (0, 0, 0);
+            for entry in entries {
+                match import_entry(rt.store(), entry, policy) {
+                    Ok(true)  => imported += 1,
+                    Ok(false) => skipped += 1,
+                    Err(e)    => {
+                        trace_error(&e);
+                        failed += 1;
+                    },
+                }
+            }
+
+            info!("Imported {} entries, skipped {}, {} failed", imported, skipped, failed);
+            if failed != 0 {
+                exit(1);
+            }
+        });
+}
+
+/// Import one entry, returns whether the entry was imported or skipped
+///
+/// New entries are created with `Store::create()`, so the create hooks are executed for them.
+///
+/// Entries with links are not renamed, as the entries they link to link back to the original id,
+/// which is the id of another entry in this store.
+fn import_entry(store: &StoreAccess, entry: Entry, policy: ConflictPolicy) -> Result<bool> {
+    try!(entry.verify().map_err_into(SEK::FormatError));
+
+    let mut id = entry.get_location().clone();
+
+    if try!(store.exists(id.clone()).map_err_into(SEK::BackendError)) {
+        match policy {
+            ConflictPolicy::Skip => {
+                info!("Entry exists, skipping: {}", id);
+                return Ok(false);
+            },
+            ConflictPolicy::Overwrite => {
+                info!("Entry exists, overwriting: {}", id);
+                let mut fle = try!(store.retrieve(id).map_err_into(SEK::BackendError));
+                *fle.get_header_mut()  = entry.get_header().clone();
+                *fle.get_content_mut() = entry.get_content().clone();
+                return store.update(fle).map(|_| true).map_err_into(SEK::BackendError);
+            },
+            ConflictPolicy::Rename => {
+                let links = try!(entry.get_internal_links().map_err_into(SEK::FormatError));
+                if links.count() != 0 {
+                    return Err(SEK::LinkedEntry.into_error());
+                }
+
+                id = try!(free_id(store, &id));
+                info!("Entry exists, importing {} as {}", entry.get_location(), id);
+            },
+        }
+    }
+
+    let mut fle = try!(store.create(id).map_err_into(SEK::BackendError));
+    *fle.get_header_mut()  = entry.get_header().clone();
+    *fle.get_content_mut() = entry.get_content().clone();
+    store.update(fle).map(|_| true).map_err_into(SEK::BackendError)
+}
+
+/// Find an id which does not exist in the store yet, by appending "-1", "-2", ... to `id`
+fn free_id(store: &StoreAccess, id: &StoreId) -> Result<StoreId> {
+    let local = id.local().clone();
+    let name  = try!(local.file_name()
+                     .and_then(|n| n.to_str())
+                     .map(String::from)
+                     .ok_or(SEK::FormatError.into_error()));
+
+    for n in 1.. {
+        let candidate = local.with_file_name(format!("{}-{}", name, n));
+        let candidate = try!(StoreId::new_baseless(candidate).map_err_into(SEK::BackendError));
+
+        if !try!(store.exists(candidate.clone()).map_err_into(SEK::BackendError)) {
+            return Ok(candidate);
+        }
+    }
+
+    unreachable!()
+}
@@ -0,0 +1,87 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::path::Component;
+
+use filters::filter::Filter;
+
+use libimagrt::runtime::Runtime;
+use libimagstore::store::StoreObject;
+use libimagstore::storeid::StoreId;
+use libimagerror::trace::{MapErrTrace, trace_error};
+use libimagentryfilter::cli::get_filter;
+use libimagentrylist::cli::{get_format, list_entries_with_format, list_template};
+use libimagentrylist::format::ListFormat;
+use libimagentrylist::lister::Lister;
+use libimagentrylist::listers::template::{Template, TemplateLister, NoTemplateData};
+use libimagentrylist::listers::tree::TreeLister;
+use libimagentrylistdata::TagsAndLinks;
+use libimagutil::warn_exit::warn_exit;
+
+pub fn ls(rt: &Runtime) {
+    let scmd   = rt.cli().subcommand_matches("ls").unwrap(); // safe, we checked in main()
+    let module = scmd.value_of("module").unwrap_or("");
+    let filter = match get_filter(scmd, rt.store()) {
+        Some(Ok(filter)) => Some(filter),
+        Some(Err(e))     => warn_exit(&format!("{}", e), 1),
+        None             => None,
+    };
+
+    let entries = rt.store()
+        .walk(module)
+        .filter_map(|obj| match obj {
+            StoreObject::Id(id)        => Some(id),
+            StoreObject::Collection(_) => None,
+        })
+        .filter(|id| !is_hidden(id))
+        .filter_map(|id| match rt.store().get(id) {
+            Ok(entry) => entry,
+            Err(e)    => {
+                trace_error(&e);
+                None
+            },
+        })
+        .filter(|entry| filter.as_ref().map(|f| f.filter(entry)).unwrap_or(true));
+
+    let result = if scmd.is_present("tree") {
+        let depth  = scmd.value_of("depth").and_then(|d| d.parse::<usize>().ok());
+        let lister = match depth {
+            Some(d) => TreeLister::new().with_max_depth(d),
+            None    => TreeLister::new(),
+        };
+        lister.list(entries)
+    } else if get_format(scmd).is_some() || scmd.is_present(list_template()) {
+        let config = rt.config().map(|c| c.config());
+        list_entries_with_format(scmd, config, &TagsAndLinks, ListFormat::Line, entries)
+    } else {
+        // Plain store ids, one per line
+        let ids = Template::parse("{id}").unwrap(); // the template is valid
+        TemplateLister::new(ids, &NoTemplateData).list(entries)
+    };
+
+    result.map_err_trace_exit(1).ok();
+}
+
+/// Whether the entry is in a hidden directory, like the `.git` directory of the store
+fn is_hidden(id: &StoreId) -> bool {
+    id.local().components().any(|c| match c {
+        Component::Normal(name) => name.to_str().map(|n| n.starts_with('.')).unwrap_or(false),
+        _                       => false,
+    })
+}
@@ -33,32 +33,53 @@
 )]
 
 extern crate clap;
+extern crate filters;
 #[macro_use] extern crate log;
+extern crate rustc_serialize;
 extern crate semver;
+extern crate tar;
 extern crate toml;
 #[macro_use] extern crate version;
 
 extern crate libimagrt;
+extern crate libimagentrytag;
+extern crate libimagentrylist;
+extern crate libimagentrylistdata;
+extern crate libimagentryfilter;
+extern crate libimagentrylink;
 extern crate libimagstore;
 extern crate libimagutil;
 #[macro_use] extern crate libimagerror;
 
 use libimagrt::setup::generate_runtime_setup;
+use libimagentrytag::alias::load_aliases;
 
+mod archive;
 mod create;
+mod crypt;
 mod delete;
 mod error;
+mod export;
 mod get;
+mod import;
+mod ls;
+mod merge;
 mod retrieve;
+mod sync;
 mod ui;
 mod update;
 mod verify;
 mod util;
 
 use create::create;
+use crypt::crypt;
 use delete::delete;
+use export::export;
 use get::get;
+use import::import;
+use ls::ls;
 use retrieve::retrieve;
+use sync::sync;
 use ui::build_ui;
 use update::update;
 use verify::verify;
@@ -69,6 +90,9 @@ fn main() {
                                     "Direct interface to the store. Use with great care!",
                                     build_ui);
 
+    // The tag aliases apply to the tags of all modules
+    load_aliases(rt.config().map(|c| &**c));
+
     rt.cli()
         .subcommand_name()
         .map_or_else(
@@ -80,9 +104,14 @@ fn main() {
                 debug!("Call: {}", name);
                 match name {
                     "create"   => create(&rt),
+                    "crypt"    => crypt(&rt),
                     "delete"   => delete(&rt),
+                    "export"   => export(&rt),
                     "get"      => get(&rt),
+                    "import"   => import(&rt),
+                    "ls"       => ls(&rt),
                     "retrieve" => retrieve(&rt),
+                    "sync"     => sync(&rt),
                     "update"   => update(&rt),
                     "verify"   => verify(&rt),
                     _ => {
@@ -0,0 +1,331 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Three-way merging of entries
+//!
+//! Headers are merged key by key, contents are merged line by line. A change on one side is taken
+//! over if the other side did not change, changes on both sides which are not equal are
+//! conflicts.
+
+use std::cmp::max;
+use std::collections::BTreeMap;
+
+use toml::Value;
+
+/// Header paths which contain arrays which are merged like sets: Elements which were added on
+/// either side are added, elements which were removed on either side are removed.
+const SET_MERGED_PATHS : &'static [&'static str] = &["imag.links", "imag.tags"];
+
+/// The header section in which both values of conflicting header fields are kept
+pub const CONFLICT_SECTION : &'static str = "conflict";
+
+/// Merge the headers `local` and `remote`, which both are derived from `base`
+///
+/// Returns the merged header and the paths of the conflicting header fields. For conflicting
+/// fields, the local value is used in place, and both values are kept in the `conflict` section,
+/// as `conflict."<path>".local` and `conflict."<path>".remote`. A value which was removed on one
+/// side is missing there.
+pub fn merge_header(base: &Value, local: &Value, remote: &Value) -> (Value, Vec<String>) {
+    let mut conflicts = vec![];
+    let mut merged = merge_value("", Some(base), Some(local), Some(remote), &mut conflicts)
+        .unwrap_or_else(|| local.clone());
+
+    if !conflicts.is_empty() {
+        let mut section = BTreeMap::new();
+        for path in conflicts.iter() {
+            let mut values = BTreeMap::new();
+            if let Some(l) = local.lookup(path) {
+                values.insert(String::from("local"), l.clone());
+            }
+            if let Some(r) = remote.lookup(path) {
+                values.insert(String::from("remote"), r.clone());
+            }
+            section.insert(path.clone(), Value::Table(values));
+        }
+
+        if let Value::Table(ref mut t) = merged {
+            t.insert(String::from(CONFLICT_SECTION), Value::Table(section));
+        }
+    }
+
+    (merged, conflicts)
+}
+
+fn merge_value(path: &str,
+               base: Option<&Value>,
+               local: Option<&Value>,
+               remote: Option<&Value>,
+               conflicts: &mut Vec<String>)
+    -> Option<Value>
+{
+    if local == remote || remote == base {
+        return local.cloned();
+    }
+    if local == base {
+        return remote.cloned();
+    }
+
+    match (local, remote) {
+        (Some(&Value::Table(ref l)), Some(&Value::Table(ref r))) => {
+            let empty = BTreeMap::new();
+            let b = match base {
+                Some(&Value::Table(ref b)) => b,
+                _ => &empty,
+            };
+
+            let mut keys : Vec<&String> = l.keys().chain(r.keys()).chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            let mut table = BTreeMap::new();
+            for key in keys {
+                let subpath = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+
+                if let Some(v) = merge_value(&subpath, b.get(key), l.get(key), r.get(key), conflicts) {
+                    table.insert(key.clone(), v);
+                }
+            }
+
+            Some(Value::Table(table))
+        },
+
+        (Some(&Value::Array(ref l)), Some(&Value::Array(ref r)))
+            if SET_MERGED_PATHS.iter().any(|p| *p == path) =>
+        {
+            let empty = vec![];
+            let b = match base {
+                Some(&Value::Array(ref b)) => b,
+                _ => &empty,
+            };
+
+            let mut merged : Vec<Value> = l.iter()
+                .filter(|v| r.contains(v) || !b.contains(v))
+                .cloned()
+                .collect();
+
+            for v in r.iter() {
+                if !b.contains(v) && !merged.contains(v) {
+                    merged.push(v.clone());
+                }
+            }
+
+            Some(Value::Array(merged))
+        },
+
+        _ => {
+            debug!("Conflict in header at '{}'", path);
+            conflicts.push(String::from(path));
+            local.cloned()
+        },
+    }
+}
+
+/// Merge the contents `local` and `remote`, which both are derived from `base`, line by line
+///
+/// Returns the merged content and whether there were conflicts. Conflicting lines are surrounded
+/// by conflict markers.
+pub fn merge_content(base: &str, local: &str, remote: &str) -> (String, bool) {
+    if local == remote || remote == base {
+        return (String::from(local), false);
+    }
+    if local == base {
+        return (String::from(remote), false);
+    }
+
+    let base   : Vec<&str> = base.split('\n').collect();
+    let local  : Vec<&str> = local.split('\n').collect();
+    let remote : Vec<&str> = remote.split('\n').collect();
+
+    let local_matches  = lcs_matches(&base, &local);
+    let remote_matches = lcs_matches(&base, &remote);
+
+    let mut merged   = vec![];
+    let mut conflict = false;
+    let (mut i, mut l, mut r) = (0, 0, 0);
+
+    loop {
+        // The next base line which is unchanged on both sides
+        let stable = (i..base.len()).find(|&j| {
+            local_matches[j].is_some() && remote_matches[j].is_some()
+        });
+
+        let (j, next_l, next_r) = match stable {
+            Some(j) => (j, local_matches[j].unwrap(), remote_matches[j].unwrap()),
+            None    => (base.len(), local.len(), remote.len()),
+        };
+
+        conflict |= merge_chunk(&base[i..j], &local[l..next_l], &remote[r..next_r], &mut merged);
+
+        if stable.is_none() {
+            break;
+        }
+
+        merged.push(base[j]);
+        i = j + 1;
+        l = next_l + 1;
+        r = next_r + 1;
+    }
+
+    (merged.join("\n"), conflict)
+}
+
+/// Merge one chunk of lines between two stable lines, returns whether the chunk is a conflict
+fn merge_chunk<'a>(base: &[&'a str],
+                   local: &[&'a str],
+                   remote: &[&'a str],
+                   out: &mut Vec<&'a str>)
+    -> bool
+{
+    if local == remote || remote == base {
+        out.extend_from_slice(local);
+        false
+    } else if local == base {
+        out.extend_from_slice(remote);
+        false
+    } else {
+        out.push("<<<<<<< local");
+        out.extend_from_slice(local);
+        out.push("=======");
+        out.extend_from_slice(remote);
+        out.push(">>>>>>> remote");
+        true
+    }
+}
+
+/// For each line in `a`, get the index of the line in `b` it is matched to in a longest common
+/// subsequence of `a` and `b`
+fn lcs_matches(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+    let (n, m) = (a.len(), b.len());
+    let mut lengths = vec![vec![0; m + 1]; n + 1];
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                max(lengths[i + 1][j], lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut matches = vec![None; n];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a[i] == b[j] {
+            matches[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod test {
+    use toml::Parser;
+    use toml::Value;
+
+    use super::{merge_content, merge_header};
+
+    fn header(s: &str) -> Value {
+        Value::Table(Parser::new(s).parse().unwrap())
+    }
+
+    #[test]
+    fn test_merge_header_independent_changes() {
+        let base   = header("[imag]\nversion = \"0.2.0\"\n[note]\nname = \"a\"\nprio = 1");
+        let local  = header("[imag]\nversion = \"0.2.0\"\n[note]\nname = \"b\"\nprio = 1");
+        let remote = header("[imag]\nversion = \"0.2.0\"\n[note]\nname = \"a\"\nprio = 2");
+
+        let (merged, conflicts) = merge_header(&base, &local, &remote);
+        assert!(conflicts.is_empty());
+        assert_eq!(merged.lookup("note.name"), Some(&Value::String(String::from("b"))));
+        assert_eq!(merged.lookup("note.prio"), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_merge_header_conflict() {
+        let base   = header("[note]\nname = \"a\"");
+        let local  = header("[note]\nname = \"b\"");
+        let remote = header("[note]\nname = \"c\"");
+
+        let (merged, conflicts) = merge_header(&base, &local, &remote);
+        assert_eq!(conflicts, vec![String::from("note.name")]);
+        assert_eq!(merged.lookup("note.name"), Some(&Value::String(String::from("b"))));
+
+        let kept = match merged.lookup("conflict") {
+            Some(&Value::Table(ref t)) => t.get("note.name").cloned(),
+            _ => None,
+        };
+        assert_eq!(kept.as_ref().and_then(|k| k.lookup("local")),
+                   Some(&Value::String(String::from("b"))));
+        assert_eq!(kept.as_ref().and_then(|k| k.lookup("remote")),
+                   Some(&Value::String(String::from("c"))));
+    }
+
+    #[test]
+    fn test_merge_header_without_conflict_has_no_conflict_section() {
+        let base   = header("[note]\nname = \"a\"");
+        let local  = header("[note]\nname = \"b\"");
+        let remote = header("[note]\nname = \"a\"");
+
+        let (merged, _) = merge_header(&base, &local, &remote);
+        assert!(merged.lookup("conflict").is_none());
+    }
+
+    #[test]
+    fn test_merge_header_links_are_merged_as_set() {
+        let base   = header("[imag]\nlinks = [\"a\", \"b\"]");
+        let local  = header("[imag]\nlinks = [\"a\", \"b\", \"c\"]");
+        let remote = header("[imag]\nlinks = [\"b\", \"d\"]");
+
+        let (merged, conflicts) = merge_header(&base, &local, &remote);
+        assert!(conflicts.is_empty());
+
+        let expected = vec!["b", "c", "d"]
+            .into_iter()
+            .map(|s| Value::String(String::from(s)))
+            .collect();
+        assert_eq!(merged.lookup("imag.links"), Some(&Value::Array(expected)));
+    }
+
+    #[test]
+    fn test_merge_content_independent_lines() {
+        let (merged, conflict) = merge_content("a\nb\nc", "A\nb\nc", "a\nb\nC");
+        assert!(!conflict);
+        assert_eq!(merged, "A\nb\nC");
+    }
+
+    #[test]
+    fn test_merge_content_conflict() {
+        let (merged, conflict) = merge_content("a\nb\nc", "a\nx\nc", "a\ny\nc");
+        assert!(conflict);
+        assert_eq!(merged, "a\n<<<<<<< local\nx\n=======\ny\n>>>>>>> remote\nc");
+    }
+
+}
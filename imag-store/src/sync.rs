@@ -0,0 +1,358 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Synchronizing the store with another store
+//!
+//! The sync is a three-way merge of each entry in both stores against the state of the entry
+//! after the last sync. This base state is recorded in the store in `.imag-sync/`, separately for
+//! each store the store is synced with.
+//!
+//! Conflicts are written into the merged entry in both stores, unless they are only reported: Lines
+//! of the content are surrounded by conflict markers, the values of header fields are kept in the
+//! `conflict` section of the header.
+//!
+//! The other store is opened with the configuration of this store, so entries of encrypted modules
+//! are encrypted in both stores. The base state of these entries is encrypted as well.
+
+use std::collections::BTreeMap;
+use std::fs::{File, create_dir_all, read_dir, remove_file};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use toml::Value;
+
+use libimagrt::runtime::Runtime;
+use libimagstore::crypt::{Encryption, SYNC_DIR_NAME, is_encrypted};
+use libimagstore::access::StoreAccess;
+use libimagstore::store::{Entry, Store};
+use libimagstore::storeid::StoreId;
+use libimagerror::trace::{trace_error, trace_error_exit};
+use libimagerror::into::IntoError;
+
+use archive::Result;
+use error::StoreErrorKind as SEK;
+use error::MapErrInto;
+use merge::{merge_content, merge_header};
+
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Unchanged,
+    Synced,
+    Conflict,
+}
+
+pub fn sync(rt: &Runtime) {
+    rt.cli()
+        .subcommand_matches("sync")
+        .map(|scmd| {
+            // unwrap is safe as value is required
+            let other_path  = PathBuf::from(scmd.value_of("store").unwrap());
+            let report_only = scmd.value_of("conflicts") == Some("report");
+
+            let other = match Store::new(other_path.clone(), rt.store().primary().config().cloned()) {
+                Err(e) => trace_error_exit(&e, 1),
+                Ok(s) => s,
+            };
+            let base = BaseState::new(rt.store().primary().path(), &other_path, rt.store().primary().encryption());
+
+            let mut ids : Vec<PathBuf> = vec![];
+            for dir in &[rt.store().primary().path(), other.path(), &base.path] {
+                match entry_paths(dir) {
+                    Err(e) => trace_error_exit(&e, 1),
+                    Ok(paths) => ids.extend(paths),
+                }
+            }
+            ids.sort();
+            ids.dedup();
+
+            let (mut synced, mut conflicts) = (0, 0);
+            for id in ids {
+                let id = match StoreId::new_baseless(id) {
+                    Err(e) => {
+                        trace_error(&e);
+                        continue;
+                    },
+                    Ok(id) => id,
+                };
+
+                match sync_entry(rt.store(), &other, &base, &id, report_only) {
+                    Ok(Outcome::Unchanged) => debug!("Unchanged: {}", id),
+                    Ok(Outcome::Synced)    => synced += 1,
+                    Ok(Outcome::Conflict)  => conflicts += 1,
+                    Err(e)                 => trace_error(&e),
+                }
+            }
+
+            info!("Synced {} entries, {} conflicts", synced, conflicts);
+        });
+}
+
+/// Sync one entry between the stores
+///
+/// Conflicts are reported on stdout.
+fn sync_entry(local: &StoreAccess, remote: &StoreAccess, base: &BaseState, id: &StoreId, report_only: bool)
+    -> Result<Outcome>
+{
+    let b = try!(base.get(id));
+    let l = try!(load(local, id));
+    let r = try!(load(remote, id));
+
+    match (l, r) {
+        (None, None) => base.set(id, None).map(|_| Outcome::Unchanged),
+
+        (Some(l), None) => sync_one_sided(local, remote, base, id, l, b, "remote", report_only),
+        (None, Some(r)) => sync_one_sided(remote, local, base, id, r, b, "local", report_only),
+
+        (Some(l), Some(r)) => {
+            if l == r {
+                return base.set(id, Some(&l)).map(|_| Outcome::Unchanged);
+            }
+
+            // If there is no base state, the entry was created on both sides independently
+            let b = b.unwrap_or_else(|| {
+                let mut e = Entry::new(id.clone());
+                *e.get_header_mut() = Value::Table(BTreeMap::new());
+                e
+            });
+
+            let (header, header_conflicts) = merge_header(b.get_header(), l.get_header(), r.get_header());
+            let (content, content_conflict) = merge_content(b.get_content(), l.get_content(), r.get_content());
+
+            for path in header_conflicts.iter() {
+                println!("{}: Conflict in header at '{}': local {}, remote {}",
+                         id, path, show(l.get_header().lookup(path)), show(r.get_header().lookup(path)));
+            }
+            if content_conflict {
+                println!("{}: Conflict in content", id);
+            }
+
+            // Conflicting header fields keep the local value and both values are recorded in the
+            // conflict section of the header, conflicting lines of the content are surrounded by
+            // conflict markers. The merged entry is written to both stores, so the conflicts can be
+            // resolved in either of them.
+            let has_conflicts = content_conflict || !header_conflicts.is_empty();
+            if has_conflicts && report_only {
+                return Ok(Outcome::Conflict);
+            }
+
+            let mut merged = Entry::new(id.clone());
+            *merged.get_header_mut()  = header;
+            *merged.get_content_mut() = content;
+
+            if merged != l {
+                try!(write(local, &merged));
+            }
+            if merged != r {
+                try!(write(remote, &merged));
+            }
+
+            // Only advanced if both stores were written, else the next sync merges again
+            try!(base.set(id, Some(&merged)));
+
+            Ok(if has_conflicts { Outcome::Conflict } else { Outcome::Synced })
+        },
+    }
+}
+
+/// Sync an entry which exists only in the store `present`, but not in `absent`
+fn sync_one_sided(present: &StoreAccess,
+                  absent: &StoreAccess,
+                  base: &BaseState,
+                  id: &StoreId,
+                  entry: Entry,
+                  base_entry: Option<Entry>,
+                  absent_name: &str,
+                  report_only: bool)
+    -> Result<Outcome>
+{
+    match base_entry {
+        // new entry
+        None => {
+            try!(write(absent, &entry));
+            base.set(id, Some(&entry)).map(|_| Outcome::Synced)
+        },
+
+        // deleted in `absent`, unchanged in `present`
+        Some(ref b) if *b == entry => {
+            try!(delete(present, id));
+            base.set(id, None).map(|_| Outcome::Synced)
+        },
+
+        // deleted in `absent`, but changed in `present`, so we restore it
+        Some(_) => {
+            println!("{}: Deleted in {} store, but changed in the other", id, absent_name);
+            if report_only {
+                return Ok(Outcome::Conflict);
+            }
+
+            try!(write(absent, &entry));
+            base.set(id, Some(&entry)).map(|_| Outcome::Conflict)
+        },
+    }
+}
+
+/// Show a header value for a conflict report
+fn show(v: Option<&Value>) -> String {
+    v.map(|v| format!("{}", v)).unwrap_or_else(|| String::from("(removed)"))
+}
+
+fn load(store: &StoreAccess, id: &StoreId) -> Result<Option<Entry>> {
+    if try!(store.exists(id.clone()).map_err_into(SEK::BackendError)) {
+        store.retrieve_copy(id.clone()).map(Some).map_err_into(SEK::BackendError)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Write the header and content of `entry` to the store, creating the entry if it does not exist
+fn write(store: &StoreAccess, entry: &Entry) -> Result<()> {
+    let id = entry.get_location().clone();
+    let exists = try!(store.exists(id.clone()).map_err_into(SEK::BackendError));
+
+    let mut fle = if exists {
+        try!(store.retrieve(id).map_err_into(SEK::BackendError))
+    } else {
+        try!(store.create(id).map_err_into(SEK::BackendError))
+    };
+
+    *fle.get_header_mut()  = entry.get_header().clone();
+    *fle.get_content_mut() = entry.get_content().clone();
+    store.update(fle).map_err_into(SEK::BackendError)
+}
+
+fn delete(store: &StoreAccess, id: &StoreId) -> Result<()> {
+    // The store only deletes entries it has loaded
+    let _ = try!(store.get(id.clone()).map_err_into(SEK::BackendError));
+    store.delete(id.clone()).map_err_into(SEK::BackendError)
+}
+
+/// The state of the entries after the last sync with a certain store
+struct BaseState<'a> {
+    path: PathBuf,
+    encryption: Option<&'a Encryption>,
+}
+
+impl<'a> BaseState<'a> {
+
+    fn new(store_path: &PathBuf, other_path: &PathBuf, encryption: Option<&'a Encryption>)
+        -> BaseState<'a>
+    {
+        let name : String = other_path
+            .to_string_lossy()
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+
+        let mut path = store_path.clone();
+        path.push(SYNC_DIR_NAME);
+        path.push(name);
+
+        BaseState {
+            path: path,
+            encryption: encryption,
+        }
+    }
+
+    fn entry_path(&self, id: &StoreId) -> PathBuf {
+        let mut path = self.path.clone();
+        path.push(id.local());
+        path
+    }
+
+    fn get(&self, id: &StoreId) -> Result<Option<Entry>> {
+        let path = self.entry_path(id);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let mut data = vec![];
+        try!(File::open(&path).and_then(|mut f| f.read_to_end(&mut data)).map_err_into(SEK::IoError));
+
+        if is_encrypted(&data) {
+            let enc = try!(self.encryption.ok_or(SEK::BackendError.into_error()));
+            data = try!(enc.key().and_then(|key| key.decrypt(&data, id)).map_err_into(SEK::BackendError));
+        }
+
+        let s = try!(String::from_utf8(data).map_err_into(SEK::FormatError));
+        Entry::from_str(id.clone(), &s).map(Some).map_err_into(SEK::FormatError)
+    }
+
+    fn set(&self, id: &StoreId, entry: Option<&Entry>) -> Result<()> {
+        let path = self.entry_path(id);
+
+        match entry {
+            None => if path.is_file() {
+                remove_file(&path).map_err_into(SEK::IoError)
+            } else {
+                Ok(())
+            },
+
+            Some(entry) => {
+                if let Some(parent) = path.parent() {
+                    try!(create_dir_all(parent).map_err_into(SEK::IoError));
+                }
+
+                let data = match self.encryption {
+                    Some(enc) if enc.encrypts(id) => {
+                        try!(enc.key()
+                             .and_then(|key| key.encrypt(entry.to_str().as_bytes(), id))
+                             .map_err_into(SEK::BackendError))
+                    },
+                    _ => entry.to_str().into_bytes(),
+                };
+
+                File::create(&path)
+                    .and_then(|mut f| f.write_all(&data))
+                    .map_err_into(SEK::IoError)
+            },
+        }
+    }
+
+}
+
+/// Get the paths of all files below `dir`, relative to `dir`, ignoring hidden files and
+/// directories
+fn entry_paths(dir: &PathBuf) -> Result<Vec<PathBuf>> {
+    fn collect(root: &PathBuf, dir: &PathBuf, paths: &mut Vec<PathBuf>) -> Result<()> {
+        for dent in try!(read_dir(dir).map_err_into(SEK::IoError)) {
+            let path = try!(dent.map_err_into(SEK::IoError)).path();
+
+            let hidden = path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with('.'))
+                .unwrap_or(true);
+            if hidden {
+                continue;
+            }
+
+            if path.is_dir() {
+                try!(collect(root, &path, paths));
+            } else if let Ok(local) = path.strip_prefix(root) {
+                paths.push(PathBuf::from(local));
+            }
+        }
+        Ok(())
+    }
+
+    let mut paths = vec![];
+    if dir.is_dir() {
+        try!(collect(dir, dir, &mut paths));
+    }
+    Ok(paths)
+}
@@ -19,6 +19,9 @@
 
 use clap::{Arg, App, ArgGroup, SubCommand};
 
+use libimagentrylist::cli::{list_fields_arg, list_format_arg, list_template_arg, list_with_content_arg};
+use libimagentryfilter::cli::filter_argument;
+
 pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
     app.subcommand(SubCommand::with_name("create")
                    .about("Create an entry from the store")
@@ -201,8 +204,155 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                         .value_name("PATH"))
                    )
 
+       .subcommand(SubCommand::with_name("ls")
+                   .about("List entries of the store")
+                   .version("0.1")
+                   .arg(Arg::with_name("module")
+                        .index(1)
+                        .takes_value(true)
+                        .required(false)
+                        .help("Only list entries of this module or folder, for example 'notes' or 'notes/work'")
+                        .value_name("MODULE"))
+                   .arg(Arg::with_name("tree")
+                        .long("tree")
+                        .short("t")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Show the entries as a tree of folders, with the number of entries per folder"))
+                   .arg(Arg::with_name("depth")
+                        .long("depth")
+                        .short("d")
+                        .takes_value(true)
+                        .required(false)
+                        .requires("tree")
+                        .validator(|d| d.parse::<usize>().map(|_| ()).map_err(|_| format!("Not a number: '{}'", d)))
+                        .help("Collapse folders below this depth")
+                        .value_name("N"))
+                   .arg(list_format_arg().conflicts_with("tree"))
+                   .arg(list_fields_arg())
+                   .arg(list_with_content_arg())
+                   .arg(list_template_arg().conflicts_with("tree"))
+                   .arg(filter_argument())
+                   )
+
        .subcommand(SubCommand::with_name("verify")
                    .about("Verify the store")
                    .version("0.1")
                    )
+
+       .subcommand(SubCommand::with_name("export")
+                   .about("Export entries from the store")
+                   .version("0.1")
+                   .arg(Arg::with_name("format")
+                        .long("format")
+                        .short("f")
+                        .takes_value(true)
+                        .required(false)
+                        .possible_values(&["jsonl", "tar"])
+                        .help("Export as JSON-lines (id, header, content per line) or as tar archive. Default: jsonl")
+                        .value_name("FORMAT"))
+                   .arg(Arg::with_name("file")
+                        .long("file")
+                        .short("o")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Write the export to this file ('-' for stdout, default)")
+                        .value_name("FILE"))
+                   .arg(Arg::with_name("module")
+                        .long("module")
+                        .short("m")
+                        .takes_value(true)
+                        .required(false)
+                        .multiple(true)
+                        .help("Export all entries of this module, multiple allowed. Default: all modules")
+                        .value_name("MODULE"))
+                   .arg(Arg::with_name("id")
+                        .long("id")
+                        .short("i")
+                        .takes_value(true)
+                        .required(false)
+                        .multiple(true)
+                        .help("Export this entry, multiple allowed")
+                        .value_name("PATH"))
+                   .arg(Arg::with_name("stdin")
+                        .long("stdin")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Read the ids of the entries to export from stdin, one per line"))
+                   .arg(Arg::with_name("decrypt")
+                        .long("decrypt")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Also export entries of encrypted modules. The archive is not encrypted, so these entries are written in plain text"))
+                   )
+
+       .subcommand(SubCommand::with_name("import")
+                   .about("Import entries into the store")
+                   .version("0.1")
+                   .arg(Arg::with_name("format")
+                        .long("format")
+                        .short("f")
+                        .takes_value(true)
+                        .required(false)
+                        .possible_values(&["jsonl", "tar"])
+                        .help("Import from JSON-lines or from a tar archive. Default: jsonl")
+                        .value_name("FORMAT"))
+                   .arg(Arg::with_name("file")
+                        .long("file")
+                        .short("i")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Read the import from this file ('-' for stdin, default)")
+                        .value_name("FILE"))
+                   .arg(Arg::with_name("on-conflict")
+                        .long("on-conflict")
+                        .short("c")
+                        .takes_value(true)
+                        .required(false)
+                        .possible_values(&["skip", "overwrite", "rename"])
+                        .help("What to do if an imported entry exists already. Default: skip")
+                        .value_name("POLICY"))
+                   )
+
+       .subcommand(SubCommand::with_name("sync")
+                   .about("Synchronize the store with another store")
+                   .version("0.1")
+                   .arg(Arg::with_name("store")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("Path of the store to synchronize with")
+                        .value_name("STORE"))
+                   .arg(Arg::with_name("conflicts")
+                        .long("conflicts")
+                        .takes_value(true)
+                        .required(false)
+                        .possible_values(&["markers", "report"])
+                        .help("Write conflicts with conflict markers into the entries or only report them and leave the entries untouched. Header conflicts are written to the 'conflict' section of the header. Default: markers")
+                        .value_name("MODE"))
+                   )
+
+       .subcommand(SubCommand::with_name("crypt")
+                   .about("Manage the encryption of the store")
+                   .version("0.1")
+                   .subcommand(SubCommand::with_name("rotate")
+                               .about("Re-encrypt the store with the configured key, applying the configured list of encrypted modules. Configure the new key before running this")
+                               .version("0.1")
+                               .arg(Arg::with_name("old-keyfile")
+                                    .long("old-keyfile")
+                                    .takes_value(true)
+                                    .required(false)
+                                    .help("The entries are currently encrypted with the key from this key file")
+                                    .value_name("FILE"))
+                               .arg(Arg::with_name("old-passphrase-env")
+                                    .long("old-passphrase-env")
+                                    .takes_value(true)
+                                    .required(false)
+                                    .help("The entries are currently encrypted with the key derived from the passphrase in this environment variable")
+                                    .value_name("VAR"))
+                               .group(ArgGroup::with_name("rotate-old-key-group")
+                                      .args(&["old-keyfile", "old-passphrase-env"])
+                                      .required(false))
+                               )
+                   )
 }
@@ -21,11 +21,13 @@ extern crate clap;
 #[macro_use] extern crate log;
 extern crate semver;
 extern crate toml;
+extern crate chrono;
 #[macro_use] extern crate version;
 
 extern crate libimagstore;
 extern crate libimagrt;
 extern crate libimagentrytag;
+extern crate libimagentrylist;
 extern crate libimagerror;
 extern crate libimagutil;
 
@@ -33,15 +35,24 @@ use std::path::PathBuf;
 
 use libimagrt::runtime::Runtime;
 use libimagrt::setup::generate_runtime_setup;
+use libimagentrytag::alias::load_aliases;
 use libimagentrytag::tagable::Tagable;
 use libimagentrytag::tag::Tag;
+use libimagentrytag::hierarchy::TagTree;
+use libimagentrytag::rewrite::{TagOperation, plan, execute};
 use libimagerror::trace::{trace_error, trace_error_exit};
 use libimagentrytag::ui::{get_add_tags, get_remove_tags};
+use libimagentrylist::cli::{get_fields, get_format, list_with_content};
+use libimagentrylist::format::ListFormat;
+use libimagentrylist::pipeline::Pipeline;
+use libimagstore::store::StoreObject;
 use libimagstore::storeid::StoreId;
 use libimagutil::warn_exit::warn_exit;
 
+mod stats;
 mod ui;
 
+use stats::stats;
 use ui::build_ui;
 
 fn main() {
@@ -50,21 +61,30 @@ fn main() {
                                     "Direct interface to the store. Use with great care!",
                                     build_ui);
 
-    let id = rt.cli().value_of("id").unwrap(); // enforced by clap
+    // The tag aliases apply to the tags of all modules
+    load_aliases(rt.config().map(|c| &**c));
+
+    let id = || match rt.cli().value_of("id") {
+        Some(id) => PathBuf::from(id),
+        None     => warn_exit("No entry specified, use --id", 1),
+    };
+
     rt.cli()
         .subcommand_name()
         .map_or_else(
             || {
-                let id = PathBuf::from(id);
+                let id = id();
                 let add = get_add_tags(rt.cli());
                 let rem = get_remove_tags(rt.cli());
                 alter(&rt, id, add, rem);
             },
             |name| {
-                let id = PathBuf::from(id);
                 debug!("Call: {}", name);
                 match name {
-                    "list" => list(id, &rt),
+                    "list" => list(id(), &rt),
+                    "tree" => tree(&rt),
+                    "stats" => stats(&rt),
+                    "rename" | "merge" | "delete" => rewrite(name, &rt),
                     _ => {
                         warn!("Unknown command");
                         // More error handling
@@ -75,7 +95,7 @@ fn main() {
 
 fn alter(rt: &Runtime, id: PathBuf, add: Option<Vec<Tag>>, rem: Option<Vec<Tag>>) {
     let path = {
-        match StoreId::new(Some(rt.store().path().clone()), id) {
+        match StoreId::new(Some(rt.store().primary().path().clone()), id) {
             Err(e) => trace_error_exit(&e, 1),
             Ok(s) => s,
         }
@@ -115,7 +135,7 @@ fn alter(rt: &Runtime, id: PathBuf, add: Option<Vec<Tag>>, rem: Option<Vec<Tag>>
 }
 
 fn list(id: PathBuf, rt: &Runtime) {
-    let path = match StoreId::new(Some(rt.store().path().clone()), id) {
+    let path = match StoreId::new(Some(rt.store().primary().path().clone()), id) {
         Err(e) => trace_error_exit(&e, 1),
         Ok(s)  => s,
     };
@@ -133,12 +153,31 @@ fn list(id: PathBuf, rt: &Runtime) {
 
     let scmd = rt.cli().subcommand_matches("list").unwrap(); // safe, we checked in main()
 
-    let json_out = scmd.is_present("json");
+    // `--json` is a shorthand for `--format json`
+    let format = if scmd.is_present("json") {
+        Some(ListFormat::Json)
+    } else {
+        get_format(scmd)
+    };
+
+    if let Some(format) = format {
+        let mut fields = get_fields(scmd);
+        if fields.is_empty() {
+            fields.push(String::from("imag.tags"));
+        }
+
+        let with_content = scmd.is_present(list_with_content());
+        if let Err(e) = format.list(&Pipeline::new(), fields, with_content, Some(entry).into_iter()) {
+            trace_error_exit(&e, 1);
+        }
+        return;
+    }
+
     let line_out = scmd.is_present("linewise");
     let sepp_out = scmd.is_present("sep");
     let mut comm_out = scmd.is_present("commasep");
 
-    if !vec![json_out, line_out, comm_out, sepp_out].iter().any(|v| *v) {
+    if !vec![line_out, comm_out, sepp_out].iter().any(|v| *v) {
         // None of the flags passed, go to default
         comm_out = true;
     }
@@ -149,10 +188,6 @@ fn list(id: PathBuf, rt: &Runtime) {
     }
     let tags = tags.unwrap();
 
-    if json_out {
-        unimplemented!()
-    }
-
     if line_out {
         for tag in &tags {
             println!("{}", tag);
@@ -169,3 +204,87 @@ fn list(id: PathBuf, rt: &Runtime) {
     }
 }
 
+fn tree(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("tree").unwrap(); // safe, we checked in main()
+
+    let mut tree = TagTree::new();
+    for id in entries_of_module(rt, "") {
+        match rt.store().retrieve_copy(id.clone()).map(|e| e.get_tags()) {
+            Ok(Ok(tags)) => tree.add_entry(&tags),
+            Ok(Err(e))   => {
+                warn!("Could not read tags of '{:?}'", id);
+                trace_error(&e);
+            },
+            Err(e) => trace_error(&e),
+        }
+    }
+
+    match scmd.value_of("tag") {
+        Some(tag) => match tree.get(tag) {
+            Some(subtree) => {
+                println!("{} ({})", tag, subtree.count());
+                print!("{}", subtree);
+            },
+            None => warn_exit(&format!("No entry is tagged with '{}'", tag), 1),
+        },
+        None => print!("{}", tree),
+    }
+}
+
+/// Rename, merge or delete a tag in all entries
+///
+/// All changes are written in one store session, so the git hook commits them at once when the
+/// store is unloaded.
+fn rewrite(name: &str, rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches(name).unwrap(); // safe, we checked in main()
+
+    let op = match name {
+        "rename" => TagOperation::Rename {
+            from: String::from(scmd.value_of("old").unwrap()), // enforced by clap
+            to: String::from(scmd.value_of("new").unwrap()), // enforced by clap
+        },
+        "merge" => TagOperation::Merge {
+            from: scmd.values_of("tags").unwrap().map(String::from).collect(), // enforced by clap
+            into: String::from(scmd.value_of("into").unwrap()), // enforced by clap
+        },
+        "delete" => TagOperation::Delete(String::from(scmd.value_of("tag").unwrap())), // enforced by clap
+        _ => unreachable!(),
+    };
+
+    let changes = match plan(rt.store(), entries_of_module(rt, "").into_iter(), &op) {
+        Ok(changes) => changes,
+        Err(e)      => trace_error_exit(&e, 1),
+    };
+
+    for change in changes.iter() {
+        println!("{}", change);
+    }
+
+    if scmd.is_present("dry-run") {
+        println!("{}: {} entries would be changed (dry run)", op, changes.len());
+        return;
+    }
+
+    if let Err(e) = execute(rt.store(), &changes) {
+        trace_error_exit(&e, 1);
+    }
+    println!("{}: {} entries changed", op, changes.len());
+}
+
+/// Get the ids of all entries of a module, or of the whole store if the module is empty
+///
+/// Hidden directories like ".git" are skipped.
+fn entries_of_module(rt: &Runtime, module: &str) -> Vec<StoreId> {
+    rt.store()
+        .walk(module)
+        .filter_map(|obj| match obj {
+            StoreObject::Id(id)        => Some(id),
+            StoreObject::Collection(_) => None,
+        })
+        .filter(|id| {
+            !id.local()
+                .components()
+                .any(|c| c.as_os_str().to_str().map(|s| s.starts_with('.')).unwrap_or(true))
+        })
+        .collect()
+}
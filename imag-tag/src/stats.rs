@@ -0,0 +1,95 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::metadata;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{Local, TimeZone};
+
+use libimagrt::runtime::Runtime;
+use libimagentrytag::alias::canonical;
+use libimagentrytag::tagable::Tagable;
+use libimagerror::trace::trace_error;
+
+use entries_of_module;
+
+/// The number of entries carrying a tag, and the time the last of these entries was modified
+struct TagStats {
+    count: usize,
+    last_used: Option<SystemTime>,
+}
+
+pub fn stats(rt: &Runtime) {
+    let scmd   = rt.cli().subcommand_matches("stats").unwrap(); // safe, we checked in main()
+    let module = scmd.value_of("module").unwrap_or("");
+
+    let mut stats : BTreeMap<String, TagStats> = BTreeMap::new();
+    for id in entries_of_module(rt, module) {
+        let tags = match rt.store().retrieve_copy(id.clone()).map(|e| e.get_tags()) {
+            Ok(Ok(tags)) => tags,
+            Ok(Err(e))   => {
+                warn!("Could not read tags of '{:?}'", id);
+                trace_error(&e);
+                continue;
+            },
+            Err(e) => {
+                trace_error(&e);
+                continue;
+            },
+        };
+
+        // The modification time of the entry is the best guess for the time a tag was used
+        let modified = id
+            .into_pathbuf()
+            .ok()
+            .and_then(|path| metadata(path).ok())
+            .and_then(|meta| meta.modified().ok());
+
+        // An entry tagged with two aliases of a tag is counted once for the tag
+        let tags = tags.iter().map(|t| canonical(t)).collect::<BTreeSet<_>>();
+
+        for tag in tags {
+            let stat = stats.entry(tag).or_insert(TagStats { count: 0, last_used: None });
+            stat.count += 1;
+            if modified > stat.last_used {
+                stat.last_used = modified;
+            }
+        }
+    }
+
+    let mut stats = stats.into_iter().collect::<Vec<_>>();
+    stats.sort_by(|&(ref a_tag, ref a), &(ref b_tag, ref b)| {
+        match b.count.cmp(&a.count) {
+            Ordering::Equal => a_tag.cmp(b_tag),
+            other           => other,
+        }
+    });
+
+    let width = stats.iter().map(|&(ref tag, _)| tag.len()).max().unwrap_or(0);
+    for (tag, stat) in stats {
+        let last_used = stat.last_used
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| Local.timestamp(d.as_secs() as i64, 0).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| String::from("-"));
+
+        println!("{:<width$}  {:>5}  {}", tag, stat.count, last_used, width = width);
+    }
+}
@@ -17,12 +17,14 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
-use clap::{Arg, App, ArgGroup, SubCommand};
+use clap::{Arg, App, AppSettings, ArgGroup, SubCommand};
 
 use libimagentrytag::ui::{tag_add_arg, tag_remove_arg};
+use libimagentrylist::cli::{list_fields_arg, list_format_arg, list_with_content_arg};
 
 pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
-    app.arg(Arg::with_name("id")
+    app.setting(AppSettings::SubcommandsNegateReqs)
+        .arg(Arg::with_name("id")
                 .long("id")
                 .short("i")
                 .takes_value(true)
@@ -41,7 +43,7 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                         .short("j")
                         .takes_value(false)
                         .required(false)
-                        .help("List as JSON"))
+                        .help("List as JSON, same as '--format json'"))
                    .arg(Arg::with_name("linewise")
                         .long("linewise")
                         .short("l")
@@ -62,14 +64,100 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                         .help("Separated by string")
                         .value_name("SEP"))
 
+                   .arg(list_format_arg())
+                   .arg(list_fields_arg())
+                   .arg(list_with_content_arg())
+
                    .group(ArgGroup::with_name("list-group")
                           .args(&[
                                 "json",
                                 "linewise",
                                 "commasep",
                                 "sep",
+                                "format",
                           ])
                           .required(true))
                    )
 
+       .subcommand(SubCommand::with_name("tree")
+                   .about("Print the tags of all entries as tree, with the number of entries for each tag")
+                   .version("0.1")
+                   .arg(Arg::with_name("tag")
+                        .index(1)
+                        .takes_value(true)
+                        .required(false)
+                        .help("Only print the subtree of this tag")
+                        .value_name("TAG"))
+                   )
+
+       .subcommand(SubCommand::with_name("stats")
+                   .about("Print all tags with the number of entries and the time they were last used")
+                   .version("0.1")
+                   .arg(Arg::with_name("module")
+                        .long("module")
+                        .short("m")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Only count the entries of this module")
+                        .value_name("MODULE"))
+                   )
+
+       .subcommand(SubCommand::with_name("rename")
+                   .about("Rename a tag (and its descendants) in all entries")
+                   .version("0.1")
+                   .arg(Arg::with_name("old")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("The tag to rename")
+                        .value_name("OLD"))
+                   .arg(Arg::with_name("new")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .help("The new name of the tag")
+                        .value_name("NEW"))
+                   .arg(dry_run_arg())
+                   )
+
+       .subcommand(SubCommand::with_name("merge")
+                   .about("Replace several tags (and their descendants) by one tag in all entries")
+                   .version("0.1")
+                   .arg(Arg::with_name("tags")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(true)
+                        .help("The tags to merge")
+                        .value_name("TAGS"))
+                   .arg(Arg::with_name("into")
+                        .long("into")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The tag to merge into")
+                        .value_name("TAG"))
+                   .arg(dry_run_arg())
+                   )
+
+       .subcommand(SubCommand::with_name("delete")
+                   .about("Remove a tag (and its descendants) from all entries")
+                   .version("0.1")
+                   .arg(Arg::with_name("tag")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("The tag to delete")
+                        .value_name("TAG"))
+                   .arg(dry_run_arg())
+                   )
+
+}
+
+fn dry_run_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("dry-run")
+        .long("dry-run")
+        .short("n")
+        .takes_value(false)
+        .required(false)
+        .help("Only print the changes, do not write them")
 }
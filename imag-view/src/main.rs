@@ -48,6 +48,7 @@ use std::path::PathBuf;
 
 use libimagrt::setup::generate_runtime_setup;
 use libimagerror::trace::trace_error_exit;
+use libimagentryview::builtin::markdown::{MarkdownViewer, looks_like_markdown};
 use libimagentryview::builtin::stdout::StdoutViewer;
 use libimagentryview::viewer::Viewer;
 
@@ -66,6 +67,7 @@ fn main() {
     let entry_id     = rt.cli().value_of("id").unwrap(); // enforced by clap
     let view_header  = rt.cli().is_present("view-header");
     let view_content = rt.cli().is_present("view-content");
+    let view_plain   = rt.cli().is_present("view-plain");
 
     let entry = match rt.store().get(PathBuf::from(entry_id)) {
         Ok(Some(fle)) => fle,
@@ -101,7 +103,11 @@ fn main() {
             },
         };
 
-        StdoutViewer::new(view_header, view_content).view_entry(&entry)
+        if view_content && !view_plain && looks_like_markdown(entry.get_content()) {
+            MarkdownViewer::new(view_header, rt.use_colors()).view_entry(&entry)
+        } else {
+            StdoutViewer::new(view_header, view_content).view_entry(&entry)
+        }
     };
 
     if let Err(e) = res {
@@ -41,6 +41,12 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
             .takes_value(false)
             .required(false)
             .help("View content"))
+        .arg(Arg::with_name("view-plain")
+            .long("plain")
+            .short("p")
+            .takes_value(false)
+            .required(false)
+            .help("Print the content as it is, even if it is markdown"))
 
         .subcommand(SubCommand::with_name("view-in")
                    .about("View the entry in ...")
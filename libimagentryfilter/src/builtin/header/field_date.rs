@@ -0,0 +1,242 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use chrono::naive::datetime::NaiveDateTime;
+use toml::Value;
+
+use libimagstore::store::Entry;
+use libimagtimeui::relative::{parse_absolute, parse_point, parse_range};
+
+use builtin::header::field_path::FieldPath;
+use builtin::header::field_predicate::FieldPredicate;
+use builtin::header::field_predicate::Predicate;
+use filters::filter::Filter;
+
+/// Parse a header value as point in time
+///
+/// Strings in the format `YYYY-MM-DD[THH[:mm[:ss]]]` and TOML datetimes are understood.
+fn header_datetime(v: Value) -> Option<NaiveDateTime> {
+    match v {
+        Value::String(s) | Value::Datetime(s) => parse_absolute(s.trim_right_matches('Z')),
+        _ => None,
+    }
+}
+
+struct DatePred {
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+}
+
+impl Predicate for DatePred {
+
+    fn evaluate(&self, v: Value) -> bool {
+        header_datetime(v)
+            .map(|dt| {
+                self.start.map(|start| start <= dt).unwrap_or(true) &&
+                    self.end.map(|end| dt < end).unwrap_or(true)
+            })
+            .unwrap_or(false)
+    }
+
+}
+
+/// Check whether a header field contains a date before a point in time
+pub struct FieldDateBefore {
+    filter: FieldPredicate<DatePred>,
+}
+
+impl FieldDateBefore {
+
+    pub fn new(path: FieldPath, end: NaiveDateTime) -> FieldDateBefore {
+        FieldDateBefore {
+            filter: FieldPredicate::new(path, Box::new(DatePred { start: None, end: Some(end) })),
+        }
+    }
+
+    /// Build the filter from an absolute or relative point in time, like "yesterday"
+    ///
+    /// Returns `None` if the point in time cannot be parsed.
+    pub fn parse(path: FieldPath, s: &str, now: NaiveDateTime) -> Option<FieldDateBefore> {
+        parse_point(s, now).map(|end| FieldDateBefore::new(path, end))
+    }
+
+}
+
+impl Filter<Entry> for FieldDateBefore {
+
+    fn filter(&self, e: &Entry) -> bool {
+        self.filter.filter(e)
+    }
+
+}
+
+/// Check whether a header field contains a date at or after a point in time
+pub struct FieldDateAfter {
+    filter: FieldPredicate<DatePred>,
+}
+
+impl FieldDateAfter {
+
+    pub fn new(path: FieldPath, start: NaiveDateTime) -> FieldDateAfter {
+        FieldDateAfter {
+            filter: FieldPredicate::new(path, Box::new(DatePred { start: Some(start), end: None })),
+        }
+    }
+
+    /// Build the filter from an absolute or relative point in time, like "3 days ago"
+    ///
+    /// Returns `None` if the point in time cannot be parsed.
+    pub fn parse(path: FieldPath, s: &str, now: NaiveDateTime) -> Option<FieldDateAfter> {
+        parse_point(s, now).map(|start| FieldDateAfter::new(path, start))
+    }
+
+}
+
+impl Filter<Entry> for FieldDateAfter {
+
+    fn filter(&self, e: &Entry) -> bool {
+        self.filter.filter(e)
+    }
+
+}
+
+/// Check whether a header field contains a date in a range, including the start of the range
+/// but not its end
+pub struct FieldDateInRange {
+    filter: FieldPredicate<DatePred>,
+}
+
+impl FieldDateInRange {
+
+    pub fn new(path: FieldPath, start: NaiveDateTime, end: NaiveDateTime) -> FieldDateInRange {
+        FieldDateInRange {
+            filter: FieldPredicate::new(path, Box::new(DatePred { start: Some(start), end: Some(end) })),
+        }
+    }
+
+    /// Build the filter from an absolute or relative range, like "last 7 days"
+    ///
+    /// Returns `None` if the range cannot be parsed.
+    pub fn parse(path: FieldPath, s: &str, now: NaiveDateTime) -> Option<FieldDateInRange> {
+        parse_range(s, now).map(|(start, end)| FieldDateInRange::new(path, start, end))
+    }
+
+}
+
+impl Filter<Entry> for FieldDateInRange {
+
+    fn filter(&self, e: &Entry) -> bool {
+        self.filter.filter(e)
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use chrono::naive::date::NaiveDate;
+    use chrono::naive::datetime::NaiveDateTime;
+
+    use libimagstore::store::Entry;
+    use libimagstore::storeid::StoreId;
+
+    use filters::filter::Filter;
+    use super::{FieldDateBefore, FieldDateAfter, FieldDateInRange};
+
+    fn entry() -> Entry {
+        let s = r#"---
+[imag]
+version = "0.2.0"
+
+[note]
+date = "2016-10-12T10:00"
+created = 2016-10-11T08:00:00Z
+name = "meeting"
+---
+"#;
+
+        Entry::from_str(StoreId::new_baseless(PathBuf::from("test/date")).unwrap(), s).unwrap()
+    }
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd(2016, 10, 13).and_hms(15, 30, 0)
+    }
+
+    fn dt(y: i32, m: u32, d: u32, h: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(y, m, d).and_hms(h, 0, 0)
+    }
+
+    #[test]
+    fn test_before_and_after() {
+        let e = entry();
+        let path = || String::from("note.date");
+
+        assert!(FieldDateBefore::new(path(), dt(2016, 10, 12, 11)).filter(&e));
+        assert!(!FieldDateBefore::new(path(), dt(2016, 10, 12, 10)).filter(&e));
+        assert!(FieldDateAfter::new(path(), dt(2016, 10, 12, 10)).filter(&e));
+        assert!(!FieldDateAfter::new(path(), dt(2016, 10, 12, 11)).filter(&e));
+    }
+
+    #[test]
+    fn test_toml_datetime() {
+        let e = entry();
+
+        assert!(FieldDateBefore::new(String::from("note.created"), dt(2016, 10, 11, 9)).filter(&e));
+        assert!(!FieldDateAfter::new(String::from("note.created"), dt(2016, 10, 11, 9)).filter(&e));
+    }
+
+    #[test]
+    fn test_in_range() {
+        let e = entry();
+        let path = || String::from("note.date");
+
+        assert!(FieldDateInRange::new(path(), dt(2016, 10, 12, 10), dt(2016, 10, 12, 11)).filter(&e));
+        assert!(!FieldDateInRange::new(path(), dt(2016, 10, 12, 9), dt(2016, 10, 12, 10)).filter(&e));
+    }
+
+    #[test]
+    fn test_no_date() {
+        let e = entry();
+
+        assert!(!FieldDateAfter::new(String::from("note.name"), dt(2000, 1, 1, 0)).filter(&e));
+        assert!(!FieldDateAfter::new(String::from("note.missing"), dt(2000, 1, 1, 0)).filter(&e));
+    }
+
+    #[test]
+    fn test_parse_relative() {
+        let e = entry();
+        let path = || String::from("note.date");
+
+        assert!(FieldDateAfter::parse(path(), "2 days ago", now()).unwrap().filter(&e));
+        assert!(FieldDateBefore::parse(path(), "today", now()).unwrap().filter(&e));
+        assert!(FieldDateInRange::parse(path(), "last 7 days", now()).unwrap().filter(&e));
+        assert!(!FieldDateInRange::parse(path(), "today", now()).unwrap().filter(&e));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let path = || String::from("note.date");
+
+        assert!(FieldDateAfter::parse(path(), "someday", now()).is_none());
+        assert!(FieldDateBefore::parse(path(), "999999999 days ago", now()).is_none());
+        assert!(FieldDateInRange::parse(path(), "last 9999999999 weeks", now()).is_none());
+    }
+
+}
@@ -17,6 +17,7 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+pub mod field_date;
 pub mod field_eq;
 pub mod field_exists;
 pub mod field_grep;
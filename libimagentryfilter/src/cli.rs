@@ -17,3 +17,762 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+//! A commandline-to-filter DSL
+//!
+//! Filter expressions are parsed into a `FilterExpression`, which is a `Filter<Entry>` built from
+//! the builtin filters. An expression consists of predicates, combined with `and`, `or`, `not` and
+//! parentheses, for example:
+//!
+//! ```text
+//! header.note.name ~ "^meet" and (tag:work or not content.len > 1000)
+//! ```
+//!
+//! The predicates are:
+//!
+//! | Predicate                      | Filter                                  |
+//! | ------------------------------ | --------------------------------------- |
+//! | `header.a.b = VALUE`, `!=`     | `FieldEq`                               |
+//! | `header.a.b ~ "REGEX"`         | `FieldGrep`                             |
+//! | `header.a.b > NUM`, `<`, `>=`, `<=` | `FieldGt`, `FieldLt`               |
+//! | `header.a.b is TYPE`           | `FieldIsType`                           |
+//! | `header.a.b before "TIME"`     | `FieldDateBefore`                       |
+//! | `header.a.b after "TIME"`      | `FieldDateAfter`                        |
+//! | `header.a.b within "RANGE"`    | `FieldDateInRange`                      |
+//! | `tag:TAG`                      | `HasTag`                                |
+//! | `links-to:ID`, `links-to:GLOB` | `LinksTo`, `LinksToGlob`                |
+//! | `unlinked`                     | `LinkCountInRange::unlinked()`          |
+//! | `links.count in N..M`, `N..`   | `LinkCountInRange`                      |
+//! | `links-external:DOMAIN`        | `HasExternalLinkToDomain`               |
+//! | `annotation`                   | `IsAnnotation`                          |
+//! | `content ~ "REGEX"`            | `ContentGrep`                           |
+//! | `content.len > N`, `<`         | `ContentLengthIsOver`, `ContentLengthIsUnder` |
+//! | `version = V`, `<`, `>`        | `VersionEq`, `VersionLt`, `VersionGt`   |
+//! | `version in V1..V2`            | `VersionInRange`                        |
+//! | `true`, `false`                | `BoolFilter`                            |
+//!
+//! Values are quoted strings, integers, floats or booleans. Unquoted words are taken as strings.
+//! `and` binds stronger than `or`. Points in time and ranges can be relative, like `"yesterday"` or
+//! `"last 7 days"`, see `libimagtimeui::relative`.
+//!
+//! `links-external:DOMAIN` has to look up the URLs of the external links in the store, so it can
+//! only be used in expressions parsed with `parse_with_store()`.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::Chars;
+use std::iter::{Enumerate, Peekable};
+use std::path::PathBuf;
+
+use chrono::Local;
+use clap::{Arg, ArgMatches};
+use glob::Pattern;
+use regex::Regex;
+use semver::Version;
+use toml::Value;
+
+use libimagstore::store::Entry;
+use libimagstore::access::StoreAccess;
+use libimagstore::storeid::StoreId;
+
+use builtin::bool_filter::BoolFilter;
+use builtin::content::grep::ContentGrep;
+use builtin::content::length::is_over::ContentLengthIsOver;
+use builtin::content::length::is_under::ContentLengthIsUnder;
+use builtin::header::field_date::{FieldDateAfter, FieldDateBefore, FieldDateInRange};
+use builtin::header::field_eq::FieldEq;
+use builtin::header::field_grep::FieldGrep;
+use builtin::header::field_gt::FieldGt;
+use builtin::header::field_istype::{FieldIsType, Type};
+use builtin::header::field_lt::FieldLt;
+use builtin::header::field_path::FieldPath;
+use builtin::header::version::eq::VersionEq;
+use builtin::header::version::gt::VersionGt;
+use builtin::header::version::lt::VersionLt;
+use builtin::header::version::range::VersionInRange;
+use filters::filter::Filter;
+use links::{HasExternalLinkToDomain, IsAnnotation, LinkCountInRange, LinksTo, LinksToGlob};
+use tags::HasTag;
+
+/// A parsed filter expression
+///
+/// The lifetime is the one of the store used by some predicates.
+pub enum FilterExpression<'a> {
+    And(Box<FilterExpression<'a>>, Box<FilterExpression<'a>>),
+    Or(Box<FilterExpression<'a>>, Box<FilterExpression<'a>>),
+    Not(Box<FilterExpression<'a>>),
+    Predicate(Box<Filter<Entry> + 'a>),
+}
+
+impl<'a> FilterExpression<'a> {
+
+    fn predicate<F: Filter<Entry> + 'a>(f: F) -> FilterExpression<'a> {
+        FilterExpression::Predicate(Box::new(f))
+    }
+
+    fn and(self, other: FilterExpression<'a>) -> FilterExpression<'a> {
+        FilterExpression::And(Box::new(self), Box::new(other))
+    }
+
+    fn or(self, other: FilterExpression<'a>) -> FilterExpression<'a> {
+        FilterExpression::Or(Box::new(self), Box::new(other))
+    }
+
+    fn not(self) -> FilterExpression<'a> {
+        FilterExpression::Not(Box::new(self))
+    }
+
+}
+
+impl<'a> Filter<Entry> for FilterExpression<'a> {
+
+    fn filter(&self, e: &Entry) -> bool {
+        match *self {
+            FilterExpression::And(ref a, ref b) => a.filter(e) && b.filter(e),
+            FilterExpression::Or(ref a, ref b)  => a.filter(e) || b.filter(e),
+            FilterExpression::Not(ref a)        => !a.filter(e),
+            FilterExpression::Predicate(ref f)  => f.filter(e),
+        }
+    }
+
+}
+
+/// An error while parsing a filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The position in the expression (in characters) where the error occured
+    pub position: usize,
+    pub message: String,
+}
+
+impl ParseError {
+
+    fn new<S: Into<String>>(position: usize, message: S) -> ParseError {
+        ParseError {
+            position: position,
+            message: message.into(),
+        }
+    }
+
+}
+
+impl Display for ParseError {
+
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "Invalid filter expression at position {}: {}", self.position, self.message)
+    }
+
+}
+
+impl Error for ParseError {
+
+    fn description(&self) -> &str {
+        "Invalid filter expression"
+    }
+
+}
+
+/// Parse a filter expression
+///
+/// Predicates which need the store, like `links-external:DOMAIN`, are an error. Use
+/// `parse_with_store()` for these.
+pub fn parse(expr: &str) -> Result<FilterExpression<'static>, ParseError> {
+    parse_expression(expr, None)
+}
+
+/// Parse a filter expression, which may contain predicates which need the store
+pub fn parse_with_store<'a>(expr: &str, store: &'a StoreAccess) -> Result<FilterExpression<'a>, ParseError> {
+    parse_expression(expr, Some(store))
+}
+
+fn parse_expression<'a>(expr: &str, store: Option<&'a StoreAccess>)
+    -> Result<FilterExpression<'a>, ParseError>
+{
+    let tokens = try!(tokenize(expr));
+    let mut parser = Parser { tokens: tokens, pos: 0, end: expr.chars().count(), store: store };
+
+    let result = try!(parser.parse_or());
+    match parser.peek() {
+        None => Ok(result),
+        Some(&(pos, ref t)) => Err(ParseError::new(pos, format!("Unexpected {}", t))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Op(&'static str),
+    Str(String),
+    Word(String),
+}
+
+impl Display for Token {
+
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            Token::LParen      => write!(fmt, "'('"),
+            Token::RParen      => write!(fmt, "')'"),
+            Token::Op(op)      => write!(fmt, "'{}'", op),
+            Token::Str(ref s)  => write!(fmt, "\"{}\"", s),
+            Token::Word(ref w) => write!(fmt, "'{}'", w),
+        }
+    }
+
+}
+
+const OPERATORS: &'static [&'static str] = &["==", "!=", "<=", ">=", "=", "~", "<", ">"];
+
+fn is_operator_char(c: char) -> bool {
+    "=!<>~".contains(c)
+}
+
+fn tokenize(expr: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars  = expr.chars().enumerate().peekable();
+
+    loop {
+        let (pos, c) = match chars.next() {
+            Some(next) => next,
+            None       => break,
+        };
+
+        if c.is_whitespace() {
+            continue;
+        }
+
+        let token = match c {
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '"' => Token::Str(try!(read_string(pos, &mut chars))),
+            c if is_operator_char(c) => {
+                let two = chars.peek().map(|&(_, next)| format!("{}{}", c, next));
+                let one = c.to_string();
+
+                match two.and_then(|two| OPERATORS.iter().find(|o| **o == two).cloned()) {
+                    Some(op) => {
+                        chars.next();
+                        Token::Op(op)
+                    },
+                    None => match OPERATORS.iter().find(|o| **o == one).cloned() {
+                        Some(op) => Token::Op(op),
+                        None     => return Err(ParseError::new(pos, format!("Unknown operator '{}'", one))),
+                    },
+                }
+            },
+            c => {
+                let mut word = c.to_string();
+                loop {
+                    match chars.peek() {
+                        Some(&(_, next)) if !next.is_whitespace() && !is_operator_char(next) &&
+                            next != '(' && next != ')' && next != '"' => word.push(next),
+                        _ => break,
+                    }
+                    chars.next();
+                }
+                Token::Word(word)
+            },
+        };
+
+        tokens.push((pos, token));
+    }
+
+    Ok(tokens)
+}
+
+/// Read a quoted string, the opening quote is already consumed
+fn read_string(start: usize, chars: &mut Peekable<Enumerate<Chars>>)
+    -> Result<String, ParseError>
+{
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            Some((_, '"'))  => return Ok(s),
+            Some((_, '\\')) => match chars.next() {
+                Some((_, c)) => s.push(c),
+                None         => break,
+            },
+            Some((_, c))    => s.push(c),
+            None            => break,
+        }
+    }
+    Err(ParseError::new(start, "Unterminated string"))
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+
+    /// The length of the expression, for errors at its end
+    end: usize,
+
+    store: Option<&'a StoreAccess>,
+}
+
+impl<'a> Parser<'a> {
+
+    fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<(usize, Token), ParseError> {
+        match self.tokens.get(self.pos).cloned() {
+            Some(t) => {
+                self.pos += 1;
+                Ok(t)
+            },
+            None => Err(ParseError::new(self.end, "Unexpected end of expression")),
+        }
+    }
+
+    /// Consume the next token if it is the keyword `kw`
+    fn keyword(&mut self, kw: &str) -> bool {
+        let found = match self.peek() {
+            Some(&(_, Token::Word(ref w))) => w == kw,
+            _ => false,
+        };
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpression<'a>, ParseError> {
+        let mut expr = try!(self.parse_and());
+        while self.keyword("or") {
+            let rhs = try!(self.parse_and());
+            expr = expr.or(rhs);
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpression<'a>, ParseError> {
+        let mut expr = try!(self.parse_unary());
+        while self.keyword("and") {
+            let rhs = try!(self.parse_unary());
+            expr = expr.and(rhs);
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<FilterExpression<'a>, ParseError> {
+        if self.keyword("not") {
+            return self.parse_unary().map(FilterExpression::not);
+        }
+
+        match try!(self.next()) {
+            (_, Token::LParen) => {
+                let expr = try!(self.parse_or());
+                match try!(self.next()) {
+                    (_, Token::RParen) => Ok(expr),
+                    (pos, t)           => Err(ParseError::new(pos, format!("Expected ')', found {}", t))),
+                }
+            },
+            (pos, Token::Word(w)) => self.parse_predicate(pos, w),
+            (pos, t) => Err(ParseError::new(pos, format!("Expected a predicate, found {}", t))),
+        }
+    }
+
+    fn parse_predicate(&mut self, pos: usize, word: String) -> Result<FilterExpression<'a>, ParseError> {
+        if word == "true" || word == "false" {
+            return Ok(FilterExpression::predicate(BoolFilter::new(word == "true")));
+        }
+
+        if word.starts_with("tag:") {
+            return Ok(FilterExpression::predicate(HasTag::new(String::from(&word[4..]))));
+        }
+
+        if word.starts_with("links-to:") {
+            return links_to(pos, &word[9..]);
+        }
+
+        if word.starts_with("links-external:") {
+            return self.links_external(pos, &word[15..]);
+        }
+
+        if word.starts_with("header.") {
+            return self.parse_header_predicate(String::from(&word[7..]));
+        }
+
+        match &word[..] {
+            "content"     => self.parse_content_predicate(),
+            "content.len" => self.parse_content_length_predicate(),
+            "version"     => self.parse_version_predicate(),
+            "links.count" => self.parse_link_count_predicate(),
+            "unlinked"    => Ok(FilterExpression::predicate(LinkCountInRange::unlinked())),
+            "annotation"  => Ok(FilterExpression::predicate(IsAnnotation::new())),
+            _             => Err(ParseError::new(pos, format!("Unknown predicate '{}'", word))),
+        }
+    }
+
+    fn parse_header_predicate(&mut self, path: String) -> Result<FilterExpression<'a>, ParseError> {
+        if self.keyword("is") {
+            let (pos, ty) = try!(self.next_word());
+            let ty = match &ty[..] {
+                "array"              => Type::Array,
+                "bool" | "boolean"   => Type::Boolean,
+                "float"              => Type::Float,
+                "int" | "integer"    => Type::Integer,
+                "string"             => Type::String,
+                "table"              => Type::Table,
+                _ => return Err(ParseError::new(pos, format!("Unknown type '{}'", ty))),
+            };
+            return Ok(FilterExpression::predicate(FieldIsType::new(path, ty)));
+        }
+
+        let date_keyword = ["before", "after", "within"].iter().cloned().find(|kw| self.keyword(kw));
+        if let Some(kw) = date_keyword {
+            return self.parse_date_predicate(path, kw);
+        }
+
+        let (pos, op) = try!(self.next_operator());
+        let value     = try!(self.next_value());
+
+        // FieldGt matches if the passed value is greater than the header value, FieldLt the other
+        // way round. So `header.a > 5` is a `FieldLt` with 5.
+        let expr = match op {
+            "=" | "==" => FilterExpression::predicate(FieldEq::new(path, value)),
+            "!="       => FilterExpression::predicate(FieldEq::new(path, value)).not(),
+            ">"        => FilterExpression::predicate(FieldLt::new(path, try!(number(pos, value)))),
+            "<"        => FilterExpression::predicate(FieldGt::new(path, try!(number(pos, value)))),
+            ">=" => {
+                let value = try!(number(pos, value));
+                FilterExpression::predicate(FieldLt::new(path.clone(), value.clone()))
+                    .or(FilterExpression::predicate(FieldEq::new(path, value)))
+            },
+            "<=" => {
+                let value = try!(number(pos, value));
+                FilterExpression::predicate(FieldGt::new(path.clone(), value.clone()))
+                    .or(FilterExpression::predicate(FieldEq::new(path, value)))
+            },
+            "~" => match value {
+                Value::String(s) => FilterExpression::predicate(FieldGrep::new(path, try!(regex(pos, &s)))),
+                _ => return Err(ParseError::new(pos, "Expected a regex")),
+            },
+            _ => unreachable!(),
+        };
+
+        Ok(expr)
+    }
+
+    fn parse_date_predicate(&mut self, path: FieldPath, kw: &str) -> Result<FilterExpression<'a>, ParseError> {
+        let (pos, time) = try!(self.next_word());
+        let now         = Local::now().naive_local();
+
+        let expr = match kw {
+            "before" => FieldDateBefore::parse(path, &time, now).map(FilterExpression::predicate),
+            "after"  => FieldDateAfter::parse(path, &time, now).map(FilterExpression::predicate),
+            "within" => FieldDateInRange::parse(path, &time, now).map(FilterExpression::predicate),
+            _        => unreachable!(),
+        };
+
+        expr.ok_or_else(|| ParseError::new(pos, format!("Invalid time '{}'", time)))
+    }
+
+    fn parse_content_predicate(&mut self) -> Result<FilterExpression<'a>, ParseError> {
+        match try!(self.next_operator()) {
+            (pos, "~") => match try!(self.next_value()) {
+                Value::String(s) => {
+                    let grep = try!(regex(pos, &s));
+                    ContentGrep::new(grep)
+                        .map(FilterExpression::predicate)
+                        .map_err(|e| ParseError::new(pos, format!("Invalid regex: {}", e)))
+                },
+                _ => Err(ParseError::new(pos, "Expected a regex")),
+            },
+            (pos, op) => Err(ParseError::new(pos, format!("Operator '{}' not supported for content", op))),
+        }
+    }
+
+    fn parse_content_length_predicate(&mut self) -> Result<FilterExpression<'a>, ParseError> {
+        let (pos, op) = try!(self.next_operator());
+        let len = match try!(self.next_value()) {
+            Value::Integer(i) if i >= 0 => i as usize,
+            _ => return Err(ParseError::new(pos, "Expected a length")),
+        };
+
+        match op {
+            ">" => Ok(FilterExpression::predicate(ContentLengthIsOver::new(len))),
+            "<" => Ok(FilterExpression::predicate(ContentLengthIsUnder::new(len))),
+            _   => Err(ParseError::new(pos, format!("Operator '{}' not supported for content.len", op))),
+        }
+    }
+
+    fn parse_version_predicate(&mut self) -> Result<FilterExpression<'a>, ParseError> {
+        if self.keyword("in") {
+            let (pos, range) = try!(self.next_word());
+            let mut bounds = range.splitn(2, "..");
+            return match (bounds.next(), bounds.next()) {
+                (Some(lower), Some(upper)) => {
+                    let lower = try!(version(pos, lower));
+                    let upper = try!(version(pos, upper));
+                    Ok(FilterExpression::predicate(VersionInRange::new(lower, upper)))
+                },
+                _ => Err(ParseError::new(pos, "Expected a version range like '0.1.0..0.3.0'")),
+            };
+        }
+
+        let (pos, op) = try!(self.next_operator());
+        let (_, v)    = try!(self.next_word());
+        let v         = try!(version(pos, &v));
+
+        match op {
+            "=" | "==" => Ok(FilterExpression::predicate(VersionEq::new(v))),
+            ">"        => Ok(FilterExpression::predicate(VersionGt::new(v))),
+            "<"        => Ok(FilterExpression::predicate(VersionLt::new(v))),
+            _          => Err(ParseError::new(pos, format!("Operator '{}' not supported for version", op))),
+        }
+    }
+
+    fn parse_link_count_predicate(&mut self) -> Result<FilterExpression<'a>, ParseError> {
+        if !self.keyword("in") {
+            let pos = self.peek().map(|&(pos, _)| pos).unwrap_or(self.end);
+            return Err(ParseError::new(pos, "Expected 'in'"));
+        }
+
+        let (pos, range) = try!(self.next_word());
+        let mut bounds   = range.splitn(2, "..");
+        let invalid      = || ParseError::new(pos, "Expected a range like '1..3' or '1..'");
+
+        let min = match bounds.next().map(|b| b.parse::<usize>()) {
+            Some(Ok(min)) => min,
+            _             => return Err(invalid()),
+        };
+        let max = match bounds.next() {
+            Some("")  => None,
+            Some(max) => Some(try!(max.parse::<usize>().map_err(|_| invalid()))),
+            None      => return Err(invalid()),
+        };
+
+        Ok(FilterExpression::predicate(LinkCountInRange::new(min, max)))
+    }
+
+    fn links_external(&self, pos: usize, domain: &str) -> Result<FilterExpression<'a>, ParseError> {
+        if domain.is_empty() {
+            return Err(ParseError::new(pos, "Expected a domain"));
+        }
+
+        match self.store {
+            Some(store) => {
+                let filter = HasExternalLinkToDomain::new(store, String::from(domain));
+                Ok(FilterExpression::predicate(filter))
+            },
+            None => Err(ParseError::new(pos, "'links-external:' can not be used without a store")),
+        }
+    }
+
+    fn next_operator(&mut self) -> Result<(usize, &'static str), ParseError> {
+        match try!(self.next()) {
+            (pos, Token::Op(op)) => Ok((pos, op)),
+            (pos, t)             => Err(ParseError::new(pos, format!("Expected an operator, found {}", t))),
+        }
+    }
+
+    fn next_word(&mut self) -> Result<(usize, String), ParseError> {
+        match try!(self.next()) {
+            (pos, Token::Word(w)) | (pos, Token::Str(w)) => Ok((pos, w)),
+            (pos, t) => Err(ParseError::new(pos, format!("Expected a word, found {}", t))),
+        }
+    }
+
+    fn next_value(&mut self) -> Result<Value, ParseError> {
+        match try!(self.next()) {
+            (_, Token::Str(s))  => Ok(Value::String(s)),
+            (_, Token::Word(w)) => Ok(word_to_value(w)),
+            (pos, t) => Err(ParseError::new(pos, format!("Expected a value, found {}", t))),
+        }
+    }
+
+}
+
+/// `LinksToGlob` if `target` contains glob characters, `LinksTo` otherwise
+fn links_to<'a>(pos: usize, target: &str) -> Result<FilterExpression<'a>, ParseError> {
+    if target.contains(|c: char| c == '*' || c == '?' || c == '[') {
+        Pattern::new(target)
+            .map(|p| FilterExpression::predicate(LinksToGlob::new(p)))
+            .map_err(|e| ParseError::new(pos, format!("Invalid glob '{}': {}", target, e)))
+    } else {
+        StoreId::new_baseless(PathBuf::from(target))
+            .map(|id| FilterExpression::predicate(LinksTo::new(id)))
+            .map_err(|e| ParseError::new(pos, format!("Invalid id '{}': {}", target, e)))
+    }
+}
+
+fn word_to_value(w: String) -> Value {
+    if w == "true" || w == "false" {
+        return Value::Boolean(w == "true");
+    }
+
+    if let Ok(i) = w.parse::<i64>() {
+        return Value::Integer(i);
+    }
+
+    if let Ok(f) = w.parse::<f64>() {
+        return Value::Float(f);
+    }
+
+    Value::String(w)
+}
+
+fn number(pos: usize, v: Value) -> Result<Value, ParseError> {
+    match v {
+        Value::Integer(_) | Value::Float(_) => Ok(v),
+        _ => Err(ParseError::new(pos, "Expected a number")),
+    }
+}
+
+fn regex(pos: usize, s: &str) -> Result<Regex, ParseError> {
+    Regex::new(s).map_err(|e| ParseError::new(pos, format!("Invalid regex: {}", e)))
+}
+
+fn version(pos: usize, s: &str) -> Result<Version, ParseError> {
+    Version::parse(s).map_err(|e| ParseError::new(pos, format!("Invalid version '{}': {}", s, e)))
+}
+
+/// Generates a `clap::Arg` for a filter expression, which can be integrated into the
+/// commandline-ui builder of any imag binary
+pub fn filter_argument<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(filter_argument_name())
+        .long("filter")
+        .short("F")
+        .takes_value(true)
+        .required(false)
+        .help("Only use entries matching this filter expression, for example 'tag:work and header.note.name ~ \"^meet\"'")
+        .value_name("EXPR")
+}
+
+pub fn filter_argument_name() -> &'static str {
+    "filter-expression"
+}
+
+/// Get the filter passed on the commandline
+///
+/// Returns none if the argument was not specified
+pub fn get_filter<'a>(matches: &ArgMatches, store: &'a StoreAccess)
+    -> Option<Result<FilterExpression<'a>, ParseError>>
+{
+    matches.value_of(filter_argument_name()).map(|expr| parse_with_store(expr, store))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::store::Entry;
+    use libimagstore::storeid::StoreId;
+
+    use filters::filter::Filter;
+    use super::parse;
+
+    fn entry(content: &str) -> Entry {
+        let s = format!(r#"---
+[imag]
+links = ["project/imag"]
+version = "0.2.0"
+tags = ["work/clientA"]
+
+[note]
+name = "meeting with clientA"
+priority = 3
+date = "2016-10-12T10:00"
+---
+{}"#, content);
+
+        Entry::from_str(StoreId::new_baseless(PathBuf::from("test/filter")).unwrap(), &s).unwrap()
+    }
+
+    fn matches(expr: &str, e: &Entry) -> bool {
+        parse(expr).unwrap().filter(e)
+    }
+
+    #[test]
+    fn test_header_predicates() {
+        let e = entry("");
+
+        assert!(matches("header.note.name ~ \"^meet\"", &e));
+        assert!(matches("header.note.priority = 3", &e));
+        assert!(matches("header.note.priority != 4", &e));
+        assert!(matches("header.note.priority > 2", &e));
+        assert!(matches("header.note.priority >= 3", &e));
+        assert!(!matches("header.note.priority < 3", &e));
+        assert!(matches("header.note.name is string", &e));
+    }
+
+    #[test]
+    fn test_combined() {
+        let e = entry("short");
+
+        assert!(matches("header.note.name ~ \"^meet\" and (tag:work or not content.len > 1000)", &e));
+        assert!(matches("tag:private or content ~ \"sho\"", &e));
+        assert!(!matches("tag:work and not content ~ short", &e));
+        assert!(matches("version in 0.1.0..0.3.0 and version > 0.1.0", &e));
+    }
+
+    #[test]
+    fn test_links() {
+        let e = entry("");
+
+        assert!(matches("links-to:project/imag and links-to:project/*", &e));
+        assert!(!matches("links-to:project/other or unlinked or annotation", &e));
+    }
+
+    #[test]
+    fn test_link_count() {
+        let e = entry("");
+
+        assert!(matches("links.count in 1..1", &e));
+        assert!(matches("links.count in 0..", &e));
+        assert!(!matches("links.count in 2..", &e));
+        assert!(!matches("links.count in 0..0", &e));
+
+        assert!(parse("links.count in x..2").is_err());
+        assert!(parse("links.count in 1").is_err());
+        assert!(parse("links.count = 1").is_err());
+    }
+
+    #[test]
+    fn test_links_external() {
+        use url::Url;
+
+        use libimagstore::store::Store;
+        use libimagentrylink::external::ExternalLinker;
+
+        use super::parse_with_store;
+
+        let store = Store::new(PathBuf::from("/"), None).unwrap();
+        {
+            let mut e = store.retrieve(PathBuf::from("test/external")).unwrap();
+            let url   = Url::parse("https://blog.example.com/post").unwrap();
+            assert!(e.add_external_link(&store, url).is_ok());
+        }
+        let e = store.retrieve_copy(PathBuf::from("test/external")).unwrap();
+
+        assert!(parse_with_store("links-external:example.com", &store).unwrap().filter(&e));
+        assert!(parse_with_store("links-external:blog.example.com", &store).unwrap().filter(&e));
+        assert!(!parse_with_store("links-external:ample.com", &store).unwrap().filter(&e));
+        assert!(!parse_with_store("links-external:example.org", &store).unwrap().filter(&e));
+
+        assert!(parse_with_store("links-external:", &store).is_err());
+        assert!(parse("links-external:example.com").is_err());
+    }
+
+    #[test]
+    fn test_dates() {
+        let e = entry("");
+
+        assert!(matches("header.note.date after 2016-10-01 and header.note.date before \"2016-10-13T12\"", &e));
+        assert!(matches("header.note.date within 2016-10-01..2016-11-01", &e));
+        assert!(!matches("header.note.date within \"last 2 days\"", &e));
+        assert!(parse("header.note.date within \"last fortnight\"").is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse("header.a.b").err().map(|e| e.position), Some(10));
+        assert_eq!(parse("foo = 1").err().map(|e| e.position), Some(0));
+        assert_eq!(parse("(tag:a or tag:b").err().map(|e| e.position), Some(15));
+        assert_eq!(parse("tag:a tag:b").err().map(|e| e.position), Some(6));
+        assert!(parse("header.a ~ \"(\"").is_err());
+        assert!(parse("content.len > x").is_err());
+    }
+
+}
@@ -32,23 +32,30 @@
     while_true,
 )]
 
+extern crate chrono;
+extern crate clap;
 extern crate filters;
+extern crate glob;
 extern crate itertools;
 extern crate regex;
 extern crate semver;
 extern crate toml;
+extern crate url;
 
 extern crate libimagstore;
+extern crate libimagentrylink;
 extern crate libimagentrytag;
+extern crate libimagtimeui;
 
 // core functionality modules of the crate,
 // these depend only on libimagstore
 
-pub mod cli;
 pub mod builtin;
 
 // extended functionality of the crate
 // these depend on other internal libraries than libimagstore and use the upper core modules for
 // their functionality
 
+pub mod cli;
+pub mod links;
 pub mod tags;
@@ -0,0 +1,191 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Filters on the links of an entry, see `libimagentrylink`
+
+use glob::Pattern;
+use toml::Value;
+use url::Url;
+
+use libimagstore::store::Entry;
+use libimagstore::access::StoreAccess;
+use libimagstore::storeid::StoreId;
+use libimagstore::toml_ext::TomlValueExt;
+use libimagentrylink::external::ExternalLinker;
+use libimagentrylink::external::is_external_link_storeid;
+use libimagentrylink::internal::InternalLinker;
+
+use filters::filter::Filter;
+
+/// Check whether an Entry links to a certain entry
+pub struct LinksTo {
+    id: StoreId,
+}
+
+impl LinksTo {
+
+    pub fn new(id: StoreId) -> LinksTo {
+        LinksTo {
+            id: id,
+        }
+    }
+
+}
+
+impl Filter<Entry> for LinksTo {
+
+    fn filter(&self, e: &Entry) -> bool {
+        e.get_internal_links()
+            .map(|mut links| links.any(|l| l.local() == self.id.local()))
+            .unwrap_or(false)
+    }
+
+}
+
+/// Check whether an Entry links to an entry with an id matching a glob, like `project/*`
+pub struct LinksToGlob {
+    pattern: Pattern,
+}
+
+impl LinksToGlob {
+
+    pub fn new(pattern: Pattern) -> LinksToGlob {
+        LinksToGlob {
+            pattern: pattern,
+        }
+    }
+
+}
+
+impl Filter<Entry> for LinksToGlob {
+
+    fn filter(&self, e: &Entry) -> bool {
+        e.get_internal_links()
+            .map(|mut links| {
+                links.any(|l| !is_external_link_storeid(&l) && self.pattern.matches_path(l.local()))
+            })
+            .unwrap_or(false)
+    }
+
+}
+
+/// Check whether the number of links of an Entry is in a range, including both bounds
+///
+/// Internal and external links are counted.
+pub struct LinkCountInRange {
+    min: usize,
+    max: Option<usize>,
+}
+
+impl LinkCountInRange {
+
+    /// The range `min..max`, without upper bound if `max` is `None`
+    pub fn new(min: usize, max: Option<usize>) -> LinkCountInRange {
+        LinkCountInRange {
+            min: min,
+            max: max,
+        }
+    }
+
+    /// Matches entries without any link
+    pub fn unlinked() -> LinkCountInRange {
+        LinkCountInRange::new(0, Some(0))
+    }
+
+}
+
+impl Filter<Entry> for LinkCountInRange {
+
+    fn filter(&self, e: &Entry) -> bool {
+        e.get_internal_links()
+            .map(|links| {
+                let n = links.count();
+                n >= self.min && self.max.map(|max| n <= max).unwrap_or(true)
+            })
+            .unwrap_or(false)
+    }
+
+}
+
+/// Check whether an Entry has an external link to a domain or one of its subdomains
+///
+/// The URLs of external links are stored in their own entries, so this filter needs the store to
+/// look them up.
+pub struct HasExternalLinkToDomain<'a> {
+    store: &'a StoreAccess,
+    domain: String,
+}
+
+impl<'a> HasExternalLinkToDomain<'a> {
+
+    pub fn new(store: &'a StoreAccess, domain: String) -> HasExternalLinkToDomain<'a> {
+        HasExternalLinkToDomain {
+            store: store,
+            domain: domain.to_lowercase(),
+        }
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        url.host_str()
+            .map(|host| {
+                host == self.domain ||
+                    (host.ends_with(&self.domain[..]) &&
+                     host[..host.len() - self.domain.len()].ends_with('.'))
+            })
+            .unwrap_or(false)
+    }
+
+}
+
+impl<'a> Filter<Entry> for HasExternalLinkToDomain<'a> {
+
+    fn filter(&self, e: &Entry) -> bool {
+        e.get_external_links(self.store)
+            .map(|mut urls| urls.any(|url| url.map(|url| self.matches(&url)).unwrap_or(false)))
+            .unwrap_or(false)
+    }
+
+}
+
+/// Check whether an Entry is an annotation of another entry
+///
+/// That is the case if the header field `annotation.is_annotation` is set, as done by
+/// libimagannotation, or if the entry has a link with the relation "annotation-of".
+pub struct IsAnnotation;
+
+impl IsAnnotation {
+
+    pub fn new() -> IsAnnotation {
+        IsAnnotation
+    }
+
+}
+
+impl Filter<Entry> for IsAnnotation {
+
+    fn filter(&self, e: &Entry) -> bool {
+        let flagged = match e.get_header().read("annotation.is_annotation") {
+            Ok(Some(Value::Boolean(b))) => b,
+            _ => false,
+        };
+
+        flagged || e.get_links_by_relation("annotation-of").map(|mut l| l.next().is_some()).unwrap_or(false)
+    }
+
+}
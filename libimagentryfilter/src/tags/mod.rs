@@ -24,6 +24,8 @@ use libimagentrytag::tag::Tag;
 use filters::filter::Filter;
 
 /// Check whether an Entry has a certain tag
+///
+/// Tags are hierarchical, so the filter for `work` also matches entries tagged `work/clientA`.
 pub struct HasTag {
     tag: Tag,
 }
@@ -41,7 +43,7 @@ impl HasTag {
 impl Filter<Entry> for HasTag {
 
     fn filter(&self, e: &Entry) -> bool {
-        e.has_tag(&self.tag).ok().unwrap_or(false)
+        e.has_tag_or_descendant(&self.tag).ok().unwrap_or(false)
     }
 
 }
@@ -65,7 +67,7 @@ impl HasAllTags {
 impl Filter<Entry> for HasAllTags {
 
     fn filter(&self, e: &Entry) -> bool {
-        e.has_tags(&self.tags).ok().unwrap_or(false)
+        e.has_tags_or_descendants(&self.tags).ok().unwrap_or(false)
     }
 
 }
@@ -89,7 +91,7 @@ impl HasAnyTags {
 impl Filter<Entry> for HasAnyTags {
 
     fn filter(&self, e: &Entry) -> bool {
-        self.tags.iter().any(|tag| e.has_tag(tag).ok().unwrap_or(false))
+        self.tags.iter().any(|tag| e.has_tag_or_descendant(tag).ok().unwrap_or(false))
     }
 
 }
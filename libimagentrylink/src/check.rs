@@ -0,0 +1,340 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Consistency checks for internal links
+//!
+//! Linking A to B also links B to A, but the two entries are written back one after the other.
+//! If imag is interrupted in between, or an entry is edited by hand, this symmetry can break. The
+//! functions in this module find such links and repair them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::PathBuf;
+
+use toml::Value;
+
+use libimagstore::access::StoreAccess;
+use libimagstore::store::FileLockEntry;
+use libimagstore::storeid::StoreId;
+use libimagerror::into::IntoError;
+
+use error::LinkErrorKind as LEK;
+use error::MapErrInto;
+use internal::{InternalLinker, Link, TypedLink, remove_link_to, reverse_relation};
+use result::Result;
+
+/// A problem with the links of an entry
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkProblem {
+    /// `from` links to `to`, but `to` does not link back
+    Asymmetric {
+        from: Link,
+        to: Link,
+    },
+
+    /// `from` links to `to`, which does not exist
+    Dangling {
+        from: Link,
+        to: Link,
+    },
+
+    /// `from` has the relation `relation` to `to`, but `to` does not have the reverse relation
+    AsymmetricRelation {
+        from: Link,
+        to: Link,
+        relation: String,
+        attributes: BTreeMap<String, Value>,
+    },
+}
+
+/// How links and relations which are not symmetric are repaired
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repair {
+    /// Add the missing link or relation to the other entry
+    Complete,
+
+    /// Remove the link or relation from the entry which has it
+    Remove,
+}
+
+impl LinkProblem {
+
+    /// Repair the problem
+    ///
+    /// Links and relations which are not symmetric are completed or removed, depending on `mode`.
+    /// Links to entries which do not exist are always removed.
+    pub fn repair(&self, store: &StoreAccess, mode: Repair) -> Result<()> {
+        match *self {
+            LinkProblem::Asymmetric { ref from, ref to } => {
+                let mut from = try!(get_entry(store, from));
+                match mode {
+                    Repair::Complete => {
+                        let mut to = try!(get_entry(store, to));
+                        to.add_internal_link(&mut from)
+                    },
+                    Repair::Remove => remove_link_to(&mut from, to),
+                }
+            },
+
+            LinkProblem::Dangling { ref from, ref to } => {
+                let mut from = try!(get_entry(store, from));
+                remove_link_to(&mut from, to)
+            },
+
+            LinkProblem::AsymmetricRelation { ref from, ref to, ref relation, ref attributes } => {
+                let mut from = try!(get_entry(store, from));
+                let mut to   = try!(get_entry(store, to));
+                match mode {
+                    Repair::Complete => from.add_typed_link(&mut to, relation, attributes.clone()),
+                    Repair::Remove   => from.remove_typed_link(&mut to, relation),
+                }
+            },
+        }
+    }
+
+}
+
+impl Display for LinkProblem {
+
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            LinkProblem::Asymmetric { ref from, ref to } => {
+                write!(fmt, "asymmetric: {} -> {}, but no link back", show(from), show(to))
+            },
+            LinkProblem::Dangling { ref from, ref to } => {
+                write!(fmt, "dangling:   {} -> {}, which does not exist", show(from), show(to))
+            },
+            LinkProblem::AsymmetricRelation { ref from, ref to, ref relation, .. } => {
+                write!(fmt, "relation:   {} -[{}]-> {}, but no '{}' back",
+                       show(from), relation, show(to), reverse_relation(relation))
+            },
+        }
+    }
+
+}
+
+fn show(id: &Link) -> String {
+    id.local().display().to_string()
+}
+
+fn get_entry<'a>(store: &'a StoreAccess, id: &Link) -> Result<FileLockEntry<'a>> {
+    store.get(id.clone())
+        .map_err_into(LEK::StoreReadError)
+        .and_then(|e| e.ok_or(LEK::LinkTargetDoesNotExist.into_error()))
+}
+
+/// Checker for the links of entries, which caches the links of the entries it loaded
+pub struct LinkChecker<'a> {
+    store: &'a StoreAccess,
+    cache: HashMap<PathBuf, Option<Vec<TypedLink>>>,
+}
+
+impl<'a> LinkChecker<'a> {
+
+    pub fn new(store: &'a StoreAccess) -> LinkChecker<'a> {
+        LinkChecker {
+            store: store,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Get the links of an entry, `None` if the entry does not exist
+    fn links_of(&mut self, id: &StoreId) -> Result<Option<Vec<TypedLink>>> {
+        let key = id.local().clone();
+        if let Some(links) = self.cache.get(&key) {
+            return Ok(links.clone());
+        }
+
+        let links = if try!(self.store.exists(id.clone()).map_err_into(LEK::StoreReadError)) {
+            let entry = try!(self.store
+                             .retrieve_copy(id.clone().without_base())
+                             .map_err_into(LEK::StoreReadError));
+            Some(try!(entry.get_typed_links()))
+        } else {
+            None
+        };
+
+        self.cache.insert(key, links.clone());
+        Ok(links)
+    }
+
+    /// Check the links of the entry `id`
+    pub fn check(&mut self, id: &StoreId) -> Result<Vec<LinkProblem>> {
+        let from = id.clone().without_base();
+        let mut problems = vec![];
+
+        let links = match try!(self.links_of(&from)) {
+            Some(links) => links,
+            None        => return Ok(problems),
+        };
+
+        for link in links {
+            let back = match try!(self.links_of(&link.link)) {
+                None => {
+                    let problem = LinkProblem::Dangling { from: from.clone(), to: link.link.clone() };
+                    if !problems.contains(&problem) {
+                        problems.push(problem);
+                    }
+                    continue;
+                },
+                Some(back) => back,
+            };
+
+            if !back.iter().any(|b| b.link == from) {
+                let problem = LinkProblem::Asymmetric { from: from.clone(), to: link.link.clone() };
+                if !problems.contains(&problem) {
+                    problems.push(problem);
+                }
+            }
+
+            if let Some(relation) = link.relation {
+                let reverse = reverse_relation(&relation);
+                let has_reverse = back
+                    .iter()
+                    .any(|b| b.link == from && b.relation.as_ref() == Some(&reverse));
+
+                if !has_reverse {
+                    problems.push(LinkProblem::AsymmetricRelation {
+                        from: from.clone(),
+                        to: link.link,
+                        relation: relation,
+                        attributes: link.attributes,
+                    });
+                }
+            }
+        }
+
+        Ok(problems)
+    }
+
+    /// Check the links of all entries `ids`
+    pub fn check_all<I: Iterator<Item = StoreId>>(&mut self, ids: I) -> Result<Vec<LinkProblem>> {
+        let mut problems = vec![];
+        for id in ids {
+            problems.extend(try!(self.check(&id)));
+        }
+        Ok(problems)
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::store::Store;
+    use libimagstore::storeid::StoreId;
+
+    use internal::{InternalLinker, remove_link_to};
+    use super::*;
+
+    fn setup_logging() {
+        use env_logger;
+        let _ = env_logger::init().unwrap_or(());
+    }
+
+    pub fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None).unwrap()
+    }
+
+    fn id(s: &str) -> StoreId {
+        StoreId::new_baseless(PathBuf::from(s)).unwrap()
+    }
+
+    #[test]
+    fn test_symmetric_links_are_fine() {
+        setup_logging();
+        let store = get_store();
+        {
+            let mut a = store.retrieve(PathBuf::from("check_fine_a")).unwrap();
+            let mut b = store.retrieve(PathBuf::from("check_fine_b")).unwrap();
+            assert!(a.add_internal_link(&mut b).is_ok());
+        }
+
+        let problems = LinkChecker::new(&store).check(&id("check_fine_a")).unwrap();
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn test_asymmetric_link_repair() {
+        setup_logging();
+        let store = get_store();
+        {
+            let mut a = store.retrieve(PathBuf::from("check_asym_a")).unwrap();
+            let mut b = store.retrieve(PathBuf::from("check_asym_b")).unwrap();
+            assert!(a.add_internal_link(&mut b).is_ok());
+            assert!(remove_link_to(&mut b, &id("check_asym_a")).is_ok());
+        }
+
+        let problems = LinkChecker::new(&store).check(&id("check_asym_a")).unwrap();
+        assert_eq!(problems, vec![LinkProblem::Asymmetric {
+            from: id("check_asym_a"),
+            to: id("check_asym_b"),
+        }]);
+
+        assert!(problems[0].repair(&store, Repair::Complete).is_ok());
+        assert!(LinkChecker::new(&store).check(&id("check_asym_a")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_asymmetric_link_repair_by_removing() {
+        setup_logging();
+        let store = get_store();
+        {
+            let mut a = store.retrieve(PathBuf::from("check_asym_rm_a")).unwrap();
+            let mut b = store.retrieve(PathBuf::from("check_asym_rm_b")).unwrap();
+            assert!(a.add_internal_link(&mut b).is_ok());
+            assert!(remove_link_to(&mut b, &id("check_asym_rm_a")).is_ok());
+        }
+
+        let problems = LinkChecker::new(&store).check(&id("check_asym_rm_a")).unwrap();
+        assert_eq!(problems.len(), 1);
+
+        assert!(problems[0].repair(&store, Repair::Remove).is_ok());
+        assert!(LinkChecker::new(&store).check(&id("check_asym_rm_a")).unwrap().is_empty());
+
+        let a = store.retrieve(PathBuf::from("check_asym_rm_a")).unwrap();
+        let b = store.retrieve(PathBuf::from("check_asym_rm_b")).unwrap();
+        assert_eq!(a.get_internal_links().unwrap().count(), 0);
+        assert_eq!(b.get_internal_links().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_dangling_link_repair() {
+        setup_logging();
+        let store = get_store();
+        {
+            let mut a = store.retrieve(PathBuf::from("check_dangling_a")).unwrap();
+            let mut b = store.retrieve(PathBuf::from("check_dangling_b")).unwrap();
+            assert!(a.add_internal_link(&mut b).is_ok());
+        }
+        assert!(store.get(id("check_dangling_b")).is_ok());
+        assert!(store.delete(id("check_dangling_b")).is_ok());
+
+        let problems = LinkChecker::new(&store).check(&id("check_dangling_a")).unwrap();
+        assert_eq!(problems, vec![LinkProblem::Dangling {
+            from: id("check_dangling_a"),
+            to: id("check_dangling_b"),
+        }]);
+
+        assert!(problems[0].repair(&store, Repair::Complete).is_ok());
+        assert!(LinkChecker::new(&store).check(&id("check_dangling_a")).unwrap().is_empty());
+    }
+
+}
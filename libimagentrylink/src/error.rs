@@ -27,7 +27,10 @@ generate_error_module!(
         InvalidUri              => "URI is not valid",
         StoreReadError          => "Store read error",
         StoreWriteError         => "Store write error",
-        StoreIdError            => "StoreId handling error"
+        StoreIdError            => "StoreId handling error",
+        GraphExportError        => "Error while exporting the link graph",
+        LinkRewriteError        => "Error while rewriting links to a moved entry",
+        HttpRequestError        => "Error while requesting an external link"
     );
 );
 
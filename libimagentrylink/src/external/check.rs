@@ -0,0 +1,346 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Checking whether external links are still reachable
+//!
+//! The links are checked with a HEAD request, falling back to GET if the server does not support
+//! HEAD. The result of the check is written to the `imag.content.check` section of the link
+//! entry.
+//!
+//! Requests are done by an `HttpClient`, so this module does not depend on a specific HTTP
+//! library, and tests can use a stub client.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use toml::Value;
+use url::Url;
+use chrono::Local;
+use chrono::naive::datetime::NaiveDateTime;
+
+use libimagstore::access::StoreAccess;
+use libimagstore::ownership::LibrarySection;
+use libimagstore::storeid::StoreId;
+use libimagstore::toml_ext::TomlValueExt;
+use libimagerror::into::IntoError;
+
+use error::LinkErrorKind as LEK;
+use error::MapErrInto;
+use result::Result;
+use super::{ADDED_DATE_FORMAT, is_external_link_storeid, read_string};
+
+/// The response to a request, as far as the link checker is interested in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+
+    /// The value of the `Location` header, if any
+    pub location: Option<String>,
+}
+
+/// A HTTP client, which does not follow redirects
+///
+/// Timeouts are up to the client. Errors should be returned as
+/// `LinkErrorKind::HttpRequestError`.
+pub trait HttpClient : Send + Sync {
+    fn head(&self, url: &Url) -> Result<HttpResponse>;
+    fn get(&self, url: &Url) -> Result<HttpResponse>;
+}
+
+/// The state of an external link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkStatus {
+    /// The server answered with a success status code
+    Alive(u16),
+
+    /// The server redirects to another URL
+    Redirect(u16, String),
+
+    /// The server answered with an error status code
+    Dead(u16),
+
+    /// The server could not be reached
+    Unreachable(String),
+}
+
+impl LinkStatus {
+
+    fn from_response(res: HttpResponse) -> LinkStatus {
+        match (res.status, res.location) {
+            (s @ 200...299, _)          => LinkStatus::Alive(s),
+            (s @ 300...399, Some(loc))  => LinkStatus::Redirect(s, loc),
+            (s @ 300...399, None)       => LinkStatus::Alive(s),
+            (s, _)                      => LinkStatus::Dead(s),
+        }
+    }
+
+    /// Whether the link is dead or unreachable
+    pub fn is_broken(&self) -> bool {
+        match *self {
+            LinkStatus::Dead(_) | LinkStatus::Unreachable(_) => true,
+            _ => false,
+        }
+    }
+
+}
+
+impl Display for LinkStatus {
+
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            LinkStatus::Alive(s)              => write!(fmt, "ok ({})", s),
+            LinkStatus::Redirect(s, ref loc)  => write!(fmt, "redirect ({}) to {}", s, loc),
+            LinkStatus::Dead(s)               => write!(fmt, "dead ({})", s),
+            LinkStatus::Unreachable(ref e)    => write!(fmt, "unreachable: {}", e),
+        }
+    }
+
+}
+
+/// The result of checking one external link
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// The id of the link entry
+    pub id: StoreId,
+    pub url: Url,
+    pub status: LinkStatus,
+    pub checked: NaiveDateTime,
+}
+
+/// Check a single URL
+pub fn check_url<C: HttpClient + ?Sized>(client: &C, url: &Url) -> LinkStatus {
+    let res = client.head(url).and_then(|res| match res.status {
+        // HEAD is not allowed or not implemented, try again with GET
+        405 | 501 => client.get(url),
+        _         => Ok(res),
+    });
+
+    match res {
+        Ok(res) => LinkStatus::from_response(res),
+        Err(e)  => LinkStatus::Unreachable(format!("{}", e)),
+    }
+}
+
+/// Get the ids and URLs of all external links in the store
+pub fn all_external_links(store: &StoreAccess) -> Result<Vec<(StoreId, Url)>> {
+    let mut links = vec![];
+
+    let ids = try!(store.retrieve_for_module("links").map_err_into(LEK::StoreReadError));
+    for id in ids.filter(is_external_link_storeid) {
+        let entry = try!(store.retrieve_copy(id.clone()).map_err_into(LEK::StoreReadError));
+
+        match try!(read_string(entry.get_header(), "imag.content.url")) {
+            Some(url) => match Url::parse(&url) {
+                Ok(url) => links.push((id, url)),
+                Err(_)  => warn!("Link entry {:?} contains invalid URL '{}', ignoring", id, url),
+            },
+            None => warn!("Link entry {:?} has no URL, ignoring", id),
+        }
+    }
+
+    Ok(links)
+}
+
+/// Check the `links` with up to `jobs` requests at once
+///
+/// The results are in the order of `links`.
+pub fn check_links<C>(client: Arc<C>, links: Vec<(StoreId, Url)>, jobs: usize) -> Vec<CheckResult>
+    where C: HttpClient + 'static
+{
+    let count   = links.len();
+    let queue   = Arc::new(Mutex::new(links.into_iter().enumerate().collect::<Vec<_>>()));
+    let results = Arc::new(Mutex::new(Vec::with_capacity(count)));
+
+    let workers = (0..::std::cmp::max(jobs, 1))
+        .map(|_| {
+            let client  = client.clone();
+            let queue   = queue.clone();
+            let results = results.clone();
+
+            thread::spawn(move || loop {
+                let next = match queue.lock() {
+                    Ok(mut queue) => queue.pop(),
+                    Err(_)        => None,
+                };
+
+                let (i, (id, url)) = match next {
+                    Some(next) => next,
+                    None       => break,
+                };
+
+                debug!("Checking {}", url);
+                let status = check_url(&*client, &url);
+                let result = CheckResult {
+                    id: id,
+                    url: url,
+                    status: status,
+                    checked: Local::now().naive_local(),
+                };
+
+                if let Ok(mut results) = results.lock() {
+                    results.push((i, result));
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for worker in workers {
+        if worker.join().is_err() {
+            warn!("Link checker thread panicked");
+        }
+    }
+
+    let mut results = match Arc::try_unwrap(results) {
+        Ok(results) => results.into_inner().unwrap_or_else(|e| e.into_inner()),
+        Err(_)      => unreachable!(), // all workers are joined
+    };
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// Write the result of a check to the `imag.content.check` section of the link entry
+pub fn record_result(store: &StoreAccess, result: &CheckResult) -> Result<()> {
+    let mut entry = match try!(store.get(result.id.clone()).map_err_into(LEK::StoreReadError)) {
+        Some(entry) => entry,
+        None        => return Err(LEK::LinkTargetDoesNotExist.into_error()),
+    };
+
+    let mut check = BTreeMap::new();
+    let checked   = result.checked.format(ADDED_DATE_FORMAT).to_string();
+    check.insert(String::from("last-checked"), Value::String(checked));
+
+    match result.status {
+        LinkStatus::Alive(s) | LinkStatus::Dead(s) => {
+            check.insert(String::from("status"), Value::Integer(s as i64));
+        },
+        LinkStatus::Redirect(s, ref target) => {
+            check.insert(String::from("status"), Value::Integer(s as i64));
+            check.insert(String::from("redirect"), Value::String(target.clone()));
+        },
+        LinkStatus::Unreachable(ref e) => {
+            check.insert(String::from("error"), Value::String(e.clone()));
+        },
+    }
+
+    entry.grant_section_write(LibrarySection::ExternalLinkContent);
+    entry.get_header_mut()
+        .set("imag.content.check", Value::Table(check))
+        .map(|_| ())
+        .map_err_into(LEK::EntryHeaderWriteError)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use url::Url;
+
+    use libimagstore::storeid::StoreId;
+    use libimagerror::into::IntoError;
+
+    use error::LinkErrorKind as LEK;
+    use result::Result;
+    use super::*;
+
+    /// A client which answers from a table of URLs, and fails for all other URLs
+    struct StubClient {
+        head: HashMap<String, HttpResponse>,
+        get: HashMap<String, HttpResponse>,
+    }
+
+    impl StubClient {
+        fn answer(map: &HashMap<String, HttpResponse>, url: &Url) -> Result<HttpResponse> {
+            map.get(url.as_str()).cloned().ok_or(LEK::HttpRequestError.into_error())
+        }
+    }
+
+    impl HttpClient for StubClient {
+        fn head(&self, url: &Url) -> Result<HttpResponse> {
+            StubClient::answer(&self.head, url)
+        }
+
+        fn get(&self, url: &Url) -> Result<HttpResponse> {
+            StubClient::answer(&self.get, url)
+        }
+    }
+
+    fn response(status: u16, location: Option<&str>) -> HttpResponse {
+        HttpResponse {
+            status: status,
+            location: location.map(String::from),
+        }
+    }
+
+    fn stub() -> StubClient {
+        let mut head = HashMap::new();
+        let mut get  = HashMap::new();
+
+        head.insert(String::from("http://alive.example/"), response(200, None));
+        head.insert(String::from("http://dead.example/"), response(404, None));
+        head.insert(String::from("http://moved.example/"), response(301, Some("http://new.example/")));
+        head.insert(String::from("http://nohead.example/"), response(405, None));
+        get.insert(String::from("http://nohead.example/"), response(200, None));
+
+        StubClient { head: head, get: get }
+    }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_check_url() {
+        let client = stub();
+
+        assert_eq!(check_url(&client, &url("http://alive.example/")), LinkStatus::Alive(200));
+        assert_eq!(check_url(&client, &url("http://dead.example/")), LinkStatus::Dead(404));
+        assert_eq!(check_url(&client, &url("http://moved.example/")),
+                   LinkStatus::Redirect(301, String::from("http://new.example/")));
+        assert_eq!(check_url(&client, &url("http://nohead.example/")), LinkStatus::Alive(200));
+        assert!(check_url(&client, &url("http://unknown.example/")).is_broken());
+    }
+
+    #[test]
+    fn test_check_links_keeps_order() {
+        let urls = vec![
+            "http://alive.example/",
+            "http://dead.example/",
+            "http://moved.example/",
+            "http://unknown.example/",
+        ];
+
+        let links = urls.iter()
+            .enumerate()
+            .map(|(i, u)| (StoreId::new_baseless(PathBuf::from(format!("links/external/{}", i))).unwrap(), url(u)))
+            .collect();
+
+        let results = check_links(Arc::new(stub()), links, 3);
+
+        assert_eq!(results.len(), 4);
+        for (result, u) in results.iter().zip(urls.iter()) {
+            assert_eq!(result.url.as_str(), *u);
+        }
+        assert_eq!(results.iter().filter(|r| r.status.is_broken()).count(), 2);
+    }
+
+}
@@ -0,0 +1,771 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+/// External linking is a complex implementation to be able to serve a clean and easy-to-use
+/// interface.
+///
+/// Internally, there are no such things as "external links" (plural). Each Entry in the store can
+/// only have _one_ external link.
+///
+/// This library does the following therefor: It allows you to have several external links with one
+/// entry, which are internally one file in the store for each link, linked with "internal
+/// linking".
+///
+/// This helps us greatly with deduplication of URLs. URLs are normalized (see `normalize_url()`)
+/// before they are hashed, so URLs which differ only trivially end up in the same entry.
+///
+/// The link entries can also carry metadata about the link (see `LinkMetadata`), and as they link
+/// back to all entries linking to the URL, they make the lookup of these entries cheap (see
+/// `entries_linking_to()`).
+///
+
+use std::ops::DerefMut;
+use std::collections::BTreeMap;
+
+use libimagstore::store::Entry;
+use libimagstore::store::FileLockEntry;
+use libimagstore::ownership::LibrarySection;
+use libimagstore::access::StoreAccess;
+use libimagstore::storeid::StoreId;
+use libimagstore::storeid::IntoStoreId;
+use libimagstore::toml_ext::TomlValueExt;
+use libimagutil::debug_result::*;
+
+use error::LinkError as LE;
+use error::LinkErrorKind as LEK;
+use error::MapErrInto;
+use result::Result;
+use internal::InternalLinker;
+use module_path::ModuleEntryPath;
+
+use self::iter::*;
+
+use toml::Value;
+use url::Url;
+use crypto::sha1::Sha1;
+use crypto::digest::Digest;
+use chrono::Local;
+use chrono::naive::datetime::NaiveDateTime;
+
+/// The format of the date a link was added
+const ADDED_DATE_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S";
+
+/// Metadata about an external link
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LinkMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+
+    /// When the link was added to the store. This is set when the link entry is created.
+    pub added: Option<NaiveDateTime>,
+}
+
+/// "Link" Type, just an abstraction over `FileLockEntry` to have some convenience internally.
+pub struct Link<'a> {
+    link: FileLockEntry<'a>
+}
+
+impl<'a> Link<'a> {
+
+    pub fn new(fle: FileLockEntry<'a>) -> Link<'a> {
+        Link { link: fle }
+    }
+
+    /// Get a link Url object from a `FileLockEntry`, ignore errors.
+    fn get_link_uri_from_filelockentry(file: &FileLockEntry<'a>) -> Option<Url> {
+        file.get_header()
+            .read("imag.content.url")
+            .ok()
+            .and_then(|opt| match opt {
+                Some(Value::String(s)) => {
+                    debug!("Found url, parsing: {:?}", s);
+                    Url::parse(&s[..]).ok()
+                },
+                _ => None
+            })
+    }
+
+    pub fn get_url(&self) -> Result<Option<Url>> {
+        let opt = self.link
+            .get_header()
+            .read("imag.content.url");
+
+        match opt {
+            Ok(Some(Value::String(s))) => {
+                Url::parse(&s[..])
+                     .map(Some)
+                     .map_err(|e| LE::new(LEK::EntryHeaderReadError, Some(Box::new(e))))
+            },
+            Ok(None) => Ok(None),
+            _ => Err(LE::new(LEK::EntryHeaderReadError, None))
+        }
+    }
+
+    /// Get the metadata of the link
+    pub fn get_metadata(&self) -> Result<LinkMetadata> {
+        let header = self.link.get_header();
+
+        let title       = try!(read_string(header, "imag.content.title"));
+        let description = try!(read_string(header, "imag.content.description"));
+        let added       = try!(read_string(header, "imag.content.added"));
+        let added       = match added {
+            Some(s) => Some(try!(NaiveDateTime::parse_from_str(&s, ADDED_DATE_FORMAT)
+                                 .map_err(|e| LE::new(LEK::EntryHeaderReadError, Some(Box::new(e)))))),
+            None => None,
+        };
+
+        Ok(LinkMetadata {
+            title: title,
+            description: description,
+            added: added,
+        })
+    }
+
+    /// Set the title and the description of the link
+    ///
+    /// Passing `None` removes the value. The date the link was added is only changed if it is
+    /// passed.
+    pub fn set_metadata(&mut self, meta: LinkMetadata) -> Result<()> {
+        self.link.grant_section_write(LibrarySection::ExternalLinkContent);
+        let header = self.link.get_header_mut();
+
+        try!(write_string(header, "imag.content.title", meta.title));
+        try!(write_string(header, "imag.content.description", meta.description));
+        if let Some(added) = meta.added {
+            let added = Some(added.format(ADDED_DATE_FORMAT).to_string());
+            try!(write_string(header, "imag.content.added", added));
+        }
+        Ok(())
+    }
+
+    /// Get the entries linking to this link
+    pub fn get_linking_entries(&self) -> Result<Vec<StoreId>> {
+        self.link
+            .get_internal_links()
+            .map(|links| links.filter(|l| !is_external_link_storeid(l)).collect())
+    }
+
+}
+
+fn read_string(header: &Value, path: &str) -> Result<Option<String>> {
+    match header.read(path) {
+        Ok(Some(Value::String(s))) => Ok(Some(s)),
+        Ok(None)                   => Ok(None),
+        Ok(Some(_))                => Err(LE::new(LEK::EntryHeaderReadError, None)),
+        Err(e)                     => Err(LE::new(LEK::EntryHeaderReadError, Some(Box::new(e)))),
+    }
+}
+
+fn write_string(header: &mut Value, path: &str, value: Option<String>) -> Result<()> {
+    let result = match value {
+        Some(s) => header.set(path, Value::String(s)),
+        None    => header.delete(path),
+    };
+    result.map(|_| ()).map_err_into(LEK::EntryHeaderWriteError)
+}
+
+/// Normalize an URL, so URLs which point to the same resource are equal
+///
+/// `Url::parse()` lowercases the scheme and the host, removes default ports and gives URLs of
+/// special schemes like `http` a path of `/` if they have none. On top of that, this
+///
+/// * removes the trailing dot of a fully qualified host name,
+/// * gives URLs of all schemes with a host a path of `/` if they have none,
+/// * uppercases the hex digits of percent-encoded octets in the path, query and fragment.
+///
+/// Everything else, including the order of the query parameters and a trailing slash of a
+/// non-empty path, may be significant for the resource and is kept.
+pub fn normalize_url(mut url: Url) -> Url {
+    let host = url.host_str()
+        .map(|h| h.to_lowercase())
+        .map(|h| String::from(h.trim_right_matches('.')));
+    if let Some(host) = host {
+        if !host.is_empty() && Some(&host[..]) != url.host_str() {
+            let _ = url.set_host(Some(&host));
+        }
+    }
+
+    if url.port().is_some() && url.port() == default_port(url.scheme()) {
+        let _ = url.set_port(None);
+    }
+
+    if url.host_str().is_some() && url.path().is_empty() {
+        url.set_path("/");
+    }
+
+    let path = uppercase_percent_encoding(url.path());
+    if path != url.path() {
+        url.set_path(&path);
+    }
+
+    let query = url.query().map(uppercase_percent_encoding);
+    if query.as_ref().map(|q| &q[..]) != url.query() {
+        url.set_query(query.as_ref().map(|q| &q[..]));
+    }
+
+    let fragment = url.fragment().map(uppercase_percent_encoding);
+    if fragment.as_ref().map(|f| &f[..]) != url.fragment() {
+        url.set_fragment(fragment.as_ref().map(|f| &f[..]));
+    }
+
+    url
+}
+
+/// Uppercase the hex digits of percent-encoded octets, as `%2f` and `%2F` are the same octet
+fn uppercase_percent_encoding(s: &str) -> String {
+    let mut out        = String::with_capacity(s.len());
+    let mut hex_digits = 0;
+
+    for c in s.chars() {
+        if hex_digits > 0 && c.is_digit(16) {
+            out.extend(c.to_uppercase());
+            hex_digits -= 1;
+        } else {
+            hex_digits = if c == '%' { 2 } else { 0 };
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws"   => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp"           => Some(21),
+        "gopher"        => Some(70),
+        _               => None,
+    }
+}
+
+/// Get the id of the entry for an URL
+fn link_entry_id(url: &Url) -> Result<StoreId> {
+    hashed_entry_id(normalize_url(url.clone()).as_str())
+}
+
+/// Get the id an entry for an URL had before URLs were normalized
+fn legacy_link_entry_id(url: &Url) -> Result<StoreId> {
+    hashed_entry_id(url.as_str())
+}
+
+fn hashed_entry_id(url: &str) -> Result<StoreId> {
+    let hash = {
+        let mut s = Sha1::new();
+        s.input_str(url);
+        s.result_str()
+    };
+
+    ModuleEntryPath::new(format!("external/{}", hash))
+        .into_storeid()
+        .map_err_into(LEK::StoreWriteError)
+        .map_dbg_err(|_| format!("Failed to build StoreId for this hash '{:?}'", hash))
+}
+
+/// Get the id of the existing entry for an URL
+///
+/// If there is no entry for the normalized URL, the entry for the URL as it is passed is looked
+/// up, as entries written before URLs were normalized are stored under that id.
+fn existing_link_entry_id(store: &StoreAccess, url: &Url) -> Result<Option<StoreId>> {
+    let id = try!(link_entry_id(url));
+    if try!(store.exists(id.clone()).map_err_into(LEK::StoreReadError)) {
+        return Ok(Some(id));
+    }
+
+    let legacy = try!(legacy_link_entry_id(url));
+    if legacy != id && try!(store.exists(legacy.clone()).map_err_into(LEK::StoreReadError)) {
+        debug!("Using the entry {:?} for {:?}, which was written before normalization", legacy, url);
+        return Ok(Some(legacy));
+    }
+
+    Ok(None)
+}
+
+/// Get the entry for an URL, if some entry links to this URL
+pub fn get_external_link<'a>(store: &'a StoreAccess, url: &Url) -> Result<Option<Link<'a>>> {
+    let id = match try!(existing_link_entry_id(store, url)) {
+        Some(id) => id,
+        None     => return Ok(None),
+    };
+
+    store.get(id)
+        .map(|opt| opt.map(Link::new))
+        .map_err_into(LEK::StoreReadError)
+}
+
+/// Get the entries which link to an URL
+///
+/// This does not scan the store, as the entry for the URL links back to these entries.
+pub fn entries_linking_to(store: &StoreAccess, url: &Url) -> Result<Vec<StoreId>> {
+    let id = match try!(existing_link_entry_id(store, url)) {
+        Some(id) => id,
+        None     => return Ok(vec![]),
+    };
+
+    store.retrieve_copy(id)
+        .map_err_into(LEK::StoreReadError)
+        .and_then(|entry| entry.get_internal_links())
+        .map(|links| links.filter(|l| !is_external_link_storeid(l)).collect())
+}
+
+/// Get the entries which link to an URL on the domain `domain` or one of its subdomains
+///
+/// This reads all external link entries, but not the entries linking to them.
+pub fn entries_linking_to_domain(store: &StoreAccess, domain: &str) -> Result<Vec<StoreId>> {
+    let domain    = domain.to_lowercase();
+    let subdomain = format!(".{}", domain);
+    let mut found : Vec<StoreId> = vec![];
+
+    let ids = try!(store.retrieve_for_module("links").map_err_into(LEK::StoreReadError));
+    for id in ids.filter(is_external_link_storeid) {
+        let entry = try!(store.retrieve_copy(id).map_err_into(LEK::StoreReadError));
+        let url   = match try!(read_string(entry.get_header(), "imag.content.url")) {
+            Some(url) => url,
+            None      => continue,
+        };
+
+        let matches = Url::parse(&url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h == domain || h.ends_with(&subdomain)))
+            .unwrap_or(false);
+
+        if matches {
+            for link in try!(entry.get_internal_links()) {
+                if !is_external_link_storeid(&link) && !found.contains(&link) {
+                    found.push(link);
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+pub trait ExternalLinker : InternalLinker {
+
+    /// Get the external links from the implementor object
+    fn get_external_links<'a>(&self, store: &'a StoreAccess) -> Result<UrlIter<'a>>;
+
+    /// Set the external links for the implementor object
+    fn set_external_links(&mut self, store: &StoreAccess, links: Vec<Url>) -> Result<()>;
+
+    /// Add an external link to the implementor object
+    fn add_external_link(&mut self, store: &StoreAccess, link: Url) -> Result<()>;
+
+    /// Remove an external link from the implementor object
+    fn remove_external_link(&mut self, store: &StoreAccess, link: Url) -> Result<()>;
+
+}
+
+pub mod check;
+
+pub mod iter {
+    //! Iterator helpers for external linking stuff
+    //!
+    //! Contains also helpers to filter iterators for external/internal links
+    //!
+    //!
+    //! # Warning
+    //!
+    //! This module uses `internal::Link` as link type, so we operate on _store ids_ here.
+    //!
+    //! Not to confuse with `external::Link` which is a real `FileLockEntry` under the hood.
+    //!
+
+    use libimagutil::debug_result::*;
+    use libimagstore::access::StoreAccess;
+
+    use internal::Link;
+    use internal::iter::LinkIter;
+    use error::LinkErrorKind as LEK;
+    use error::MapErrInto;
+    use result::Result;
+
+    use url::Url;
+
+    /// Helper for building `OnlyExternalIter` and `NoExternalIter`
+    ///
+    /// The boolean value defines, how to interpret the `is_external_link_storeid()` return value
+    /// (here as "pred"):
+    ///
+    ///     pred | bool | xor | take?
+    ///     ---- | ---- | --- | ----
+    ///        0 |    0 |   0 |   1
+    ///        0 |    1 |   1 |   0
+    ///        1 |    0 |   1 |   0
+    ///        1 |    1 |   0 |   1
+    ///
+    /// If `bool` says "take if return value is false", we take the element if the `pred` returns
+    /// false... and so on.
+    ///
+    /// As we can see, the operator between these two operants is `!(a ^ b)`.
+    pub struct ExternalFilterIter(LinkIter, bool);
+
+    impl Iterator for ExternalFilterIter {
+        type Item = Link;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            use super::is_external_link_storeid;
+
+            while let Some(elem) = self.0.next() {
+                if !(self.1 ^ is_external_link_storeid(&elem)) {
+                    return Some(elem);
+                }
+            }
+            None
+        }
+    }
+
+    /// Helper trait to be implemented on `LinkIter` to select or deselect all external links
+    ///
+    /// # See also
+    ///
+    /// Also see `OnlyExternalIter` and `NoExternalIter` and the helper traits/functions
+    /// `OnlyInteralLinks`/`only_internal_links()` and `OnlyExternalLinks`/`only_external_links()`.
+    pub trait SelectExternal {
+        fn select_external_links(self, b: bool) -> ExternalFilterIter;
+    }
+
+    impl SelectExternal for LinkIter {
+        fn select_external_links(self, b: bool) -> ExternalFilterIter {
+            ExternalFilterIter(self, b)
+        }
+    }
+
+
+    pub struct OnlyExternalIter(ExternalFilterIter);
+
+    impl OnlyExternalIter {
+        pub fn new(li: LinkIter) -> OnlyExternalIter {
+            OnlyExternalIter(ExternalFilterIter(li, true))
+        }
+
+        pub fn urls<'a>(self, store: &'a StoreAccess) -> UrlIter<'a> {
+            UrlIter(self, store)
+        }
+    }
+
+    impl Iterator for OnlyExternalIter {
+        type Item = Link;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.next()
+        }
+    }
+
+    pub struct NoExternalIter(ExternalFilterIter);
+
+    impl NoExternalIter {
+        pub fn new(li: LinkIter) -> NoExternalIter {
+            NoExternalIter(ExternalFilterIter(li, false))
+        }
+    }
+
+    impl Iterator for NoExternalIter {
+        type Item = Link;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.next()
+        }
+    }
+
+    pub trait OnlyExternalLinks : Sized {
+        fn only_external_links(self) -> OnlyExternalIter ;
+
+        fn no_internal_links(self) -> OnlyExternalIter {
+            self.only_external_links()
+        }
+    }
+
+    impl OnlyExternalLinks for LinkIter {
+        fn only_external_links(self) -> OnlyExternalIter {
+            OnlyExternalIter::new(self)
+        }
+    }
+
+    pub trait OnlyInternalLinks : Sized {
+        fn only_internal_links(self) -> NoExternalIter;
+
+        fn no_external_links(self) -> NoExternalIter {
+            self.only_internal_links()
+        }
+    }
+
+    impl OnlyInternalLinks for LinkIter {
+        fn only_internal_links(self) -> NoExternalIter {
+            NoExternalIter::new(self)
+        }
+    }
+
+    pub struct UrlIter<'a>(OnlyExternalIter, &'a StoreAccess);
+
+    impl<'a> Iterator for UrlIter<'a> {
+        type Item = Result<Url>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            use super::get_external_link_from_file;
+
+            self.0
+                .next()
+                .map(|id| {
+                    debug!("Retrieving entry for id: '{:?}'", id);
+                    self.1
+                        .retrieve(id.clone())
+                        .map_err_into(LEK::StoreReadError)
+                        .map_dbg_err(|_| format!("Retrieving entry for id: '{:?}' failed", id))
+                        .and_then(|f| {
+                            debug!("Store::retrieve({:?}) succeeded", id);
+                            debug!("getting external link from file now");
+                            get_external_link_from_file(&f)
+                                .map_dbg_err(|e| format!("URL -> Err = {:?}", e))
+                        })
+                })
+        }
+
+    }
+
+}
+
+
+/// Check whether the StoreId starts with `/link/external/`
+pub fn is_external_link_storeid(id: &StoreId) -> bool {
+    debug!("Checking whether this is a 'links/external/': '{:?}'", id);
+    id.local().starts_with("links/external")
+}
+
+fn get_external_link_from_file(entry: &FileLockEntry) -> Result<Url> {
+    Link::get_link_uri_from_filelockentry(entry) // TODO: Do not hide error by using this function
+        .ok_or(LE::new(LEK::StoreReadError, None))
+}
+
+/// Implement `ExternalLinker` for `Entry`, hiding the fact that there is no such thing as an external
+/// link in an entry, but internal links to other entries which serve as external links, as one
+/// entry in the store can only have one external link.
+impl ExternalLinker for Entry {
+
+    /// Get the external links from the implementor object
+    fn get_external_links<'a>(&self, store: &'a StoreAccess) -> Result<UrlIter<'a>> {
+        // Iterate through all internal links and filter for FileLockEntries which live in
+        // /link/external/<SHA> -> load these files and get the external link from their headers,
+        // put them into the return vector.
+        self.get_internal_links()
+            .map_err(|e| LE::new(LEK::StoreReadError, Some(Box::new(e))))
+            .map(|iter| {
+                debug!("Getting external links");
+                iter.only_external_links().urls(store)
+            })
+    }
+
+    /// Set the external links for the implementor object
+    fn set_external_links(&mut self, store: &StoreAccess, links: Vec<Url>) -> Result<()> {
+        // Take all the links, generate a SHA sum out of each one, filter out the already existing
+        // store entries and store the other URIs in the header of one FileLockEntry each, in
+        // the path /link/external/<SHA of the URL>
+
+        debug!("Iterating {} links = {:?}", links.len(), links);
+        for link in links { // for all links
+            let file_id = match try!(existing_link_entry_id(store, &link)) {
+                Some(id) => id,
+                None     => try!(link_entry_id(&link)),
+            };
+            let link    = normalize_url(link);
+
+            debug!("Link    = '{:?}'", link);
+            debug!("StoreId = '{:?}'", file_id);
+
+            // retrieve the file from the store, which implicitely creates the entry if it does not
+            // exist
+            let mut file = try!(store
+                .retrieve(file_id.clone())
+                .map_err_into(LEK::StoreWriteError)
+                .map_dbg_err(|_| {
+                    format!("Failed to create or retrieve an file for this link '{:?}'", link)
+                }));
+
+            debug!("Generating header content!");
+            file.grant_section_write(LibrarySection::ExternalLinkContent);
+            {
+                let mut hdr = file.deref_mut().get_header_mut();
+
+                let mut table = match hdr.read("imag.content") {
+                    Ok(Some(Value::Table(table))) => table,
+                    Ok(Some(_)) => {
+                        warn!("There is a value at 'imag.content' which is not a table.");
+                        warn!("Going to override this value");
+                        BTreeMap::new()
+                    },
+                    Ok(None) => BTreeMap::new(),
+                    Err(e)   => return Err(LE::new(LEK::StoreWriteError, Some(Box::new(e)))),
+                };
+
+                let v = Value::String(link.into_string());
+
+                debug!("setting URL = '{:?}", v);
+                table.insert(String::from("url"), v);
+
+                if !table.contains_key("added") {
+                    let added = Local::now().naive_local().format(ADDED_DATE_FORMAT).to_string();
+                    table.insert(String::from("added"), Value::String(added));
+                }
+
+                if let Err(e) = hdr.set("imag.content", Value::Table(table)) {
+                    return Err(LE::new(LEK::StoreWriteError, Some(Box::new(e))));
+                } else {
+                    debug!("Setting URL worked");
+                }
+            }
+
+            // then add an internal link to the new file or return an error if this fails
+            if let Err(e) = self.add_internal_link(file.deref_mut()) {
+                debug!("Error adding internal link");
+                return Err(LE::new(LEK::StoreWriteError, Some(Box::new(e))));
+            }
+        }
+        debug!("Ready iterating");
+        Ok(())
+    }
+
+    /// Add an external link to the implementor object
+    fn add_external_link(&mut self, store: &StoreAccess, link: Url) -> Result<()> {
+        // get external links, add this one, save them
+        debug!("Getting links");
+        self.get_external_links(store)
+            .and_then(|links| {
+                // TODO: Do not ignore errors here
+                let mut links = links.filter_map(Result::ok).collect::<Vec<_>>();
+                debug!("Adding link = '{:?}' to links = {:?}", link, links);
+                links.push(link);
+                debug!("Setting {} links = {:?}", links.len(), links);
+                self.set_external_links(store, links)
+            })
+    }
+
+    /// Remove an external link from the implementor object
+    fn remove_external_link(&mut self, store: &StoreAccess, link: Url) -> Result<()> {
+        // get external links, remove this one, save them
+        self.get_external_links(store)
+            .and_then(|links| {
+                debug!("Removing link = '{:?}'", link);
+                let link  = normalize_url(link);
+                let links = links
+                    .filter_map(Result::ok)
+                    .filter(|l| normalize_url(l.clone()) != link)
+                    .collect::<Vec<_>>();
+                self.set_external_links(store, links)
+            })
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use url::Url;
+    use crypto::sha1::Sha1;
+    use crypto::digest::Digest;
+
+    use libimagstore::store::Store;
+    use libimagstore::storeid::IntoStoreId;
+    use libimagstore::toml_ext::TomlValueExt;
+    use toml::Value;
+
+    use internal::InternalLinker;
+    use module_path::ModuleEntryPath;
+    use super::{normalize_url, entries_linking_to, ExternalLinker};
+
+    fn normalized(s: &str) -> String {
+        String::from(normalize_url(Url::parse(s).unwrap()).as_str())
+    }
+
+    pub fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(normalized("HTTP://Example.COM:80/a/b"), "http://example.com/a/b");
+        assert_eq!(normalized("https://example.com:443/"), "https://example.com/");
+        assert_eq!(normalized("https://example.com:8443/"), "https://example.com:8443/");
+    }
+
+    #[test]
+    fn test_normalize_url_keeps_path_query_and_fragment() {
+        assert_eq!(normalized("https://example.com/a/"), "https://example.com/a/");
+        assert_eq!(normalized("https://example.com/a#section"), "https://example.com/a#section");
+        assert_eq!(normalized("https://example.com/s?b=2&a=1"), "https://example.com/s?b=2&a=1");
+        assert_eq!(normalized("https://example.com/A/B"), "https://example.com/A/B");
+    }
+
+    #[test]
+    fn test_normalize_url_removes_trailing_dot_of_host() {
+        assert_eq!(normalized("https://Example.COM./a"), "https://example.com/a");
+    }
+
+    #[test]
+    fn test_normalize_url_adds_empty_path_for_all_schemes() {
+        assert_eq!(normalized("ssh://example.com"), "ssh://example.com/");
+        assert_eq!(normalized("ssh://example.com/"), "ssh://example.com/");
+    }
+
+    #[test]
+    fn test_normalize_url_uppercases_percent_encoding() {
+        assert_eq!(normalized("https://example.com/a%2fb?q=%c3%a4#%7e"),
+                   "https://example.com/a%2Fb?q=%C3%A4#%7E");
+        assert_eq!(normalized("https://example.com/100%25"), "https://example.com/100%25");
+    }
+
+    #[test]
+    fn test_entries_linking_to() {
+        let store = get_store();
+        let url   = Url::parse("https://example.com/linking").unwrap();
+        {
+            let mut e = store.retrieve(PathBuf::from("linking_a")).unwrap();
+            assert!(e.add_external_link(&store, url.clone()).is_ok());
+        }
+
+        let url   = Url::parse("HTTPS://EXAMPLE.com:443/linking").unwrap();
+        let found = entries_linking_to(&store, &url).unwrap();
+        assert_eq!(found, vec![PathBuf::from("linking_a").into_storeid().unwrap()]);
+    }
+
+    #[test]
+    fn test_entries_linking_to_finds_entries_written_before_normalization() {
+        let store = get_store();
+        let raw   = "https://example.com/legacy?b=2&a=1#top";
+        let id    = {
+            let mut s = Sha1::new();
+            s.input_str(raw);
+            ModuleEntryPath::new(format!("external/{}", s.result_str())).into_storeid().unwrap()
+        };
+
+        {
+            let mut link = store.retrieve(id).unwrap();
+            let url      = Value::String(String::from(raw));
+            assert!(link.get_header_mut().set("imag.content.url", url).is_ok());
+
+            let mut e = store.retrieve(PathBuf::from("legacy_a")).unwrap();
+            assert!(e.add_internal_link(&mut link).is_ok());
+        }
+
+        let found = entries_linking_to(&store, &Url::parse(raw).unwrap()).unwrap();
+        assert_eq!(found, vec![PathBuf::from("legacy_a").into_storeid().unwrap()]);
+    }
+
+}
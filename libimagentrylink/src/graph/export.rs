@@ -0,0 +1,367 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Export of the link graph as Graphviz DOT or GraphML
+//!
+//! The exported graph contains the passed entries, the entries they link to and the links between
+//! them. Links are undirected, so each link is exported once. If a link has a relation, the
+//! relation is the label of the edge. Entries which are linked with several relations get one edge
+//! per relation.
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use toml::Value;
+
+use libimagstore::access::StoreAccess;
+use libimagstore::store::Entry;
+use libimagstore::storeid::StoreId;
+use libimagstore::toml_ext::TomlValueExt;
+
+use error::LinkErrorKind as LEK;
+use error::MapErrInto;
+use external::is_external_link_storeid;
+use internal::{InternalLinker, reverse_relation};
+use result::Result;
+
+/// The formats the graph can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    GraphML,
+}
+
+impl GraphFormat {
+
+    pub fn from_name(name: &str) -> Option<GraphFormat> {
+        match name {
+            "dot"     => Some(GraphFormat::Dot),
+            "graphml" => Some(GraphFormat::GraphML),
+            _         => None,
+        }
+    }
+
+}
+
+#[derive(Debug)]
+struct Node {
+    id: String,
+    label: String,
+    external: bool,
+}
+
+#[derive(Debug)]
+struct Edge {
+    from: String,
+    to: String,
+    relation: Option<String>,
+}
+
+/// Exporter for the link graph of a set of entries
+pub struct GraphExporter<'a> {
+    store: &'a StoreAccess,
+    label: Option<String>,
+    include_external: bool,
+}
+
+impl<'a> GraphExporter<'a> {
+
+    pub fn new(store: &'a StoreAccess) -> GraphExporter<'a> {
+        GraphExporter {
+            store: store,
+            label: None,
+            include_external: false,
+        }
+    }
+
+    /// Label the nodes with the value at this header path, for example "note.name".
+    ///
+    /// Entries which do not have a value there are labelled with their id.
+    pub fn label_with(mut self, path: String) -> GraphExporter<'a> {
+        self.label = Some(path);
+        self
+    }
+
+    /// Whether external links are exported. They are labelled with their URL.
+    pub fn include_external_links(mut self, b: bool) -> GraphExporter<'a> {
+        self.include_external = b;
+        self
+    }
+
+    /// Export the graph of the entries `ids` to `out`
+    pub fn export<I, W>(&self, ids: I, format: GraphFormat, out: &mut W) -> Result<()>
+        where I: Iterator<Item = StoreId>,
+              W: Write
+    {
+        let (nodes, edges) = try!(self.collect(ids));
+        debug!("Exporting {} nodes and {} edges as {:?}", nodes.len(), edges.len(), format);
+
+        let written = match format {
+            GraphFormat::Dot     => write_dot(out, &nodes, &edges),
+            GraphFormat::GraphML => write_graphml(out, &nodes, &edges),
+        };
+        written.map_err_into(LEK::GraphExportError)
+    }
+
+    fn collect<I: Iterator<Item = StoreId>>(&self, ids: I) -> Result<(Vec<Node>, Vec<Edge>)> {
+        let mut nodes      = vec![];
+        let mut known      = HashSet::new();
+        let mut edges      = vec![];
+        let mut seen_edges = HashSet::new();
+
+        for id in ids {
+            let id    = id.without_base();
+            let name  = try!(id_string(&id));
+            let entry = try!(self.store.retrieve_copy(id).map_err_into(LEK::StoreReadError));
+
+            if known.insert(name.clone()) {
+                nodes.push(self.node(&name, &entry));
+            }
+
+            for link in try!(entry.get_typed_links()) {
+                if !self.include_external && is_external_link_storeid(&link.link) {
+                    continue;
+                }
+
+                let target = try!(id_string(&link.link));
+                if known.insert(target.clone()) {
+                    let target_entry = try!(self.store
+                                            .retrieve_copy(link.link.clone())
+                                            .map_err_into(LEK::StoreReadError));
+                    nodes.push(self.node(&target, &target_entry));
+                }
+
+                // A link is found from both of its entries, with the reverse relation from the
+                // other one, so the key is the relation as seen from the smaller id
+                let key = if name < target {
+                    (name.clone(), target.clone(), link.relation.clone())
+                } else {
+                    (target.clone(), name.clone(), link.relation.as_ref().map(|r| reverse_relation(r)))
+                };
+
+                if seen_edges.insert(key) {
+                    edges.push(Edge {
+                        from: name.clone(),
+                        to: target,
+                        relation: link.relation,
+                    });
+                }
+            }
+        }
+
+        Ok((nodes, edges))
+    }
+
+    fn node(&self, id: &str, entry: &Entry) -> Node {
+        let external = is_external_link_storeid(entry.get_location());
+        let path     = if external {
+            Some("imag.content.url")
+        } else {
+            self.label.as_ref().map(|s| &s[..])
+        };
+
+        let label = path
+            .and_then(|p| entry.get_header().read(p).ok())
+            .and_then(|v| v.and_then(value_to_label))
+            .unwrap_or_else(|| String::from(id));
+
+        Node {
+            id: String::from(id),
+            label: label,
+            external: external,
+        }
+    }
+
+}
+
+fn id_string(id: &StoreId) -> Result<String> {
+    id.clone().without_base().to_str().map_err_into(LEK::StoreIdError)
+}
+
+fn value_to_label(v: Value) -> Option<String> {
+    match v {
+        Value::String(s)   => Some(s),
+        Value::Integer(i)  => Some(format!("{}", i)),
+        Value::Float(f)    => Some(format!("{}", f)),
+        Value::Boolean(b)  => Some(format!("{}", b)),
+        Value::Datetime(s) => Some(s),
+        _                  => None,
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace("\\", "\\\\").replace("\"", "\\\"").replace("\n", "\\n")
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace("&", "&amp;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;")
+        .replace("\"", "&quot;")
+        .replace("'", "&apos;")
+}
+
+fn write_dot<W: Write>(out: &mut W, nodes: &[Node], edges: &[Edge]) -> ::std::io::Result<()> {
+    try!(writeln!(out, "graph imag {{"));
+    for node in nodes {
+        let shape = if node.external { ", shape=box" } else { "" };
+        try!(writeln!(out, "    \"{}\" [label=\"{}\"{}];",
+                      escape_dot(&node.id), escape_dot(&node.label), shape));
+    }
+    for edge in edges {
+        match edge.relation {
+            Some(ref rel) => try!(writeln!(out, "    \"{}\" -- \"{}\" [label=\"{}\"];",
+                                           escape_dot(&edge.from),
+                                           escape_dot(&edge.to),
+                                           escape_dot(rel))),
+            None => try!(writeln!(out, "    \"{}\" -- \"{}\";",
+                                  escape_dot(&edge.from),
+                                  escape_dot(&edge.to))),
+        }
+    }
+    writeln!(out, "}}")
+}
+
+fn write_graphml<W: Write>(out: &mut W, nodes: &[Node], edges: &[Edge]) -> ::std::io::Result<()> {
+    try!(writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+    try!(writeln!(out, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"));
+    try!(writeln!(out, "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>"));
+    try!(writeln!(out, "  <key id=\"external\" for=\"node\" attr.name=\"external\" attr.type=\"boolean\"/>"));
+    try!(writeln!(out, "  <key id=\"relation\" for=\"edge\" attr.name=\"relation\" attr.type=\"string\"/>"));
+    try!(writeln!(out, "  <graph id=\"imag\" edgedefault=\"undirected\">"));
+    for node in nodes {
+        try!(writeln!(out, "    <node id=\"{}\">", escape_xml(&node.id)));
+        try!(writeln!(out, "      <data key=\"label\">{}</data>", escape_xml(&node.label)));
+        try!(writeln!(out, "      <data key=\"external\">{}</data>", node.external));
+        try!(writeln!(out, "    </node>"));
+    }
+    for edge in edges {
+        let (from, to) = (escape_xml(&edge.from), escape_xml(&edge.to));
+        match edge.relation {
+            Some(ref rel) => {
+                try!(writeln!(out, "    <edge source=\"{}\" target=\"{}\">", from, to));
+                try!(writeln!(out, "      <data key=\"relation\">{}</data>", escape_xml(rel)));
+                try!(writeln!(out, "    </edge>"));
+            },
+            None => try!(writeln!(out, "    <edge source=\"{}\" target=\"{}\"/>", from, to)),
+        }
+    }
+    try!(writeln!(out, "  </graph>"));
+    writeln!(out, "</graphml>")
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use toml::Value;
+
+    use libimagstore::store::Store;
+    use libimagstore::storeid::StoreId;
+
+    use internal::InternalLinker;
+    use super::*;
+
+    fn setup_logging() {
+        use env_logger;
+        let _ = env_logger::init().unwrap_or(());
+    }
+
+    pub fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None).unwrap()
+    }
+
+    fn setup(store: &Store, prefix: &str) {
+        let mut a = store.retrieve(PathBuf::from(format!("{}_a", prefix))).unwrap();
+        let mut b = store.retrieve(PathBuf::from(format!("{}_b", prefix))).unwrap();
+
+        let mut note = BTreeMap::new();
+        note.insert(String::from("name"), Value::String(String::from("A \"quoted\"")));
+        match *a.get_header_mut() {
+            Value::Table(ref mut t) => { t.insert(String::from("note"), Value::Table(note)); },
+            _ => panic!("Header is not a table"),
+        }
+
+        assert!(a.add_internal_link(&mut b).is_ok());
+    }
+
+    fn export(store: &Store, prefix: &str, format: GraphFormat) -> String {
+        let ids = vec![StoreId::new_baseless(PathBuf::from(format!("{}_a", prefix))).unwrap()];
+        let mut out = vec![];
+
+        GraphExporter::new(store)
+            .label_with(String::from("note.name"))
+            .export(ids.into_iter(), format, &mut out)
+            .unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_export_dot() {
+        setup_logging();
+        let store = get_store();
+        setup(&store, "export_dot");
+
+        let dot = export(&store, "export_dot", GraphFormat::Dot);
+        assert_eq!(dot, "graph imag {\n\
+                         \x20   \"export_dot_a\" [label=\"A \\\"quoted\\\"\"];\n\
+                         \x20   \"export_dot_b\" [label=\"export_dot_b\"];\n\
+                         \x20   \"export_dot_a\" -- \"export_dot_b\";\n\
+                         }\n");
+    }
+
+    #[test]
+    fn test_export_graphml() {
+        setup_logging();
+        let store = get_store();
+        setup(&store, "export_graphml");
+
+        let graphml = export(&store, "export_graphml", GraphFormat::GraphML);
+        assert!(graphml.contains("<data key=\"label\">A &quot;quoted&quot;</data>"));
+        assert!(graphml.contains("<edge source=\"export_graphml_a\" target=\"export_graphml_b\"/>"));
+    }
+
+    #[test]
+    fn test_export_one_edge_per_relation() {
+        setup_logging();
+        let store = get_store();
+
+        {
+            let mut a = store.retrieve(PathBuf::from("export_relations_a")).unwrap();
+            let mut b = store.retrieve(PathBuf::from("export_relations_b")).unwrap();
+            assert!(a.add_typed_link(&mut b, "parent", BTreeMap::new()).is_ok());
+            assert!(a.add_typed_link(&mut b, "blocks", BTreeMap::new()).is_ok());
+        }
+
+        // Both entries are exported, so each link is found from both sides
+        let ids = vec!["export_relations_a", "export_relations_b"]
+            .into_iter()
+            .map(|id| StoreId::new_baseless(PathBuf::from(id)).unwrap());
+        let mut out = vec![];
+        GraphExporter::new(&store).export(ids, GraphFormat::Dot, &mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+
+        assert_eq!(dot.matches(" -- ").count(), 2);
+        assert!(dot.contains("[label=\"parent\"]"));
+        assert!(dot.contains("[label=\"blocks\"]"));
+    }
+
+}
@@ -0,0 +1,322 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Traversal of the graph the internal links span over the store
+//!
+//! Entries are loaded from the store when their links are needed for the first time, so only the
+//! part of the graph which is actually visited is read.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+
+use libimagstore::access::StoreAccess;
+use libimagstore::storeid::StoreId;
+
+use error::LinkErrorKind as LEK;
+use error::MapErrInto;
+use external::is_external_link_storeid;
+use internal::{InternalLinker, Link};
+use result::Result;
+
+pub mod export;
+
+/// The link graph of a store
+pub struct LinkGraph<'a> {
+    store: &'a StoreAccess,
+    include_external: bool,
+
+    /// Links of the entries loaded so far, by id
+    cache: RefCell<HashMap<PathBuf, Vec<Link>>>,
+}
+
+impl<'a> LinkGraph<'a> {
+
+    pub fn new(store: &'a StoreAccess) -> LinkGraph<'a> {
+        LinkGraph {
+            store: store,
+            include_external: false,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the entries for external links are nodes of the graph. They are not by default.
+    pub fn include_external_links(mut self, b: bool) -> LinkGraph<'a> {
+        self.include_external = b;
+        self
+    }
+
+    /// Get the entries `id` links to
+    pub fn neighbours(&self, id: &StoreId) -> Result<Vec<Link>> {
+        let key = id.local().clone();
+        if let Some(links) = self.cache.borrow().get(&key) {
+            return Ok(links.clone());
+        }
+
+        debug!("Loading links of {:?}", id);
+        let entry = try!(self.store
+                         .retrieve_copy(id.clone().without_base())
+                         .map_err_into(LEK::StoreReadError));
+        let links = try!(entry.get_internal_links())
+            .filter(|l| self.include_external || !is_external_link_storeid(l))
+            .collect::<Vec<_>>();
+
+        self.cache.borrow_mut().insert(key, links.clone());
+        Ok(links)
+    }
+
+    /// Traverse the graph breadth-first, starting at `start`, up to `depth` links away from it.
+    ///
+    /// Returns the reached entries with their distance to `start`, `start` itself not included.
+    pub fn bfs(&self, start: &StoreId, depth: usize) -> Result<Vec<(Link, usize)>> {
+        let mut visited = HashSet::new();
+        let mut queue   = VecDeque::new();
+        let mut result  = vec![];
+
+        visited.insert(start.local().clone());
+        queue.push_back((start.clone().without_base(), 0));
+
+        while let Some((id, dist)) = queue.pop_front() {
+            if dist >= depth {
+                continue;
+            }
+
+            for next in try!(self.neighbours(&id)) {
+                if visited.insert(next.local().clone()) {
+                    result.push((next.clone(), dist + 1));
+                    queue.push_back((next, dist + 1));
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Traverse the graph depth-first, starting at `start`, up to `depth` links away from it.
+    ///
+    /// Returns the reached entries in pre-order with their depth in the traversal, `start` itself
+    /// not included.
+    pub fn dfs(&self, start: &StoreId, depth: usize) -> Result<Vec<(Link, usize)>> {
+        let mut visited = HashSet::new();
+        let mut stack   = vec![(start.clone().without_base(), 0)];
+        let mut result  = vec![];
+
+        while let Some((id, dist)) = stack.pop() {
+            if !visited.insert(id.local().clone()) {
+                continue;
+            }
+            if dist > 0 {
+                result.push((id.clone(), dist));
+            }
+            if dist >= depth {
+                continue;
+            }
+
+            // reversed, so the first neighbour is visited first
+            for next in try!(self.neighbours(&id)).into_iter().rev() {
+                if !visited.contains(next.local()) {
+                    stack.push((next, dist + 1));
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Find a shortest path from `from` to `to`
+    ///
+    /// The path contains both `from` and `to`. Returns `None` if the entries are not connected.
+    pub fn shortest_path(&self, from: &StoreId, to: &StoreId) -> Result<Option<Vec<Link>>> {
+        let mut predecessors : HashMap<PathBuf, Link> = HashMap::new();
+        let mut visited = HashSet::new();
+        let mut queue   = VecDeque::new();
+
+        visited.insert(from.local().clone());
+        queue.push_back(from.clone().without_base());
+
+        while let Some(id) = queue.pop_front() {
+            if id.local() == to.local() {
+                let mut path    = vec![];
+                let mut current = Some(id);
+                while let Some(link) = current {
+                    current = predecessors.get(link.local()).cloned();
+                    path.push(link);
+                }
+                path.reverse();
+                return Ok(Some(path));
+            }
+
+            for next in try!(self.neighbours(&id)) {
+                if visited.insert(next.local().clone()) {
+                    predecessors.insert(next.local().clone(), id.clone());
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get the connected components the entries `ids` are part of
+    ///
+    /// Components also contain the entries reachable from `ids`, even if they are not in `ids`
+    /// themselves.
+    pub fn components<I: Iterator<Item = StoreId>>(&self, ids: I) -> Result<Vec<Vec<Link>>> {
+        let mut seen       = HashSet::new();
+        let mut components = vec![];
+
+        for id in ids {
+            if seen.contains(id.local()) {
+                continue;
+            }
+
+            let mut component = vec![id.clone().without_base()];
+            for (link, _) in try!(self.bfs(&id, usize::max_value())) {
+                component.push(link);
+            }
+
+            for link in component.iter() {
+                seen.insert(link.local().clone());
+            }
+            components.push(component);
+        }
+
+        Ok(components)
+    }
+
+    /// Get the entries of `ids` which are not linked to any other entry
+    pub fn orphans<I: Iterator<Item = StoreId>>(&self, ids: I) -> Result<Vec<Link>> {
+        let mut orphans = vec![];
+        for id in ids {
+            if try!(self.neighbours(&id)).is_empty() {
+                orphans.push(id.without_base());
+            }
+        }
+        Ok(orphans)
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::store::Store;
+    use libimagstore::storeid::StoreId;
+
+    use internal::InternalLinker;
+    use super::LinkGraph;
+
+    fn setup_logging() {
+        use env_logger;
+        let _ = env_logger::init().unwrap_or(());
+    }
+
+    pub fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None).unwrap()
+    }
+
+    fn id(s: &str) -> StoreId {
+        StoreId::new_baseless(PathBuf::from(s)).unwrap()
+    }
+
+    /// Link the entries named `prefix` + "a" - "b" - "c" - "d" in a chain and create an unlinked
+    /// entry `prefix` + "e"
+    fn setup_chain(store: &Store, prefix: &str) {
+        let mut a = store.retrieve(PathBuf::from(format!("{}a", prefix))).unwrap();
+        let mut b = store.retrieve(PathBuf::from(format!("{}b", prefix))).unwrap();
+        let mut c = store.retrieve(PathBuf::from(format!("{}c", prefix))).unwrap();
+        let mut d = store.retrieve(PathBuf::from(format!("{}d", prefix))).unwrap();
+        let _     = store.retrieve(PathBuf::from(format!("{}e", prefix))).unwrap();
+
+        assert!(a.add_internal_link(&mut b).is_ok());
+        assert!(b.add_internal_link(&mut c).is_ok());
+        assert!(c.add_internal_link(&mut d).is_ok());
+    }
+
+    #[test]
+    fn test_bfs_depth() {
+        setup_logging();
+        let store = get_store();
+        setup_chain(&store, "graph_bfs_");
+
+        let graph = LinkGraph::new(&store);
+
+        let reached = graph.bfs(&id("graph_bfs_a"), 2).unwrap();
+        assert_eq!(reached, vec![(id("graph_bfs_b"), 1), (id("graph_bfs_c"), 2)]);
+
+        let reached = graph.bfs(&id("graph_bfs_b"), 1).unwrap();
+        assert_eq!(reached.len(), 2);
+    }
+
+    #[test]
+    fn test_dfs_depth() {
+        setup_logging();
+        let store = get_store();
+        setup_chain(&store, "graph_dfs_");
+
+        let graph   = LinkGraph::new(&store);
+        let reached = graph.dfs(&id("graph_dfs_a"), 10).unwrap();
+
+        assert_eq!(reached, vec![
+            (id("graph_dfs_b"), 1),
+            (id("graph_dfs_c"), 2),
+            (id("graph_dfs_d"), 3),
+        ]);
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        setup_logging();
+        let store = get_store();
+        setup_chain(&store, "graph_path_");
+
+        let graph = LinkGraph::new(&store);
+
+        let path = graph.shortest_path(&id("graph_path_a"), &id("graph_path_d")).unwrap();
+        assert_eq!(path, Some(vec![
+            id("graph_path_a"),
+            id("graph_path_b"),
+            id("graph_path_c"),
+            id("graph_path_d"),
+        ]));
+
+        let path = graph.shortest_path(&id("graph_path_a"), &id("graph_path_e")).unwrap();
+        assert_eq!(path, None);
+    }
+
+    #[test]
+    fn test_components_and_orphans() {
+        setup_logging();
+        let store = get_store();
+        setup_chain(&store, "graph_comp_");
+
+        let graph = LinkGraph::new(&store);
+        let ids   = vec![id("graph_comp_a"), id("graph_comp_c"), id("graph_comp_e")];
+
+        let components = graph.components(ids.clone().into_iter()).unwrap();
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0].len(), 4);
+        assert_eq!(components[1], vec![id("graph_comp_e")]);
+
+        let orphans = graph.orphans(ids.into_iter()).unwrap();
+        assert_eq!(orphans, vec![id("graph_comp_e")]);
+    }
+
+}
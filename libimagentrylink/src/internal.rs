@@ -17,8 +17,12 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
 use libimagstore::storeid::StoreId;
 use libimagstore::store::Entry;
+use libimagstore::ownership::LibrarySection;
 use libimagstore::store::Result as StoreResult;
 use libimagstore::toml_ext::TomlValueExt;
 use libimagerror::into::IntoError;
@@ -33,6 +37,131 @@ use toml::Value;
 
 pub type Link = StoreId;
 
+/// Where the relations and attributes of internal links are stored in the header.
+///
+/// `imag.links` stays a plain array of the linked ids, so everything which does not know about
+/// relations still sees all links.
+const RELATIONS_HEADER_PATH: &'static str = "imag.link-relations";
+
+/// Relations which are the reverse of each other.
+///
+/// Relations which are not listed here are their own reverse, like "related".
+const REVERSE_RELATIONS: &'static [(&'static str, &'static str)] = &[
+    ("parent",        "child"),
+    ("annotation-of", "annotated-by"),
+    ("depends-on",    "dependency-of"),
+    ("part-of",       "has-part"),
+    ("blocks",        "blocked-by"),
+    ("precedes",      "follows"),
+];
+
+/// Get the name of the relation the other entry of a link gets, if this entry gets `relation`.
+pub fn reverse_relation(relation: &str) -> String {
+    for &(a, b) in REVERSE_RELATIONS {
+        if relation == a {
+            return String::from(b);
+        }
+        if relation == b {
+            return String::from(a);
+        }
+    }
+    String::from(relation)
+}
+
+/// An internal link, together with its relation and attributes
+///
+/// Links which were added without a relation have no relation and no attributes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedLink {
+    pub link: Link,
+    pub relation: Option<String>,
+    pub attributes: BTreeMap<String, Value>,
+
+    /// Whether adding the relation created the plain link, which is stored as `created-link` in
+    /// the header. Only then the plain link is removed with the last relation.
+    created_link: bool,
+}
+
+impl TypedLink {
+
+    fn plain(link: Link) -> TypedLink {
+        TypedLink {
+            link: link,
+            relation: None,
+            attributes: BTreeMap::new(),
+            created_link: false,
+        }
+    }
+
+    fn with_relation(link: Link, relation: &str, attributes: BTreeMap<String, Value>) -> TypedLink {
+        TypedLink {
+            link: link,
+            relation: Some(String::from(relation)),
+            attributes: attributes,
+            created_link: false,
+        }
+    }
+
+    fn creating_link(mut self, created: bool) -> TypedLink {
+        self.created_link = created;
+        self
+    }
+
+    fn from_value(v: Value) -> Result<TypedLink> {
+        let mut table = match v {
+            Value::Table(t) => t,
+            _ => return Err(LEK::ExistingLinkTypeWrong.into_error()),
+        };
+
+        let link = match table.remove("link") {
+            Some(Value::String(s)) => try!(StoreId::new_baseless(PathBuf::from(s))
+                                           .map_err_into(LEK::StoreIdError)),
+            _ => return Err(LEK::ExistingLinkTypeWrong.into_error()),
+        };
+
+        let relation = match table.remove("relation") {
+            Some(Value::String(s)) => s,
+            _ => return Err(LEK::ExistingLinkTypeWrong.into_error()),
+        };
+
+        let attributes = match table.remove("attributes") {
+            Some(Value::Table(t)) => t,
+            None                  => BTreeMap::new(),
+            Some(_)               => return Err(LEK::ExistingLinkTypeWrong.into_error()),
+        };
+
+        let created = match table.remove("created-link") {
+            Some(Value::Boolean(b)) => b,
+            None                    => false,
+            Some(_)                 => return Err(LEK::ExistingLinkTypeWrong.into_error()),
+        };
+
+        Ok(TypedLink::with_relation(link, &relation, attributes).creating_link(created))
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        let link = try!(self.link
+                        .clone()
+                        .without_base()
+                        .to_str()
+                        .map_err_into(LEK::InternalConversionError));
+
+        let mut table = BTreeMap::new();
+        table.insert(String::from("link"), Value::String(link));
+        if let Some(ref relation) = self.relation {
+            table.insert(String::from("relation"), Value::String(relation.clone()));
+        }
+        if !self.attributes.is_empty() {
+            table.insert(String::from("attributes"), Value::Table(self.attributes.clone()));
+        }
+        if self.created_link {
+            table.insert(String::from("created-link"), Value::Boolean(true));
+        }
+        Ok(Value::Table(table))
+    }
+
+}
+
 pub trait InternalLinker {
 
     /// Get the internal links from the implementor object
@@ -45,8 +174,35 @@ pub trait InternalLinker {
     fn add_internal_link(&mut self, link: &mut Entry) -> Result<()>;
 
     /// Remove an internal link from the implementor object
+    ///
+    /// This removes all relations between the two entries as well.
     fn remove_internal_link(&mut self, link: &mut Entry) -> Result<()>;
 
+    /// Get the internal links together with their relations and attributes
+    ///
+    /// A link with several relations is returned once for each relation.
+    fn get_typed_links(&self) -> Result<Vec<TypedLink>>;
+
+    /// Get the internal links which have the relation `relation`
+    fn get_links_by_relation(&self, relation: &str) -> Result<LinkIter>;
+
+    /// Add an internal link with a relation and attributes to the implementor object
+    ///
+    /// The other entry is linked back with the reverse relation (see `reverse_relation()`) and
+    /// the same attributes. If the relation already exists, its attributes are replaced.
+    fn add_typed_link(&mut self,
+                      link: &mut Entry,
+                      relation: &str,
+                      attributes: BTreeMap<String, Value>)
+        -> Result<()>;
+
+    /// Remove the relation `relation` to another entry, and the reverse relation on the other
+    /// entry
+    ///
+    /// If no other relation between the entries is left, the link itself is removed as well,
+    /// unless the entries were linked before the first relation was added.
+    fn remove_typed_link(&mut self, link: &mut Entry, relation: &str) -> Result<()>;
+
 }
 
 pub mod iter {
@@ -61,7 +217,7 @@ pub mod iter {
     use toml::Value;
     use itertools::Itertools;
 
-    use libimagstore::store::Store;
+    use libimagstore::access::StoreAccess;
     use libimagstore::store::FileLockEntry;
 
     pub struct LinkIter(IntoIter<Link>);
@@ -72,7 +228,7 @@ pub mod iter {
             LinkIter(v.into_iter())
         }
 
-        pub fn into_getter(self, store: &Store) -> GetIter {
+        pub fn into_getter(self, store: &StoreAccess) -> GetIter {
             GetIter(self.0, store)
         }
 
@@ -112,10 +268,10 @@ pub mod iter {
     }
 
     /// An Iterator that `Store::get()`s the Entries from the store while consumed
-    pub struct GetIter<'a>(IntoIter<Link>, &'a Store);
+    pub struct GetIter<'a>(IntoIter<Link>, &'a StoreAccess);
 
     impl<'a> GetIter<'a> {
-        pub fn new(i: IntoIter<Link>, store: &'a Store) -> GetIter<'a> {
+        pub fn new(i: IntoIter<Link>, store: &'a StoreAccess) -> GetIter<'a> {
             GetIter(i, store)
         }
 
@@ -157,7 +313,7 @@ pub mod iter {
             FilterLinksIter::new(self, f)
         }
 
-        pub fn store(&self) -> &Store {
+        pub fn store(&self) -> &StoreAccess {
             self.1
         }
     }
@@ -286,6 +442,8 @@ impl InternalLinker for Entry {
             new_links.push(link);
         }
 
+        try!(rewrite_relations(self, |r| new_links.iter().any(|l| l.local() == r.link.local())));
+
         let new_links = try!(LinkIter::new(new_links)
                              .into_values()
                              .fold(Ok(vec![]), |acc, elem| {
@@ -297,6 +455,7 @@ impl InternalLinker for Entry {
                                         })
                                 })
                             }));
+        self.grant_section_write(LibrarySection::Links);
         process_rw_result(self.get_header_mut().set("imag.links", Value::Array(new_links)))
     }
 
@@ -310,7 +469,7 @@ impl InternalLinker for Entry {
                 self.get_internal_links()
                     .and_then(|links| {
                         let links = links.chain(LinkIter::new(vec![new_link]));
-                        rewrite_links(self.get_header_mut(), links)
+                        rewrite_links(self, links)
                     })
             })
     }
@@ -321,23 +480,86 @@ impl InternalLinker for Entry {
 
         debug!("Removing internal link from {:?} to {:?}", own_loc, other_loc);
 
-        link.get_internal_links()
-            .and_then(|links| {
-                debug!("Rewriting own links for {:?}, without {:?}", other_loc, own_loc);
-                rewrite_links(link.get_header_mut(), links.filter(|l| *l != own_loc))
-            })
-            .and_then(|_| {
-                self.get_internal_links()
-                    .and_then(|links| {
-                        debug!("Rewriting own links for {:?}, without {:?}", own_loc, other_loc);
-                        rewrite_links(self.get_header_mut(), links.filter(|l| *l != other_loc))
-                    })
-            })
+        let links = try!(link.get_internal_links());
+        debug!("Rewriting own links for {:?}, without {:?}", other_loc, own_loc);
+        try!(rewrite_links(link, links.filter(|l| *l != own_loc)));
+        try!(rewrite_relations(link, |r| r.link != own_loc));
+
+        let links = try!(self.get_internal_links());
+        debug!("Rewriting own links for {:?}, without {:?}", own_loc, other_loc);
+        try!(rewrite_links(self, links.filter(|l| *l != other_loc)));
+        rewrite_relations(self, |r| r.link != other_loc)
+    }
+
+    fn get_typed_links(&self) -> Result<Vec<TypedLink>> {
+        let mut relations = try!(get_relations(self));
+        let plain = try!(self.get_internal_links())
+            .filter(|l| !relations.iter().any(|r| r.link == *l))
+            .map(TypedLink::plain)
+            .collect::<Vec<_>>();
+
+        relations.extend(plain);
+        Ok(relations)
+    }
+
+    fn get_links_by_relation(&self, relation: &str) -> Result<LinkIter> {
+        get_relations(self).map(|relations| {
+            let links = relations
+                .into_iter()
+                .filter(|r| r.relation.as_ref().map(|rel| rel == relation).unwrap_or(false))
+                .map(|r| r.link)
+                .collect();
+            LinkIter::new(links)
+        })
+    }
+
+    fn add_typed_link(&mut self,
+                      link: &mut Entry,
+                      relation: &str,
+                      attributes: BTreeMap<String, Value>)
+        -> Result<()>
+    {
+        let own_loc   = self.get_location().clone().without_base();
+        let other_loc = link.get_location().clone().without_base();
+        let reverse   = reverse_relation(relation);
+
+        debug!("Adding '{}' link from {:?} to {:?} ('{}' back)", relation, own_loc, other_loc, reverse);
+
+        // Remember whether the plain link is created here, so it is only removed with the
+        // relations if it did not exist before
+        let linked = try!(self.get_internal_links()).any(|l| l == other_loc);
+        if !linked {
+            try!(self.add_internal_link(link));
+        }
+
+        let own   = TypedLink::with_relation(other_loc, relation, attributes.clone());
+        let other = TypedLink::with_relation(own_loc, &reverse, attributes);
+        try!(add_relation(self, own.creating_link(!linked)));
+        add_relation(link, other.creating_link(!linked))
+    }
+
+    fn remove_typed_link(&mut self, link: &mut Entry, relation: &str) -> Result<()> {
+        let own_loc   = self.get_location().clone().without_base();
+        let other_loc = link.get_location().clone().without_base();
+        let reverse   = reverse_relation(relation);
+
+        debug!("Removing '{}' link from {:?} to {:?}", relation, own_loc, other_loc);
+
+        let created = try!(remove_relation(self, &other_loc, relation));
+        let created = try!(remove_relation(link, &own_loc, &reverse)) || created;
+
+        let related = try!(get_relations(self)).into_iter().any(|r| r.link == other_loc);
+        if related || !created {
+            Ok(())
+        } else {
+            debug!("No relation left between {:?} and {:?}, removing link", own_loc, other_loc);
+            self.remove_internal_link(link)
+        }
     }
 
 }
 
-fn rewrite_links<I: Iterator<Item = Link>>(header: &mut Value, links: I) -> Result<()> {
+fn rewrite_links<I: Iterator<Item = Link>>(entry: &mut Entry, links: I) -> Result<()> {
     let links = try!(links.into_values()
                      .fold(Ok(vec![]), |acc, elem| {
                         acc.and_then(move |mut v| {
@@ -350,10 +572,139 @@ fn rewrite_links<I: Iterator<Item = Link>>(header: &mut Value, links: I) -> Resu
                      }));
 
     debug!("Setting new link array: {:?}", links);
-    let process = header.set("imag.links", Value::Array(links));
+    entry.grant_section_write(LibrarySection::Links);
+    let process = entry.get_header_mut().set("imag.links", Value::Array(links));
     process_rw_result(process).map(|_| ())
 }
 
+/// Remove the link to `target` from `entry`, without touching `target`.
+///
+/// Links are always symmetric, so this is only meant for repairing links, for example to remove
+/// a link to an entry which does not exist anymore. Relations to `target` are removed as well.
+pub fn remove_link_to(entry: &mut Entry, target: &StoreId) -> Result<()> {
+    let target = target.clone().without_base();
+    debug!("Removing link to {:?} from {:?} only", target, entry.get_location());
+
+    let links = try!(entry.get_internal_links());
+    try!(rewrite_links(entry, links.filter(|l| *l != target)));
+    rewrite_relations(entry, |r| r.link != target)
+}
+
+/// Let the links of `entry` to `old` point to `new`, for when `old` was moved to `new`.
+///
+/// This only touches `entry`, the moved entry itself still links to `entry`.
+pub fn rewrite_link_target(entry: &mut Entry, old: &StoreId, new: &StoreId) -> Result<()> {
+    let old = old.clone().without_base();
+    let new = new.clone().without_base();
+    debug!("Rewriting links of {:?} from {:?} to {:?}", entry.get_location(), old, new);
+
+    let links = try!(entry.get_internal_links());
+    try!(rewrite_links(entry, links.map(|l| if l == old { new.clone() } else { l })));
+
+    let relations = try!(get_relations(entry));
+    if relations.iter().any(|r| r.link == old) {
+        let relations = relations
+            .into_iter()
+            .map(|mut r| {
+                if r.link == old {
+                    r.link = new.clone();
+                }
+                r
+            })
+            .collect();
+        try!(set_relations(entry, relations));
+    }
+
+    Ok(())
+}
+
+/// Get the relations stored in the header of an entry.
+fn get_relations(entry: &Entry) -> Result<Vec<TypedLink>> {
+    let relations = match entry.get_header().read(RELATIONS_HEADER_PATH) {
+        Err(e) => return Err(LEK::EntryHeaderReadError.into_error_with_cause(Box::new(e))),
+        Ok(None) => return Ok(vec![]),
+        Ok(Some(Value::Array(a))) => a,
+        Ok(Some(_)) => {
+            debug!("We expected an Array for the link relations, but there was a non-Array!");
+            return Err(LEK::ExistingLinkTypeWrong.into_error());
+        },
+    };
+
+    relations.into_iter().map(TypedLink::from_value).collect()
+}
+
+/// Write the relations to the header of an entry, removing the header section if there are none.
+fn set_relations(entry: &mut Entry, relations: Vec<TypedLink>) -> Result<()> {
+    entry.grant_section_write(LibrarySection::LinkRelations);
+
+    let result = if relations.is_empty() {
+        entry.get_header_mut().delete(RELATIONS_HEADER_PATH)
+    } else {
+        let values = try!(relations.iter().map(TypedLink::to_value).collect::<Result<Vec<_>>>());
+        entry.get_header_mut().set(RELATIONS_HEADER_PATH, Value::Array(values))
+    };
+
+    result.map(|_| ()).map_err_into(LEK::EntryHeaderWriteError)
+}
+
+/// Add a relation to an entry, replacing the relation if it already exists.
+fn add_relation(entry: &mut Entry, mut relation: TypedLink) -> Result<()> {
+    let mut relations = try!(get_relations(entry));
+    let created = {
+        let same = |r: &TypedLink| r.link == relation.link && r.relation == relation.relation;
+        let created = relations.iter().any(|r| same(r) && r.created_link);
+        relations.retain(|r| !same(r));
+        created
+    };
+    relation.created_link |= created;
+    relations.push(relation);
+    set_relations(entry, relations)
+}
+
+/// Remove the relation `relation` to `target` from an entry
+///
+/// Returns whether the removed relation created the plain link to `target`. If other relations to
+/// `target` are left, the first of them takes this over instead, so the plain link is only removed
+/// together with the last relation.
+fn remove_relation(entry: &mut Entry, target: &Link, relation: &str) -> Result<bool> {
+    let relations = try!(get_relations(entry));
+    let len       = relations.len();
+
+    let removed = |r: &TypedLink| {
+        r.link == *target && r.relation.as_ref().map(|rel| rel == relation).unwrap_or(false)
+    };
+    let created = relations.iter().any(|r| removed(r) && r.created_link);
+    let mut relations = relations.into_iter().filter(|r| !removed(r)).collect::<Vec<_>>();
+
+    if relations.len() == len {
+        return Ok(false);
+    }
+
+    if created {
+        if let Some(r) = relations.iter_mut().find(|r| r.link == *target) {
+            r.created_link = true;
+            return set_relations(entry, relations).map(|_| false);
+        }
+    }
+
+    set_relations(entry, relations).map(|_| created)
+}
+
+/// Keep only the relations of an entry for which `keep` returns true.
+///
+/// The header is only touched if a relation was removed.
+fn rewrite_relations<F: Fn(&TypedLink) -> bool>(entry: &mut Entry, keep: F) -> Result<()> {
+    let relations = try!(get_relations(entry));
+    let len       = relations.len();
+    let relations = relations.into_iter().filter(|r| keep(r)).collect::<Vec<_>>();
+
+    if relations.len() == len {
+        Ok(())
+    } else {
+        set_relations(entry, relations)
+    }
+}
+
 /// When Linking A -> B, the specification wants us to link back B -> A.
 /// This is a helper function which does this.
 fn add_foreign_link(target: &mut Entry, from: StoreId) -> Result<()> {
@@ -373,14 +724,13 @@ fn add_foreign_link(target: &mut Entry, from: StoreId) -> Result<()> {
                                 })
                              }));
             debug!("Setting links in {:?}: {:?}", target.get_location(), links);
+            target.grant_section_write(LibrarySection::Links);
             process_rw_result(target.get_header_mut().set("imag.links", Value::Array(links)))
                 .map(|_| ())
         })
 }
 
 fn process_rw_result(links: StoreResult<Option<Value>>) -> Result<LinkIter> {
-    use std::path::PathBuf;
-
     let links = match links {
         Err(e) => {
             debug!("RW action on store failed. Generating LinkError");
@@ -562,5 +912,116 @@ mod test {
 
     }
 
+    #[test]
+    fn test_reverse_relation() {
+        use super::reverse_relation;
+
+        assert_eq!(reverse_relation("parent"), "child");
+        assert_eq!(reverse_relation("child"), "parent");
+        assert_eq!(reverse_relation("related"), "related");
+    }
+
+    #[test]
+    fn test_typed_link() {
+        use std::collections::BTreeMap;
+        use toml::Value;
+
+        setup_logging();
+        let store = get_store();
+
+        let mut e1 = store.retrieve(PathBuf::from("test_typed_link1")).unwrap();
+        let mut e2 = store.retrieve(PathBuf::from("test_typed_link2")).unwrap();
+
+        let mut attrs = BTreeMap::new();
+        attrs.insert(String::from("since"), Value::String(String::from("2016-10-01")));
+
+        assert!(e1.add_typed_link(&mut e2, "depends-on", attrs.clone()).is_ok());
+
+        // the link is a plain link as well
+        assert_eq!(e1.get_internal_links().unwrap().count(), 1);
+        assert_eq!(e2.get_internal_links().unwrap().count(), 1);
+
+        assert_eq!(e1.get_links_by_relation("depends-on").unwrap().count(), 1);
+        assert_eq!(e1.get_links_by_relation("dependency-of").unwrap().count(), 0);
+        assert_eq!(e2.get_links_by_relation("dependency-of").unwrap().count(), 1);
+
+        let typed = e2.get_typed_links().unwrap();
+        assert_eq!(typed.len(), 1);
+        assert_eq!(typed[0].relation, Some(String::from("dependency-of")));
+        assert_eq!(typed[0].attributes, attrs);
+
+        assert!(e1.remove_typed_link(&mut e2, "depends-on").is_ok());
+
+        assert_eq!(e1.get_typed_links().unwrap().len(), 0);
+        assert_eq!(e2.get_typed_links().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_remove_typed_link_keeps_previous_plain_link() {
+        use std::collections::BTreeMap;
+
+        setup_logging();
+        let store = get_store();
+
+        let mut e1 = store.retrieve(PathBuf::from("test_typed_link_plain1")).unwrap();
+        let mut e2 = store.retrieve(PathBuf::from("test_typed_link_plain2")).unwrap();
+
+        assert!(e1.add_internal_link(&mut e2).is_ok());
+        assert!(e1.add_typed_link(&mut e2, "parent", BTreeMap::new()).is_ok());
+        assert!(e1.remove_typed_link(&mut e2, "parent").is_ok());
+
+        assert_eq!(e1.get_typed_links().unwrap().len(), 1);
+        assert_eq!(e1.get_internal_links().unwrap().count(), 1);
+        assert_eq!(e2.get_internal_links().unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_remove_typed_link_removes_created_link() {
+        use std::collections::BTreeMap;
+
+        setup_logging();
+        let store = get_store();
+
+        let mut e1 = store.retrieve(PathBuf::from("test_typed_link_created1")).unwrap();
+        let mut e2 = store.retrieve(PathBuf::from("test_typed_link_created2")).unwrap();
+
+        assert!(e1.add_typed_link(&mut e2, "parent", BTreeMap::new()).is_ok());
+        assert!(e1.add_typed_link(&mut e2, "blocks", BTreeMap::new()).is_ok());
+
+        // The link was created with "parent", it goes with the last relation
+        assert!(e1.remove_typed_link(&mut e2, "parent").is_ok());
+        assert_eq!(e1.get_internal_links().unwrap().count(), 1);
+        assert!(e1.remove_typed_link(&mut e2, "blocks").is_ok());
+
+        assert_eq!(e1.get_internal_links().unwrap().count(), 0);
+        assert_eq!(e2.get_internal_links().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_typed_link_several_relations() {
+        use std::collections::BTreeMap;
+
+        setup_logging();
+        let store = get_store();
+
+        let mut e1 = store.retrieve(PathBuf::from("test_typed_link_several_relations1")).unwrap();
+        let mut e2 = store.retrieve(PathBuf::from("test_typed_link_several_relations2")).unwrap();
+
+        assert!(e1.add_typed_link(&mut e2, "parent", BTreeMap::new()).is_ok());
+        assert!(e1.add_typed_link(&mut e2, "blocks", BTreeMap::new()).is_ok());
+        assert_eq!(e1.get_typed_links().unwrap().len(), 2);
+
+        // The link stays, as "blocks" still relates the entries
+        assert!(e1.remove_typed_link(&mut e2, "parent").is_ok());
+        assert_eq!(e1.get_internal_links().unwrap().count(), 1);
+        assert_eq!(e2.get_links_by_relation("blocked-by").unwrap().count(), 1);
+        assert_eq!(e2.get_links_by_relation("child").unwrap().count(), 0);
+
+        // Removing the link removes the relations as well
+        assert!(e1.remove_internal_link(&mut e2).is_ok());
+        assert_eq!(e1.get_typed_links().unwrap().len(), 0);
+        assert_eq!(e2.get_typed_links().unwrap().len(), 0);
+    }
+
 }
 
@@ -37,6 +37,7 @@ extern crate toml;
 extern crate semver;
 extern crate url;
 extern crate crypto;
+extern crate chrono;
 
 #[cfg(test)]
 extern crate env_logger;
@@ -47,8 +48,11 @@ extern crate env_logger;
 
 module_entry_path_mod!("links");
 
+pub mod check;
 pub mod error;
 pub mod external;
+pub mod graph;
 pub mod internal;
+pub mod relink;
 pub mod result;
 
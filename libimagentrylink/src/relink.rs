@@ -0,0 +1,266 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Moving entries without breaking their links
+//!
+//! `Store::move_by_id()` and `Store::save_as()` only move the file of an entry. The entries linked
+//! to the moved entry still link to the old id afterwards. The functions in this module move the
+//! entry and let the links of all linked entries point to the new id.
+//!
+//! The links of the linked entries are rewritten before the entry is moved, so the move is not
+//! done if one of them cannot be loaded or rewritten. If writing the linked entries fails after
+//! the move, the move is reverted and the entries which were written already are restored.
+
+use toml::Value;
+
+use libimagstore::store::{Entry, FileLockEntry, Store};
+use libimagstore::storeid::StoreId;
+use libimagerror::into::IntoError;
+
+use error::LinkErrorKind as LEK;
+use error::MapErrInto;
+use internal::{InternalLinker, rewrite_link_target};
+use result::Result;
+
+/// Move the entry `old_id` to `new_id` with `Store::move_by_id()` and rewrite the links to it
+pub fn move_by_id(store: &Store, old_id: StoreId, new_id: StoreId) -> Result<()> {
+    let entry  = try!(store.retrieve_copy(old_id.clone()).map_err_into(LEK::StoreReadError));
+    let linked = try!(RewrittenLinks::new(store, &entry, &old_id, &new_id));
+
+    if let Err(e) = store.move_by_id(old_id.clone(), new_id.clone()) {
+        linked.restore();
+        return Err(e).map_err_into(LEK::StoreWriteError);
+    }
+
+    linked.write(store, &old_id, &new_id)
+}
+
+/// Save the entry to `new_id` with `Store::save_as()` and rewrite the links to it
+pub fn save_as<'a>(store: &'a Store, entry: FileLockEntry<'a>, new_id: StoreId) -> Result<()> {
+    let old_id = entry.get_location().clone();
+    let linked = try!(RewrittenLinks::new(store, &entry, &old_id, &new_id));
+
+    if let Err(e) = store.save_as(entry, new_id.clone()) {
+        linked.restore();
+        return Err(e).map_err_into(LEK::StoreWriteError);
+    }
+
+    linked.write(store, &old_id, &new_id)
+}
+
+/// The entries linked to a moved entry, with their links already rewritten in memory, and their
+/// original headers for restoring them
+struct RewrittenLinks<'a> {
+    entries: Vec<(FileLockEntry<'a>, Value)>,
+}
+
+impl<'a> RewrittenLinks<'a> {
+
+    /// Load the entries linked to `entry` and let their links to `old_id` point to `new_id`
+    ///
+    /// If this fails for one of them, all of them are restored.
+    fn new(store: &'a Store, entry: &Entry, old_id: &StoreId, new_id: &StoreId)
+        -> Result<RewrittenLinks<'a>>
+    {
+        let own_id = entry.get_location().clone().without_base();
+        let mut rewritten = RewrittenLinks { entries: vec![] };
+
+        for link in try!(entry.get_internal_links()) {
+            if link == own_id {
+                continue;
+            }
+
+            let linked = match store.get(link.clone()).map_err_into(LEK::StoreReadError) {
+                Ok(Some(e)) => e,
+                Ok(None)    => {
+                    warn!("Linked entry {:?} does not exist, not rewriting its links", link);
+                    continue;
+                },
+                Err(e) => {
+                    rewritten.restore();
+                    return Err(e);
+                },
+            };
+
+            let original = linked.get_header().clone();
+            rewritten.entries.push((linked, original));
+
+            let result = match rewritten.entries.last_mut() {
+                Some(&mut (ref mut linked, _)) => rewrite_link_target(linked, old_id, new_id),
+                None => Ok(()),
+            };
+
+            if let Err(e) = result {
+                warn!("Could not rewrite links from {:?} to {:?}: {:?}", old_id, new_id, e);
+                rewritten.restore();
+                return Err(LEK::LinkRewriteError.into_error_with_cause(Box::new(e)));
+            }
+        }
+
+        Ok(rewritten)
+    }
+
+    /// Restore the original headers, so the entries are written back unchanged
+    fn restore(self) {
+        for (mut entry, original) in self.entries {
+            *entry.get_header_mut() = original;
+        }
+    }
+
+    /// Write the rewritten entries
+    ///
+    /// If writing one of them fails, the moved entry is moved back from `new_id` to `old_id` and
+    /// the entries are restored.
+    fn write(self, store: &'a Store, old_id: &StoreId, new_id: &StoreId) -> Result<()> {
+        let mut written = vec![];
+        let mut entries = self.entries.into_iter();
+
+        while let Some((entry, original)) = entries.next() {
+            let id = entry.get_location().clone();
+            written.push((id.clone(), original));
+
+            if let Err(e) = store.update(entry) {
+                warn!("Could not write {:?}, reverting the move to {:?}: {:?}", id, new_id, e);
+
+                RewrittenLinks { entries: entries.collect() }.restore();
+                revert(store, old_id, new_id, written);
+                return Err(LEK::LinkRewriteError.into_error_with_cause(Box::new(e)));
+            }
+        }
+
+        Ok(())
+    }
+
+}
+
+/// Move the entry back from `new_id` to `old_id` and restore the headers of the entries which were
+/// written already
+fn revert(store: &Store, old_id: &StoreId, new_id: &StoreId, written: Vec<(StoreId, Value)>) {
+    if let Err(e) = store.move_by_id(new_id.clone(), old_id.clone()) {
+        warn!("Could not move {:?} back to {:?}: {:?}", new_id, old_id, e);
+    }
+
+    for (id, original) in written {
+        match store.retrieve(id.clone()) {
+            Ok(mut entry) => *entry.get_header_mut() = original,
+            Err(e)        => warn!("Could not restore the links of {:?}: {:?}", id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::store::Store;
+    use libimagstore::storeid::StoreId;
+
+    use internal::InternalLinker;
+    use super::{move_by_id, save_as};
+
+    fn setup_logging() {
+        use env_logger;
+        let _ = env_logger::init().unwrap_or(());
+    }
+
+    pub fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None).unwrap()
+    }
+
+    fn id(s: &str) -> StoreId {
+        StoreId::new_baseless(PathBuf::from(s)).unwrap()
+    }
+
+    #[test]
+    fn test_move_rewrites_links() {
+        setup_logging();
+        let store = get_store();
+        {
+            let mut a = store.retrieve(PathBuf::from("relink_a")).unwrap();
+            let mut b = store.retrieve(PathBuf::from("relink_b")).unwrap();
+            assert!(a.add_internal_link(&mut b).is_ok());
+        }
+
+        assert!(move_by_id(&store, id("relink_b"), id("relink_c")).is_ok());
+
+        let a = store.retrieve_copy(id("relink_a")).unwrap();
+        let links = a.get_internal_links().unwrap().collect::<Vec<_>>();
+        assert_eq!(links, vec![id("relink_c")]);
+
+        let c = store.retrieve_copy(id("relink_c")).unwrap();
+        let links = c.get_internal_links().unwrap().collect::<Vec<_>>();
+        assert_eq!(links, vec![id("relink_a")]);
+    }
+
+    #[test]
+    fn test_save_as_rewrites_links() {
+        setup_logging();
+        let store = get_store();
+        {
+            let mut a = store.retrieve(PathBuf::from("relink_save_as_a")).unwrap();
+            let mut b = store.retrieve(PathBuf::from("relink_save_as_b")).unwrap();
+            assert!(a.add_internal_link(&mut b).is_ok());
+        }
+
+        let b = store.retrieve(id("relink_save_as_b")).unwrap();
+        assert!(save_as(&store, b, id("relink_save_as_c")).is_ok());
+
+        assert!(!store.exists(id("relink_save_as_b")).unwrap());
+
+        let a = store.retrieve_copy(id("relink_save_as_a")).unwrap();
+        let links = a.get_internal_links().unwrap().collect::<Vec<_>>();
+        assert_eq!(links, vec![id("relink_save_as_c")]);
+
+        let c = store.retrieve_copy(id("relink_save_as_c")).unwrap();
+        let links = c.get_internal_links().unwrap().collect::<Vec<_>>();
+        assert_eq!(links, vec![id("relink_save_as_a")]);
+    }
+
+    #[test]
+    fn test_move_is_not_done_if_rewriting_fails() {
+        use toml::Value;
+        use libimagstore::toml_ext::TomlValueExt;
+
+        setup_logging();
+        let store = get_store();
+        {
+            let mut a = store.retrieve(PathBuf::from("relink_fail_a")).unwrap();
+            let mut b = store.retrieve(PathBuf::from("relink_fail_b")).unwrap();
+            let mut z = store.retrieve(PathBuf::from("relink_fail_z")).unwrap();
+            assert!(a.add_internal_link(&mut b).is_ok());
+            assert!(z.add_internal_link(&mut b).is_ok());
+
+            // The relations of z cannot be read, so its links cannot be rewritten
+            let broken = Value::String(String::from("broken"));
+            assert!(z.get_header_mut().set("imag.link-relations", broken).is_ok());
+        }
+
+        assert!(move_by_id(&store, id("relink_fail_b"), id("relink_fail_c")).is_err());
+
+        assert!(store.exists(id("relink_fail_b")).unwrap());
+        assert!(!store.exists(id("relink_fail_c")).unwrap());
+
+        for linked in &["relink_fail_a", "relink_fail_z"] {
+            let e = store.retrieve_copy(id(linked)).unwrap();
+            let links = e.get_internal_links().unwrap().collect::<Vec<_>>();
+            assert_eq!(links, vec![id("relink_fail_b")]);
+        }
+    }
+
+}
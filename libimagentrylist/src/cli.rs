@@ -17,14 +17,22 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+use std::result::Result as RResult;
+
 use clap::{Arg, ArgMatches, App, SubCommand};
+use toml::Value;
 
 use libimagstore::store::FileLockEntry;
+use libimagstore::toml_ext::TomlValueExt;
+use libimagerror::into::IntoError;
 
 use result::Result;
 use listers::line::LineLister;
 use listers::path::PathLister;
+use listers::template::{Template, TemplateData, TemplateLister};
 use lister::Lister;
+use pipeline::{Pipeline, SortKey};
+use format::ListFormat;
 use error::{ListError, ListErrorKind};
 
 pub fn build_list_cli_component<'a, 'b>() -> App<'a, 'b> {
@@ -57,6 +65,127 @@ pub fn build_list_cli_component<'a, 'b>() -> App<'a, 'b> {
              .multiple(false)
              .help("Use backend: Path (absolute)"))
 
+        .arg(Arg::with_name(list_sort())
+             .long("sort")
+             .short("s")
+             .takes_value(true)
+             .required(false)
+             .multiple(true)
+             .validator(|s| is_sort_key(&s))
+             .help("Sort by this key: 'id', 'content-length', 'mtime' or 'header.<path>'. Can be passed multiple times, later keys break ties")
+             .value_name("KEY"))
+
+        .arg(Arg::with_name(list_reverse())
+             .long("reverse")
+             .short("r")
+             .takes_value(false)
+             .required(false)
+             .multiple(false)
+             .help("Reverse the sort order"))
+
+        .arg(Arg::with_name(list_group_by())
+             .long("group-by")
+             .short("g")
+             .takes_value(true)
+             .required(false)
+             .multiple(false)
+             .help("Group entries by the value of this header field, for example 'note.name'")
+             .value_name("PATH"))
+
+        .arg(Arg::with_name(list_limit())
+             .long("limit")
+             .short("n")
+             .takes_value(true)
+             .required(false)
+             .multiple(false)
+             .validator(|s| is_number(&s))
+             .help("List at most this many entries")
+             .value_name("N"))
+
+        .arg(Arg::with_name(list_offset())
+             .long("offset")
+             .takes_value(true)
+             .required(false)
+             .multiple(false)
+             .validator(|s| is_number(&s))
+             .help("Skip this many entries before listing")
+             .value_name("N"))
+
+        .arg(list_format_arg())
+        .arg(list_fields_arg())
+        .arg(list_with_content_arg())
+        .arg(list_template_arg())
+
+}
+
+/// The `--format` flag, for binaries which list entries outside of the list subcommand
+pub fn list_format_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(list_format())
+        .long("format")
+        .takes_value(true)
+        .required(false)
+        .multiple(false)
+        .possible_values(ListFormat::names())
+        .help("Print the entries in this format")
+        .value_name("FORMAT")
+}
+
+/// The `--fields` flag, selecting the header fields printed by the table, json, csv and toml formats
+pub fn list_fields_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(list_fields())
+        .long("fields")
+        .takes_value(true)
+        .required(false)
+        .multiple(true)
+        .requires(list_format())
+        .help("Header fields to print, for example 'note.name'. The json and toml formats print the whole header if none are given")
+        .value_name("FIELD")
+}
+
+/// The `--template` flag, taking a format string or the name of a template in the configuration
+pub fn list_template_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(list_template())
+        .long("template")
+        .short("T")
+        .takes_value(true)
+        .required(false)
+        .multiple(false)
+        .conflicts_with(list_format())
+        .validator(|s| is_template(&s))
+        .help("Print each entry with this template, for example '{id:<40} {header.note.name}', or with the template of this name in [list.templates] of the configuration")
+        .value_name("TEMPLATE")
+}
+
+/// A value without any braces is taken as a template name, which can only be checked later
+fn is_template(s: &str) -> RResult<(), String> {
+    if s.contains('{') || s.contains('}') {
+        Template::parse(s).map(|_| ()).map_err(|e| format!("{}", e))
+    } else {
+        Ok(())
+    }
+}
+
+/// The `--with-content` flag, which adds the content to the json, csv and toml formats
+pub fn list_with_content_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(list_with_content())
+        .long("with-content")
+        .takes_value(false)
+        .required(false)
+        .multiple(false)
+        .requires(list_format())
+        .help("Print the content of the entries, too")
+}
+
+fn is_sort_key(s: &str) -> RResult<(), String> {
+    SortKey::parse(s)
+        .map(|_| ())
+        .ok_or_else(|| format!("Not a sort key: '{}'", s))
+}
+
+fn is_number(s: &str) -> RResult<(), String> {
+    s.parse::<usize>()
+        .map(|_| ())
+        .map_err(|_| format!("Not a number: '{}'", s))
 }
 
 pub fn list_subcommand_name() -> &'static str {
@@ -75,23 +204,162 @@ pub fn list_backend_path_absolute() -> &'static str {
     "path-absolute"
 }
 
+pub fn list_sort() -> &'static str {
+    "sort"
+}
+
+pub fn list_reverse() -> &'static str {
+    "reverse"
+}
+
+pub fn list_group_by() -> &'static str {
+    "group-by"
+}
+
+pub fn list_limit() -> &'static str {
+    "limit"
+}
+
+pub fn list_offset() -> &'static str {
+    "offset"
+}
+
+pub fn list_format() -> &'static str {
+    "format"
+}
+
+pub fn list_fields() -> &'static str {
+    "fields"
+}
+
+pub fn list_with_content() -> &'static str {
+    "with-content"
+}
+
+pub fn list_template() -> &'static str {
+    "template"
+}
+
+/// Get the template passed with `--template`, if any
+///
+/// If `config` has a template of that name in `list.templates`, that one is used, otherwise the
+/// value itself is parsed as template.
+pub fn get_template(matches: &ArgMatches, config: Option<&Value>) -> Result<Option<Template>> {
+    let value = match matches.value_of(list_template()) {
+        Some(v) => v,
+        None    => return Ok(None),
+    };
+
+    let is_name    = !(value.contains('{') || value.contains('}'));
+    let configured = match config {
+        Some(cfg) if is_name => match cfg.read(&format!("list.templates.{}", value)) {
+            Ok(Some(Value::String(s))) => Some(s),
+            Ok(Some(_))                => return Err(ListErrorKind::TemplateError.into_error()),
+            Ok(None)                   => None,
+            Err(e) => return Err(ListErrorKind::TemplateError.into_error_with_cause(Box::new(e))),
+        },
+        _ => None,
+    };
+
+    Template::parse(configured.as_ref().map(|s| &s[..]).unwrap_or(value))
+        .map(Some)
+        .map_err(|e| ListErrorKind::TemplateError.into_error_with_cause(Box::new(e)))
+}
+
+/// Get the format passed with `--format`, if any
+pub fn get_format(matches: &ArgMatches) -> Option<ListFormat> {
+    matches.value_of(list_format()).and_then(ListFormat::parse)
+}
+
+/// Get the header fields passed with `--fields`
+pub fn get_fields(matches: &ArgMatches) -> Vec<String> {
+    matches
+        .values_of(list_fields())
+        .map(|fields| fields.map(String::from).collect())
+        .unwrap_or_else(Vec::new)
+}
+
+/// List the entries in the format passed with `--format`, falling back to `default`
+///
+/// This reads the pipeline arguments as well, so binaries which add the arguments of this module
+/// to their own subcommands get sorting, paging and all formats with a single call. A template
+/// passed with `--template` takes precedence over the format, `config` is where named templates
+/// are looked up and `data` provides the tags and links for the template.
+pub fn list_entries_with_format<'a, I>(matches: &ArgMatches,
+                                       config: Option<&Value>,
+                                       data: &TemplateData,
+                                       default: ListFormat,
+                                       entries: I)
+    -> Result<()>
+    where I: Iterator<Item = FileLockEntry<'a>>
+{
+    if let Some(template) = try!(get_template(matches, config)) {
+        return get_pipeline(matches).list(&TemplateLister::new(template, data), entries)
+    }
+
+    get_format(matches)
+        .unwrap_or(default)
+        .list(&get_pipeline(matches),
+              get_fields(matches),
+              matches.is_present(list_with_content()),
+              entries)
+}
+
+/// Build the listing `Pipeline` from the sort, group and paging arguments of the list subcommand
+pub fn get_pipeline(matches: &ArgMatches) -> Pipeline {
+    let mut pipeline = Pipeline::new().reverse(matches.is_present(list_reverse()));
+
+    if let Some(keys) = matches.values_of(list_sort()) {
+        for key in keys.filter_map(SortKey::parse) {
+            pipeline = pipeline.sort_by(key);
+        }
+    }
+
+    if let Some(path) = matches.value_of(list_group_by()) {
+        pipeline = pipeline.group_by(String::from(path));
+    }
+
+    if let Some(n) = matches.value_of(list_limit()).and_then(|n| n.parse::<usize>().ok()) {
+        pipeline = pipeline.limit(n);
+    }
+
+    if let Some(n) = matches.value_of(list_offset()).and_then(|n| n.parse::<usize>().ok()) {
+        pipeline = pipeline.offset(n);
+    }
+
+    pipeline
+}
+
+/// List the entries with the lister chosen in the list subcommand of `m`
+///
+/// `config` and `data` are passed on to `list_entries_with_format()`.
 // TODO: Add Registry for listers where a HashMap name->lister is in and where we can fetch the
 // lister from.
-pub fn list_entries_with_lister<'a, I>(m: &ArgMatches, entries: I) -> Result<()>
+pub fn list_entries_with_lister<'a, I>(m: &ArgMatches,
+                                       config: Option<&Value>,
+                                       data: &TemplateData,
+                                       entries: I)
+    -> Result<()>
     where I: Iterator<Item = FileLockEntry<'a>>
 {
     if let Some(matches) = m.subcommand_matches(list_subcommand_name()) {
+        let pipeline = get_pipeline(matches);
+
+        if get_format(matches).is_some() || matches.is_present(list_template()) {
+            return list_entries_with_format(matches, config, data, ListFormat::Line, entries)
+        }
+
         if matches.is_present(list_backend_line()) {
-            return LineLister::new("<unknown>").list(entries)
+            return pipeline.list(&LineLister::new("<unknown>"), entries)
         };
 
         if matches.is_present(list_backend_path()) {
-            return PathLister::new(false).list(entries)
+            return pipeline.list(&PathLister::new(false), entries)
         }
 
 
         if matches.is_present(list_backend_path_absolute()) {
-            return PathLister::new(true).list(entries)
+            return pipeline.list(&PathLister::new(true), entries)
         }
 
         Ok(())
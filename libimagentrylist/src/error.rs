@@ -23,6 +23,7 @@ generate_error_module!(
         FormatError    => "FormatError",
         EntryError     => "EntryError",
         IterationError => "IterationError",
+        TemplateError  => "Template error",
         CLIError       => "No CLI subcommand for listing entries"
     );
 );
@@ -0,0 +1,117 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Selecting a lister by name, as done by the `--format` commandline flag
+
+use libimagstore::store::FileLockEntry;
+
+use result::Result;
+use pipeline::Pipeline;
+use listers::csv::CsvLister;
+use listers::fields::{read_field, field_to_string};
+use listers::json::JsonLister;
+use listers::line::LineLister;
+use listers::path::PathLister;
+use listers::table::TableLister;
+use listers::toml::TomlLister;
+
+/// The output formats which can be chosen on the commandline
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListFormat {
+    Line,
+    Path,
+    PathAbsolute,
+    Table,
+    Json,
+    Csv,
+    Toml,
+}
+
+impl ListFormat {
+
+    /// All format names, for use as possible values of the commandline flag
+    pub fn names() -> &'static [&'static str] {
+        &["line", "path", "path-absolute", "table", "json", "csv", "toml"]
+    }
+
+    pub fn parse(s: &str) -> Option<ListFormat> {
+        match s {
+            "line"          => Some(ListFormat::Line),
+            "path"          => Some(ListFormat::Path),
+            "path-absolute" => Some(ListFormat::PathAbsolute),
+            "table"         => Some(ListFormat::Table),
+            "json"          => Some(ListFormat::Json),
+            "csv"           => Some(ListFormat::Csv),
+            "toml"          => Some(ListFormat::Toml),
+            _               => None,
+        }
+    }
+
+    /// List `entries` in this format, after passing them through `pipeline`
+    ///
+    /// `fields` are the header fields to print and `with_content` decides whether the content is
+    /// printed as well. Formats which only print the id of an entry ignore both.
+    pub fn list<'a, I>(&self, pipeline: &Pipeline, fields: Vec<String>, with_content: bool, entries: I)
+        -> Result<()>
+        where I: Iterator<Item = FileLockEntry<'a>>
+    {
+        match *self {
+            ListFormat::Line         => pipeline.list(&LineLister::new("<unknown>"), entries),
+            ListFormat::Path         => pipeline.list(&PathLister::new(false), entries),
+            ListFormat::PathAbsolute => pipeline.list(&PathLister::new(true), entries),
+            ListFormat::Json => pipeline.list(&JsonLister::new(fields).with_content(with_content), entries),
+            ListFormat::Csv  => pipeline.list(&CsvLister::new(fields).with_content(with_content), entries),
+            ListFormat::Toml => pipeline.list(&TomlLister::new(fields).with_content(with_content), entries),
+            ListFormat::Table => {
+                let mut header = vec![String::from("id")];
+                header.extend(fields.iter().cloned());
+
+                let lister = TableLister::new(move |entry: &FileLockEntry| {
+                    let mut row = vec![format!("{}", entry.get_location())];
+                    for field in fields.iter() {
+                        let cell = read_field(entry, field)
+                            .ok()
+                            .and_then(|v| v)
+                            .map(|v| field_to_string(&v))
+                            .unwrap_or_else(String::new);
+                        row.push(cell);
+                    }
+                    row
+                });
+
+                pipeline.list(&lister.with_header(header), entries)
+            },
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::ListFormat;
+
+    #[test]
+    fn test_all_names_parse() {
+        for name in ListFormat::names() {
+            assert!(ListFormat::parse(name).is_some(), "'{}' does not parse", name);
+        }
+        assert_eq!(None, ListFormat::parse("yaml"));
+    }
+
+}
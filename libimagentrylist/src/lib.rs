@@ -37,14 +37,19 @@ extern crate clap;
 #[macro_use] extern crate log;
 extern crate toml;
 extern crate prettytable;
+extern crate rustc_serialize;
 
 extern crate libimagstore;
 extern crate libimagutil;
 #[macro_use] extern crate libimagerror;
 
+#[cfg(test)] extern crate tempdir;
+
 pub mod cli;
 pub mod error;
+pub mod format;
 pub mod lister;
 pub mod listers;
+pub mod pipeline;
 pub mod result;
 
@@ -0,0 +1,122 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::io::stdout;
+use std::io::Write;
+
+use lister::Lister;
+use listers::fields::{read_field, field_to_string};
+use result::Result;
+use error::ListErrorKind as LEK;
+
+use libimagstore::store::FileLockEntry;
+use libimagerror::into::IntoError;
+
+/// Lists entries as comma separated values, one row per entry
+///
+/// The first row names the columns: `id`, one column per selected header field and `content` if
+/// requested. Missing fields are empty cells. Quoting follows RFC 4180.
+pub struct CsvLister {
+    fields: Vec<String>,
+    with_content: bool,
+}
+
+impl CsvLister {
+
+    pub fn new(fields: Vec<String>) -> CsvLister {
+        CsvLister {
+            fields: fields,
+            with_content: false,
+        }
+    }
+
+    pub fn with_content(mut self, b: bool) -> CsvLister {
+        self.with_content = b;
+        self
+    }
+
+}
+
+impl Lister for CsvLister {
+
+    fn list<'b, I: Iterator<Item = FileLockEntry<'b>>>(&self, entries: I) -> Result<()> {
+        let out = stdout();
+        let mut out = out.lock();
+
+        let mut titles = vec![String::from("id")];
+        titles.extend(self.fields.iter().cloned());
+        if self.with_content {
+            titles.push(String::from("content"));
+        }
+        try!(write!(out, "{}\r\n", to_row(&titles))
+             .map_err(|e| LEK::IOError.into_error_with_cause(Box::new(e))));
+
+        for entry in entries {
+            let mut row = vec![format!("{}", entry.get_location())];
+            for field in self.fields.iter() {
+                let cell = try!(read_field(&entry, field))
+                    .map(|v| field_to_string(&v))
+                    .unwrap_or_else(String::new);
+                row.push(cell);
+            }
+            if self.with_content {
+                row.push(entry.get_content().clone());
+            }
+
+            try!(write!(out, "{}\r\n", to_row(&row))
+                 .map_err(|e| LEK::IOError.into_error_with_cause(Box::new(e))));
+        }
+
+        Ok(())
+    }
+
+}
+
+fn to_row(cells: &[String]) -> String {
+    cells.iter().map(|c| escape(c)).collect::<Vec<_>>().join(",")
+}
+
+/// Quote a cell if it contains a separator, a quote or a line break
+fn escape(cell: &str) -> String {
+    if cell.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", cell.replace("\"", "\"\""))
+    } else {
+        String::from(cell)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape, to_row};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!("plain", escape("plain"));
+        assert_eq!("\"a,b\"", escape("a,b"));
+        assert_eq!("\"say \"\"hi\"\"\"", escape("say \"hi\""));
+        assert_eq!("\"two\nlines\"", escape("two\nlines"));
+    }
+
+    #[test]
+    fn test_to_row() {
+        let cells = vec![String::from("notes/a"), String::new(), String::from("x,y")];
+        assert_eq!("notes/a,,\"x,y\"", to_row(&cells));
+    }
+
+}
@@ -0,0 +1,108 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Helpers for listers which print a selection of header fields of an entry
+
+use std::collections::BTreeMap;
+
+use toml::Value;
+
+use libimagstore::store::Entry;
+use libimagstore::toml_ext::TomlValueExt;
+
+use result::Result;
+use error::MapErrInto;
+use error::ListErrorKind as LEK;
+
+/// Read the header field at `path` from the entry
+pub fn read_field(entry: &Entry, path: &str) -> Result<Option<Value>> {
+    entry.get_header().read(path).map_err_into(LEK::EntryError)
+}
+
+/// Build a header table which only contains the fields at `paths`
+///
+/// The fields keep their position in the header, so `note.name` ends up as `name` in the `note`
+/// table. Fields the entry does not have are left out. If no paths are given, the whole header
+/// is returned.
+pub fn select_header(entry: &Entry, paths: &[String]) -> Result<Value> {
+    if paths.is_empty() {
+        return Ok(entry.get_header().clone());
+    }
+
+    let mut table = BTreeMap::new();
+    for path in paths {
+        if let Some(value) = try!(read_field(entry, path)) {
+            insert_nested(&mut table, path, value);
+        }
+    }
+    Ok(Value::Table(table))
+}
+
+/// Render a field value as plain text, without the quotes toml would put around strings
+pub fn field_to_string(value: &Value) -> String {
+    match *value {
+        Value::String(ref s) => s.clone(),
+        ref other            => format!("{}", other),
+    }
+}
+
+fn insert_nested(table: &mut BTreeMap<String, Value>, path: &str, value: Value) {
+    match path.find('.') {
+        None      => { table.insert(String::from(path), value); },
+        Some(pos) => {
+            let sub = table
+                .entry(String::from(&path[..pos]))
+                .or_insert_with(|| Value::Table(BTreeMap::new()));
+
+            if let Value::Table(ref mut sub) = *sub {
+                insert_nested(sub, &path[pos + 1..], value);
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use toml::Value;
+
+    use super::insert_nested;
+
+    #[test]
+    fn test_insert_nested() {
+        let mut table = BTreeMap::new();
+        insert_nested(&mut table, "a", Value::Integer(1));
+        insert_nested(&mut table, "b.c", Value::Integer(2));
+        insert_nested(&mut table, "b.d.e", Value::Integer(3));
+
+        assert_eq!(Some(&Value::Integer(1)), table.get("a"));
+        match table.get("b") {
+            Some(&Value::Table(ref b)) => {
+                assert_eq!(Some(&Value::Integer(2)), b.get("c"));
+                match b.get("d") {
+                    Some(&Value::Table(ref d)) => assert_eq!(Some(&Value::Integer(3)), d.get("e")),
+                    other => panic!("Expected table, got {:?}", other),
+                }
+            },
+            other => panic!("Expected table, got {:?}", other),
+        }
+    }
+
+}
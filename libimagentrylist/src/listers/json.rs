@@ -0,0 +1,204 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::BTreeMap;
+use std::io::stdout;
+use std::io::Write;
+
+use rustc_serialize::json::Json;
+use toml::Value;
+
+use lister::Lister;
+use listers::fields::select_header;
+use result::Result;
+use error::ListErrorKind as LEK;
+
+use libimagstore::store::FileLockEntry;
+use libimagerror::into::IntoError;
+
+/// Lists entries as a JSON array of objects with the keys `id`, `header` and optionally `content`
+///
+/// Each object is written as soon as the entry is read, one per line, so huge lists do not have
+/// to be held in memory.
+pub struct JsonLister {
+    fields: Vec<String>,
+    with_content: bool,
+}
+
+impl JsonLister {
+
+    /// Create a lister which prints the header fields at `fields`, or the whole header if `fields`
+    /// is empty
+    pub fn new(fields: Vec<String>) -> JsonLister {
+        JsonLister {
+            fields: fields,
+            with_content: false,
+        }
+    }
+
+    pub fn with_content(mut self, b: bool) -> JsonLister {
+        self.with_content = b;
+        self
+    }
+
+}
+
+impl JsonLister {
+
+    fn write_to<'b, W, I>(&self, out: &mut W, entries: I) -> Result<()>
+        where W: Write,
+              I: Iterator<Item = FileLockEntry<'b>>
+    {
+        try!(write!(out, "[").map_err(|e| LEK::IOError.into_error_with_cause(Box::new(e))));
+        for (i, entry) in entries.enumerate() {
+            let mut obj = BTreeMap::new();
+            obj.insert(String::from("id"), Json::String(format!("{}", entry.get_location())));
+            obj.insert(String::from("header"), toml_to_json(&try!(select_header(&entry, &self.fields))));
+            if self.with_content {
+                obj.insert(String::from("content"), Json::String(entry.get_content().clone()));
+            }
+
+            let sep = if i == 0 { "\n" } else { ",\n" };
+            try!(write!(out, "{}{}", sep, Json::Object(obj))
+                 .map_err(|e| LEK::IOError.into_error_with_cause(Box::new(e))));
+        }
+        write!(out, "\n]\n").map_err(|e| LEK::IOError.into_error_with_cause(Box::new(e)))
+    }
+
+}
+
+impl Lister for JsonLister {
+
+    fn list<'b, I: Iterator<Item = FileLockEntry<'b>>>(&self, entries: I) -> Result<()> {
+        let out = stdout();
+        let mut out = out.lock();
+        self.write_to(&mut out, entries)
+    }
+
+}
+
+fn toml_to_json(v: &Value) -> Json {
+    match *v {
+        Value::String(ref s)   => Json::String(s.clone()),
+        Value::Integer(i)      => Json::I64(i),
+        Value::Float(f)        => Json::F64(f),
+        Value::Boolean(b)      => Json::Boolean(b),
+        Value::Datetime(ref s) => Json::String(s.clone()),
+        Value::Array(ref a)    => Json::Array(a.iter().map(toml_to_json).collect()),
+        Value::Table(ref t)    => {
+            Json::Object(t.iter().map(|(k, v)| (k.clone(), toml_to_json(v))).collect())
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use rustc_serialize::json::Json;
+    use tempdir::TempDir;
+    use toml::Value;
+
+    use libimagstore::store::{FileLockEntry, Store};
+    use libimagstore::toml_ext::TomlValueExt;
+
+    use super::{JsonLister, toml_to_json};
+
+    fn get_store(dir: &TempDir) -> Store {
+        let store = Store::new(PathBuf::from(dir.path()), None).unwrap();
+        for &(id, name) in [("json/a", "first"), ("json/b", "second")].iter() {
+            let mut entry = store.create(PathBuf::from(id)).unwrap();
+            assert!(entry.get_header_mut().set("note.name", Value::String(String::from(name))).is_ok());
+            assert!(entry.get_header_mut().set("note.prio", Value::Integer(1)).is_ok());
+            *entry.get_content_mut() = String::from("text");
+        }
+        store
+    }
+
+    fn list(lister: JsonLister, store: &Store) -> Json {
+        let entries = ["json/a", "json/b"]
+            .iter()
+            .map(|id| store.get(PathBuf::from(id)).unwrap().unwrap());
+
+        let mut out = vec![];
+        assert!(lister.write_to(&mut out, entries).is_ok());
+        Json::from_str(&String::from_utf8(out).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_toml_to_json() {
+        let mut table = BTreeMap::new();
+        table.insert(String::from("s"), Value::String(String::from("text")));
+        table.insert(String::from("i"), Value::Integer(3));
+        table.insert(String::from("f"), Value::Float(1.5));
+        table.insert(String::from("b"), Value::Boolean(true));
+        table.insert(String::from("d"), Value::Datetime(String::from("2016-10-12T10:00:00Z")));
+        table.insert(String::from("a"), Value::Array(vec![Value::Integer(1), Value::Integer(2)]));
+
+        let json = toml_to_json(&Value::Table(table));
+        assert_eq!(json.find("s"), Some(&Json::String(String::from("text"))));
+        assert_eq!(json.find("i"), Some(&Json::I64(3)));
+        assert_eq!(json.find("f"), Some(&Json::F64(1.5)));
+        assert_eq!(json.find("b"), Some(&Json::Boolean(true)));
+        assert_eq!(json.find("d"), Some(&Json::String(String::from("2016-10-12T10:00:00Z"))));
+        assert_eq!(json.find("a"), Some(&Json::Array(vec![Json::I64(1), Json::I64(2)])));
+    }
+
+    #[test]
+    fn test_list_fields() {
+        let dir   = TempDir::new("json-lister").unwrap();
+        let store = get_store(&dir);
+
+        let json    = list(JsonLister::new(vec![String::from("note.name")]), &store);
+        let entries = json.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].find("id"), Some(&Json::String(String::from("json/a"))));
+        assert_eq!(entries[0].find_path(&["header", "note", "name"]),
+                   Some(&Json::String(String::from("first"))));
+        assert_eq!(entries[0].find_path(&["header", "note", "prio"]), None);
+        assert_eq!(entries[0].find("content"), None);
+        assert_eq!(entries[1].find_path(&["header", "note", "name"]),
+                   Some(&Json::String(String::from("second"))));
+    }
+
+    #[test]
+    fn test_list_whole_header_with_content() {
+        let dir   = TempDir::new("json-lister").unwrap();
+        let store = get_store(&dir);
+
+        let json    = list(JsonLister::new(vec![]).with_content(true), &store);
+        let entries = json.as_array().unwrap();
+
+        assert_eq!(entries[1].find_path(&["header", "note", "prio"]), Some(&Json::I64(1)));
+        assert!(entries[1].find_path(&["header", "imag", "version"]).is_some());
+        assert_eq!(entries[1].find("content"), Some(&Json::String(String::from("text"))));
+    }
+
+    #[test]
+    fn test_list_nothing() {
+        let entries : Vec<FileLockEntry> = vec![];
+
+        let mut out = vec![];
+        assert!(JsonLister::new(vec![]).write_to(&mut out, entries.into_iter()).is_ok());
+        assert_eq!(Json::from_str(&String::from_utf8(out).unwrap()).unwrap(), Json::Array(vec![]));
+    }
+
+}
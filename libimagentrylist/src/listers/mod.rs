@@ -18,6 +18,12 @@
 //
 
 pub mod core;
+pub mod csv;
+pub mod fields;
+pub mod json;
 pub mod line;
 pub mod path;
 pub mod table;
+pub mod template;
+pub mod toml;
+pub mod tree;
@@ -0,0 +1,498 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! A lister which renders each entry with a user supplied format string
+//!
+//! The format string is plain text with placeholders in braces:
+//!
+//! * `{id}` - the id of the entry in the store
+//! * `{header.<path>}` - the header field at `<path>`, for example `{header.note.name}`
+//! * `{tags}` - the tags of the entry, separated by commas
+//! * `{links}` - the number of internal links of the entry
+//! * `{external-links}` - the number of external links of the entry
+//! * `{content}` - the content on a single line, `{content.N}` cuts it after `N` characters
+//!
+//! A placeholder can be padded to a width, aligned left (`{id:<30}`), right (`{links:>3}`) or
+//! centered (`{tags:^20}`). Text between `{?KEY}` and `{/}` is only printed if the placeholder
+//! `KEY` is not empty (and not zero, for the counts), text between `{!KEY}` and `{/}` only if it
+//! is. Literal braces are written as `{{` and `}}`.
+//!
+//! Tags and links are maintained by other libraries, the `TemplateLister` gets them from the
+//! `TemplateData` it is created with.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fmt::Result as FmtResult;
+use std::io::stdout;
+use std::io::Write;
+use std::iter::repeat;
+use std::result::Result as RResult;
+
+use lister::Lister;
+use listers::fields::{read_field, field_to_string};
+use result::Result;
+use error::ListErrorKind as LEK;
+
+use libimagstore::store::{Entry, FileLockEntry};
+use libimagerror::into::IntoError;
+
+/// Provides the values of the placeholders which are not read from the entry itself
+///
+/// `None` is returned if a value cannot be read, the placeholder is left empty then.
+pub trait TemplateData {
+
+    /// The tags of the entry, for `{tags}`
+    fn tags(&self, entry: &Entry) -> Option<Vec<String>>;
+
+    /// The number of internal links of the entry, for `{links}`
+    fn internal_link_count(&self, entry: &Entry) -> Option<usize>;
+
+    /// The number of external links of the entry, for `{external-links}`
+    fn external_link_count(&self, entry: &Entry) -> Option<usize>;
+
+}
+
+/// `TemplateData` which knows nothing, `{tags}`, `{links}` and `{external-links}` are empty
+pub struct NoTemplateData;
+
+impl TemplateData for NoTemplateData {
+
+    fn tags(&self, _: &Entry) -> Option<Vec<String>> {
+        None
+    }
+
+    fn internal_link_count(&self, _: &Entry) -> Option<usize> {
+        None
+    }
+
+    fn external_link_count(&self, _: &Entry) -> Option<usize> {
+        None
+    }
+
+}
+
+/// A value which can be inserted into the template
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Field {
+    Id,
+    Header(String),
+    Tags,
+    Links,
+    ExternalLinks,
+    Content(Option<usize>),
+}
+
+impl Field {
+
+    fn is_count(&self) -> bool {
+        match *self {
+            Field::Links | Field::ExternalLinks => true,
+            _                                   => false,
+        }
+    }
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Padding {
+    align: Align,
+    width: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Placeholder(Field, Option<Padding>),
+    Conditional {
+        field: Field,
+        negate: bool,
+        body: Vec<Part>,
+    },
+}
+
+/// An error in a template format string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    /// The position in the template (in characters) where the error occured
+    pub position: usize,
+    pub message: String,
+}
+
+impl TemplateError {
+
+    fn new<S: Into<String>>(position: usize, message: S) -> TemplateError {
+        TemplateError {
+            position: position,
+            message: message.into(),
+        }
+    }
+
+}
+
+impl Display for TemplateError {
+
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "Invalid template at position {}: {}", self.position, self.message)
+    }
+
+}
+
+impl Error for TemplateError {
+
+    fn description(&self) -> &str {
+        "Invalid template"
+    }
+
+}
+
+/// A parsed format string
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+
+    pub fn parse(s: &str) -> RResult<Template, TemplateError> {
+        let mut parser = Parser { chars: s.chars().collect(), pos: 0 };
+        parser.parse_parts(None).map(|parts| Template { parts: parts })
+    }
+
+    /// Render the template, getting the value of each placeholder from `lookup`
+    fn render<F: Fn(&Field) -> String>(&self, lookup: &F) -> String {
+        let mut out = String::new();
+        render_parts(&self.parts, lookup, &mut out);
+        out
+    }
+
+    /// Render the template for an entry, with tags and links from `data`
+    pub fn render_entry(&self, entry: &Entry, data: &TemplateData) -> String {
+        self.render(&|field: &Field| field_value(entry, data, field))
+    }
+
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+
+    /// Parse until the end of the template, or until `{/}` if inside of the conditional which
+    /// starts at position `open`
+    fn parse_parts(&mut self, open: Option<usize>) -> RResult<Vec<Part>, TemplateError> {
+        let mut parts = vec![];
+        let mut text  = String::new();
+
+        loop {
+            let c = match self.chars.get(self.pos) {
+                Some(c) => *c,
+                None    => {
+                    return match open {
+                        Some(pos) => Err(TemplateError::new(pos, "Conditional is not closed with '{/}'")),
+                        None      => {
+                            flush_text(&mut parts, &mut text);
+                            Ok(parts)
+                        },
+                    };
+                },
+            };
+            let next = self.chars.get(self.pos + 1).cloned();
+
+            match (c, next) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    text.push(c);
+                    self.pos += 2;
+                },
+                ('}', _) => return Err(TemplateError::new(self.pos, "Unmatched '}', write '}}' for a literal brace")),
+                ('{', _) => {
+                    let start = self.pos;
+                    let tag   = try!(self.read_tag());
+                    flush_text(&mut parts, &mut text);
+
+                    if tag == "/" {
+                        return match open {
+                            Some(_) => Ok(parts),
+                            None    => Err(TemplateError::new(start, "'{/}' without a conditional")),
+                        };
+                    }
+
+                    if tag.starts_with('?') || tag.starts_with('!') {
+                        let field = try!(parse_field(&tag[1..]).map_err(|m| TemplateError::new(start, m)));
+                        let body  = try!(self.parse_parts(Some(start)));
+                        parts.push(Part::Conditional {
+                            field: field,
+                            negate: tag.starts_with('!'),
+                            body: body,
+                        });
+                    } else {
+                        let (key, padding) = match tag.find(':') {
+                            Some(i) => (&tag[..i], Some(&tag[i + 1..])),
+                            None    => (&tag[..], None),
+                        };
+                        let field   = try!(parse_field(key).map_err(|m| TemplateError::new(start, m)));
+                        let padding = match padding {
+                            Some(p) => Some(try!(parse_padding(p).map_err(|m| TemplateError::new(start, m)))),
+                            None    => None,
+                        };
+                        parts.push(Part::Placeholder(field, padding));
+                    }
+                },
+                _ => {
+                    text.push(c);
+                    self.pos += 1;
+                },
+            }
+        }
+    }
+
+    /// Read the text between `{` at the current position and the next `}`
+    fn read_tag(&mut self) -> RResult<String, TemplateError> {
+        let start = self.pos;
+        let mut tag = String::new();
+        self.pos += 1;
+
+        loop {
+            match self.chars.get(self.pos) {
+                Some(&'}') => {
+                    self.pos += 1;
+                    return Ok(tag);
+                },
+                Some(&'{') | None => return Err(TemplateError::new(start, "Placeholder is not closed with '}'")),
+                Some(c)           => tag.push(*c),
+            }
+            self.pos += 1;
+        }
+    }
+
+}
+
+fn flush_text(parts: &mut Vec<Part>, text: &mut String) {
+    if !text.is_empty() {
+        parts.push(Part::Text(text.clone()));
+        text.clear();
+    }
+}
+
+fn parse_field(key: &str) -> RResult<Field, String> {
+    match key {
+        "id"             => Ok(Field::Id),
+        "tags"           => Ok(Field::Tags),
+        "links"          => Ok(Field::Links),
+        "external-links" => Ok(Field::ExternalLinks),
+        "content"        => Ok(Field::Content(None)),
+        _ => {
+            if key.starts_with("content.") {
+                key["content.".len()..]
+                    .parse::<usize>()
+                    .map(|n| Field::Content(Some(n)))
+                    .map_err(|_| format!("Not a length: '{}'", &key["content.".len()..]))
+            } else if key.starts_with("header.") && key.len() > "header.".len() {
+                Ok(Field::Header(String::from(&key["header.".len()..])))
+            } else {
+                Err(format!("Unknown placeholder: '{}'", key))
+            }
+        },
+    }
+}
+
+fn parse_padding(spec: &str) -> RResult<Padding, String> {
+    let align = match spec.chars().next() {
+        Some('<') => Align::Left,
+        Some('>') => Align::Right,
+        Some('^') => Align::Center,
+        _         => return Err(format!("Padding must start with '<', '>' or '^': '{}'", spec)),
+    };
+
+    spec[1..]
+        .parse::<usize>()
+        .map(|w| Padding { align: align, width: w })
+        .map_err(|_| format!("Not a width: '{}'", &spec[1..]))
+}
+
+fn render_parts<F: Fn(&Field) -> String>(parts: &[Part], lookup: &F, out: &mut String) {
+    for part in parts {
+        match *part {
+            Part::Text(ref s) => out.push_str(s),
+            Part::Placeholder(ref field, ref padding) => {
+                let value = lookup(field);
+                match *padding {
+                    Some(ref p) => out.push_str(&pad(value, p)),
+                    None        => out.push_str(&value),
+                }
+            },
+            Part::Conditional { ref field, negate, ref body } => {
+                let value = lookup(field);
+                let set   = !(value.is_empty() || (field.is_count() && value == "0"));
+                if set != negate {
+                    render_parts(body, lookup, out);
+                }
+            },
+        }
+    }
+}
+
+fn pad(s: String, padding: &Padding) -> String {
+    let len = s.chars().count();
+    if len >= padding.width {
+        return s;
+    }
+
+    let fill   = padding.width - len;
+    let spaces = |n: usize| repeat(' ').take(n).collect::<String>();
+    match padding.align {
+        Align::Left   => format!("{}{}", s, spaces(fill)),
+        Align::Right  => format!("{}{}", spaces(fill), s),
+        Align::Center => format!("{}{}{}", spaces(fill / 2), s, spaces(fill - fill / 2)),
+    }
+}
+
+/// The content on one line, with all whitespace collapsed and cut after `max` characters
+fn preview(content: &str, max: Option<usize>) -> String {
+    let flat = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    match max {
+        Some(n) if flat.chars().count() > n => {
+            let mut s = flat.chars().take(n).collect::<String>();
+            s.push_str("...");
+            s
+        },
+        _ => flat,
+    }
+}
+
+/// Get the value of a placeholder for an entry. Fields which cannot be read are left empty.
+fn field_value(entry: &Entry, data: &TemplateData, field: &Field) -> String {
+    match *field {
+        Field::Id => format!("{}", entry.get_location()),
+        Field::Header(ref path) => read_field(entry, path)
+            .ok()
+            .and_then(|v| v)
+            .map(|v| field_to_string(&v))
+            .unwrap_or_else(String::new),
+        Field::Tags => data.tags(entry).map(|tags| tags.join(", ")).unwrap_or_else(String::new),
+        Field::Links => data
+            .internal_link_count(entry)
+            .map(|n| format!("{}", n))
+            .unwrap_or_else(String::new),
+        Field::ExternalLinks => data
+            .external_link_count(entry)
+            .map(|n| format!("{}", n))
+            .unwrap_or_else(String::new),
+        Field::Content(max) => preview(entry.get_content(), max),
+    }
+}
+
+/// Lists entries by rendering a `Template` for each of them, one line per entry
+pub struct TemplateLister<'a> {
+    template: Template,
+    data: &'a TemplateData,
+}
+
+impl<'a> TemplateLister<'a> {
+
+    pub fn new(template: Template, data: &'a TemplateData) -> TemplateLister<'a> {
+        TemplateLister {
+            template: template,
+            data: data,
+        }
+    }
+
+}
+
+impl<'a> Lister for TemplateLister<'a> {
+
+    fn list<'b, I: Iterator<Item = FileLockEntry<'b>>>(&self, entries: I) -> Result<()> {
+        let out = stdout();
+        let mut out = out.lock();
+
+        for entry in entries {
+            try!(write!(out, "{}\n", self.template.render_entry(&entry, self.data))
+                 .map_err(|e| LEK::IOError.into_error_with_cause(Box::new(e))));
+        }
+
+        Ok(())
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Template, Field, preview};
+
+    fn render(template: &str) -> String {
+        let lookup = |field: &Field| match *field {
+            Field::Id                => String::from("notes/a"),
+            Field::Header(ref p)     => if p == "note.name" { String::from("A") } else { String::new() },
+            Field::Tags              => String::from("work, home"),
+            Field::Links             => String::from("0"),
+            Field::ExternalLinks     => String::from("2"),
+            Field::Content(max)      => preview("some\n  content here", max),
+        };
+        Template::parse(template).unwrap().render(&lookup)
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!("notes/a: A [work, home]", render("{id}: {header.note.name} [{tags}]"));
+        assert_eq!("some content here", render("{content}"));
+        assert_eq!("some...", render("{content.4}"));
+    }
+
+    #[test]
+    fn test_padding() {
+        assert_eq!("notes/a   |", render("{id:<10}|"));
+        assert_eq!("   notes/a|", render("{id:>10}|"));
+        assert_eq!(" notes/a  |", render("{id:^10}|"));
+        assert_eq!("notes/a|", render("{id:<3}|"));
+    }
+
+    #[test]
+    fn test_conditionals() {
+        assert_eq!("A", render("{?header.note.name}{header.note.name}{/}"));
+        assert_eq!("", render("{?header.other}x{/}"));
+        assert_eq!("unlinked", render("{?links}linked{/}{!links}unlinked{/}"));
+        assert_eq!("2 ext", render("{?external-links}{external-links} ext{/}"));
+        assert_eq!("a b", render("{?tags}a{?links}x{/} b{/}"));
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!("{notes/a}", render("{{{id}}}"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(0, Template::parse("{unknown}").unwrap_err().position);
+        assert_eq!(3, Template::parse("ab {id").unwrap_err().position);
+        assert_eq!(0, Template::parse("{?id}x").unwrap_err().position);
+        assert!(Template::parse("x {/}").is_err());
+        assert!(Template::parse("a } b").is_err());
+        assert!(Template::parse("{id:10}").is_err());
+        assert!(Template::parse("{content.x}").is_err());
+    }
+
+}
@@ -0,0 +1,147 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::BTreeMap;
+use std::io::stdout;
+use std::io::Write;
+
+use toml::Value;
+
+use lister::Lister;
+use listers::fields::select_header;
+use result::Result;
+use error::ListErrorKind as LEK;
+
+use libimagstore::store::FileLockEntry;
+use libimagerror::into::IntoError;
+
+/// Lists entries as a TOML document with one `[[entry]]` table per entry
+///
+/// Every table has the keys `id`, `header` and optionally `content`. As arrays of tables can be
+/// split up in TOML, each entry is written on its own and the output still parses as a whole.
+pub struct TomlLister {
+    fields: Vec<String>,
+    with_content: bool,
+}
+
+impl TomlLister {
+
+    pub fn new(fields: Vec<String>) -> TomlLister {
+        TomlLister {
+            fields: fields,
+            with_content: false,
+        }
+    }
+
+    pub fn with_content(mut self, b: bool) -> TomlLister {
+        self.with_content = b;
+        self
+    }
+
+}
+
+impl TomlLister {
+
+    fn write_to<'b, W, I>(&self, out: &mut W, entries: I) -> Result<()>
+        where W: Write,
+              I: Iterator<Item = FileLockEntry<'b>>
+    {
+        for entry in entries {
+            let mut table = BTreeMap::new();
+            table.insert(String::from("id"), Value::String(format!("{}", entry.get_location())));
+            table.insert(String::from("header"), try!(select_header(&entry, &self.fields)));
+            if self.with_content {
+                table.insert(String::from("content"), Value::String(entry.get_content().clone()));
+            }
+
+            let mut doc = BTreeMap::new();
+            doc.insert(String::from("entry"), Value::Array(vec![Value::Table(table)]));
+
+            try!(write!(out, "{}\n", Value::Table(doc))
+                 .map_err(|e| LEK::IOError.into_error_with_cause(Box::new(e))));
+        }
+
+        Ok(())
+    }
+
+}
+
+impl Lister for TomlLister {
+
+    fn list<'b, I: Iterator<Item = FileLockEntry<'b>>>(&self, entries: I) -> Result<()> {
+        let out = stdout();
+        let mut out = out.lock();
+        self.write_to(&mut out, entries)
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use tempdir::TempDir;
+    use toml::{Parser, Value};
+
+    use libimagstore::store::Store;
+    use libimagstore::toml_ext::TomlValueExt;
+
+    use super::TomlLister;
+
+    fn list(lister: TomlLister) -> Value {
+        let dir   = TempDir::new("toml-lister").unwrap();
+        let store = Store::new(PathBuf::from(dir.path()), None).unwrap();
+        for &(id, name) in [("toml/a", "first"), ("toml/b", "second")].iter() {
+            let mut entry = store.create(PathBuf::from(id)).unwrap();
+            assert!(entry.get_header_mut().set("note.name", Value::String(String::from(name))).is_ok());
+            assert!(entry.get_header_mut().set("note.prio", Value::Integer(1)).is_ok());
+            *entry.get_content_mut() = String::from("text");
+        }
+
+        let entries = ["toml/a", "toml/b"]
+            .iter()
+            .map(|id| store.get(PathBuf::from(id)).unwrap().unwrap());
+
+        let mut out = vec![];
+        assert!(lister.write_to(&mut out, entries).is_ok());
+        Value::Table(Parser::new(&String::from_utf8(out).unwrap()).parse().unwrap())
+    }
+
+    #[test]
+    fn test_list_fields() {
+        let doc = list(TomlLister::new(vec![String::from("note.name")]));
+
+        assert_eq!(doc.lookup("entry.0.id"), Some(&Value::String(String::from("toml/a"))));
+        assert_eq!(doc.lookup("entry.0.header.note.name"), Some(&Value::String(String::from("first"))));
+        assert_eq!(doc.lookup("entry.0.header.note.prio"), None);
+        assert_eq!(doc.lookup("entry.0.content"), None);
+        assert_eq!(doc.lookup("entry.1.id"), Some(&Value::String(String::from("toml/b"))));
+        assert_eq!(doc.lookup("entry.1.header.note.name"), Some(&Value::String(String::from("second"))));
+    }
+
+    #[test]
+    fn test_list_whole_header_with_content() {
+        let doc = list(TomlLister::new(vec![]).with_content(true));
+
+        assert_eq!(doc.lookup("entry.1.header.note.prio"), Some(&Value::Integer(1)));
+        assert!(doc.lookup("entry.1.header.imag.version").is_some());
+        assert_eq!(doc.lookup("entry.1.content"), Some(&Value::String(String::from("text"))));
+    }
+
+}
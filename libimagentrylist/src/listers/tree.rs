@@ -0,0 +1,187 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::stdout;
+use std::io::Write;
+use std::path::Path;
+
+use lister::Lister;
+use result::Result;
+use error::ListErrorKind as LEK;
+
+use libimagstore::store::FileLockEntry;
+use libimagerror::into::IntoError;
+
+/// The folders and entries of a part of the store, counting the entries below each folder
+#[derive(Debug, Clone, Default)]
+pub struct EntryTree {
+    count: usize,
+    folders: BTreeMap<String, EntryTree>,
+    entries: BTreeSet<String>,
+}
+
+impl EntryTree {
+
+    pub fn new() -> EntryTree {
+        EntryTree::default()
+    }
+
+    /// Add an entry by its store-local id, like `notes/work/meeting~0.1.0`
+    pub fn add(&mut self, id: &Path) {
+        let mut parts = id
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
+        let name = match parts.pop() {
+            Some(name) => name,
+            None       => return,
+        };
+
+        let mut tree = &mut *self;
+        tree.count += 1;
+        for part in parts {
+            tree = { tree }.folders.entry(part).or_insert_with(EntryTree::new);
+            tree.count += 1;
+        }
+        tree.entries.insert(name);
+    }
+
+    /// The number of entries in this tree, including all sub-folders
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Render the tree, one folder or entry per line
+    ///
+    /// Folders deeper than `max_depth` are collapsed: they are shown with their count, but their
+    /// contents are not.
+    pub fn render(&self, max_depth: Option<usize>) -> String {
+        let mut out = format!(". ({})\n", self.count);
+        self.render_children("", 0, max_depth, &mut out);
+        out
+    }
+
+    fn render_children(&self, prefix: &str, depth: usize, max_depth: Option<usize>, out: &mut String) {
+        if max_depth.map(|max| depth >= max).unwrap_or(false) {
+            return;
+        }
+
+        let n = self.folders.len() + self.entries.len();
+        for (i, (name, folder)) in self.folders.iter().enumerate() {
+            let last = i + 1 == n;
+            out.push_str(&format!("{}{}{}/ ({})\n", prefix, connector(last), name, folder.count));
+
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            folder.render_children(&prefix, depth + 1, max_depth, out);
+        }
+
+        for (i, name) in self.entries.iter().enumerate() {
+            let last = self.folders.len() + i + 1 == n;
+            out.push_str(&format!("{}{}{}\n", prefix, connector(last), name));
+        }
+    }
+
+}
+
+fn connector(last: bool) -> &'static str {
+    if last { "└── " } else { "├── " }
+}
+
+/// Lists entries as a directory tree following their ids, with the number of entries per folder
+pub struct TreeLister {
+    max_depth: Option<usize>,
+}
+
+impl TreeLister {
+
+    pub fn new() -> TreeLister {
+        TreeLister {
+            max_depth: None,
+        }
+    }
+
+    /// Collapse all folders below this depth. With a depth of 1, only top-level folders are shown.
+    pub fn with_max_depth(mut self, depth: usize) -> TreeLister {
+        self.max_depth = Some(depth);
+        self
+    }
+
+}
+
+impl Lister for TreeLister {
+
+    fn list<'b, I: Iterator<Item = FileLockEntry<'b>>>(&self, entries: I) -> Result<()> {
+        let mut tree = EntryTree::new();
+        for entry in entries {
+            tree.add(entry.get_location().local());
+        }
+
+        write!(stdout(), "{}", tree.render(self.max_depth))
+            .map_err(|e| LEK::IOError.into_error_with_cause(Box::new(e)))
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::EntryTree;
+
+    fn tree() -> EntryTree {
+        let mut tree = EntryTree::new();
+        tree.add(Path::new("notes/work/meeting~0.1.0"));
+        tree.add(Path::new("notes/work/todo~0.1.0"));
+        tree.add(Path::new("notes/shopping~0.1.0"));
+        tree.add(Path::new("bookmark/rust~0.1.0"));
+        tree
+    }
+
+    #[test]
+    fn test_counts() {
+        let tree = tree();
+        assert_eq!(4, tree.count());
+        assert_eq!(Some(3), tree.folders.get("notes").map(EntryTree::count));
+        assert_eq!(Some(2), tree.folders.get("notes").and_then(|n| n.folders.get("work")).map(EntryTree::count));
+    }
+
+    #[test]
+    fn test_render() {
+        let expected = ". (4)\n\
+                        ├── bookmark/ (1)\n\
+                        │   └── rust~0.1.0\n\
+                        └── notes/ (3)\n    \
+                            ├── work/ (2)\n    \
+                            │   ├── meeting~0.1.0\n    \
+                            │   └── todo~0.1.0\n    \
+                            └── shopping~0.1.0\n";
+        assert_eq!(expected, tree().render(None));
+    }
+
+    #[test]
+    fn test_render_collapsed() {
+        let expected = ". (4)\n\
+                        ├── bookmark/ (1)\n\
+                        └── notes/ (3)\n";
+        assert_eq!(expected, tree().render(Some(1)));
+    }
+
+}
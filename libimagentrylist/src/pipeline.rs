@@ -0,0 +1,481 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! A listing pipeline which sorts, pages and groups entries before handing them to a `Lister`
+//!
+//! `Store::retrieve_for_module()` yields entries in glob order, which is rarely the order a user
+//! wants to see. The `Pipeline` collects the entries, orders them by a list of `SortKey`s, cuts
+//! out the requested page and optionally splits the result into groups, each of which is printed
+//! below a heading by the wrapped lister.
+
+use std::cmp::Ordering;
+use std::fs::metadata;
+use std::io::stdout;
+use std::io::Write;
+use std::time::UNIX_EPOCH;
+
+use toml::Value;
+
+use libimagstore::store::FileLockEntry;
+use libimagstore::toml_ext::TomlValueExt;
+use libimagerror::into::IntoError;
+
+use lister::Lister;
+use result::Result;
+use error::MapErrInto;
+use error::ListErrorKind as LEK;
+
+/// The heading used for entries which do not have the group-by field
+const NO_GROUP: &'static str = "(none)";
+
+/// Something entries can be ordered by
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    /// The id of the entry inside the store
+    StoreId,
+
+    /// The value of a header field, entries without the field are ordered last
+    Header(String),
+
+    /// The length of the content of the entry, in bytes
+    ContentLength,
+
+    /// The modification time of the file backing the entry
+    Mtime,
+}
+
+impl SortKey {
+
+    /// Parse a sort key as given on the commandline
+    ///
+    /// Accepted are `id`, `content-length`, `mtime` and `header.<path>`.
+    pub fn parse(s: &str) -> Option<SortKey> {
+        match s {
+            "id"             => Some(SortKey::StoreId),
+            "content-length" => Some(SortKey::ContentLength),
+            "mtime"          => Some(SortKey::Mtime),
+            _ => {
+                if s.starts_with("header.") && s.len() > "header.".len() {
+                    Some(SortKey::Header(String::from(&s["header.".len()..])))
+                } else {
+                    None
+                }
+            },
+        }
+    }
+
+    /// Get the value of this key for an entry
+    ///
+    /// Every key is mapped onto a toml value, so that all keys can be compared alike.
+    fn value_for(&self, entry: &FileLockEntry) -> Result<Option<Value>> {
+        match *self {
+            SortKey::StoreId => {
+                let id = format!("{}", entry.get_location().local().display());
+                Ok(Some(Value::String(id)))
+            },
+            SortKey::Header(ref path) => entry.get_header().read(path).map_err_into(LEK::EntryError),
+            SortKey::ContentLength => Ok(Some(Value::Integer(entry.get_content().len() as i64))),
+            SortKey::Mtime => {
+                let secs = entry
+                    .get_location()
+                    .clone()
+                    .into_pathbuf()
+                    .ok()
+                    .and_then(|path| metadata(path).ok())
+                    .and_then(|meta| meta.modified().ok())
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|dur| Value::Integer(dur.as_secs() as i64));
+                Ok(secs)
+            },
+        }
+    }
+
+}
+
+/// Sorts, pages and groups entries before listing them
+///
+/// With no options set, the pipeline passes the entries to the lister unchanged. Without sort keys
+/// and grouping, the entries are not collected, so listers can stream them.
+#[derive(Clone, Debug, Default)]
+pub struct Pipeline {
+    sort_keys: Vec<SortKey>,
+    reverse: bool,
+    group_by: Option<String>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl Pipeline {
+
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// Add a sort key. Later keys are used to break ties of earlier ones.
+    pub fn sort_by(mut self, key: SortKey) -> Pipeline {
+        self.sort_keys.push(key);
+        self
+    }
+
+    pub fn reverse(mut self, b: bool) -> Pipeline {
+        self.reverse = b;
+        self
+    }
+
+    /// Group the entries by the value of the header field at `path`
+    pub fn group_by(mut self, path: String) -> Pipeline {
+        self.group_by = Some(path);
+        self
+    }
+
+    /// Skip the first `n` entries, after sorting
+    pub fn offset(mut self, n: usize) -> Pipeline {
+        self.offset = n;
+        self
+    }
+
+    /// List at most `n` entries, after sorting
+    pub fn limit(mut self, n: usize) -> Pipeline {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Run the pipeline on `entries` and list the result with `lister`
+    ///
+    /// Paging is applied to the sorted entries before they are grouped, so `--limit 10` shows ten
+    /// entries in total, not ten per group. Groups appear in the order of their field value.
+    pub fn list<'a, L, I>(&self, lister: &L, entries: I) -> Result<()>
+        where L: Lister,
+              I: Iterator<Item = FileLockEntry<'a>>
+    {
+        let limit = self.limit.unwrap_or(usize::max_value());
+
+        // Without sorting and grouping, the entries are streamed to the lister
+        if self.sort_keys.is_empty() && self.group_by.is_none() {
+            return lister.list(entries.skip(self.offset).take(limit));
+        }
+
+        let mut keyed = Vec::new();
+        for entry in entries {
+            let group = match self.group_by {
+                Some(ref path) => {
+                    let value = try!(entry.get_header().read(path).map_err_into(LEK::EntryError));
+                    Some(value)
+                },
+                None => None,
+            };
+
+            let mut values = Vec::with_capacity(self.sort_keys.len());
+            for key in self.sort_keys.iter() {
+                values.push(try!(key.value_for(&entry)));
+            }
+
+            keyed.push((group, values, entry));
+        }
+
+        let reverse = self.reverse;
+        keyed.sort_by(|&(ref a_grp, ref a_vals, _), &(ref b_grp, ref b_vals, _)| {
+            let by_group = match (a_grp.as_ref(), b_grp.as_ref()) {
+                (Some(a), Some(b)) => compare_values(a.as_ref(), b.as_ref()),
+                _                  => Ordering::Equal,
+            };
+
+            let ord = a_vals
+                .iter()
+                .zip(b_vals.iter())
+                .map(|(a, b)| compare_values(a.as_ref(), b.as_ref()))
+                .fold(Ordering::Equal, |ord, next| if ord == Ordering::Equal { next } else { ord });
+
+            match by_group {
+                Ordering::Equal => if reverse { ord.reverse() } else { ord },
+                other           => other,
+            }
+        });
+
+        let page = keyed
+            .into_iter()
+            .skip(self.offset)
+            .take(limit);
+
+        if self.group_by.is_none() {
+            return lister.list(page.map(|(_, _, entry)| entry));
+        }
+
+        let mut groups : Vec<(String, Vec<FileLockEntry<'a>>)> = Vec::new();
+        for (group, _, entry) in page {
+            let heading = group_heading(group.and_then(|v| v));
+            let is_new_group = groups.last().map(|&(ref h, _)| *h != heading).unwrap_or(true);
+            if is_new_group {
+                groups.push((heading, vec![entry]));
+            } else if let Some(&mut (_, ref mut members)) = groups.last_mut() {
+                members.push(entry);
+            }
+        }
+
+        for (heading, members) in groups {
+            try!(write!(stdout(), "{}:\n", heading).map_err(|e| LEK::IOError.into_error_with_cause(Box::new(e))));
+            try!(lister.list(members.into_iter()));
+        }
+
+        Ok(())
+    }
+
+}
+
+/// The heading printed above a group of entries sharing a field value
+fn group_heading(value: Option<Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s,
+        Some(other)            => format!("{}", other),
+        None                   => String::from(NO_GROUP),
+    }
+}
+
+/// Order two optional toml values, missing values last
+///
+/// Integers and floats compare numerically with each other. Other values of differing types are
+/// ordered by their type (booleans, numbers, datetimes, strings, arrays, tables), so that the
+/// order stays transitive when a field has values of several types.
+pub fn compare_values(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    match (a, b) {
+        (Some(&Value::Integer(a)), Some(&Value::Integer(b)))   => a.cmp(&b),
+        (Some(&Value::Float(a)), Some(&Value::Float(b)))       => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Some(&Value::Integer(a)), Some(&Value::Float(b)))     => (a as f64).partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Some(&Value::Float(a)), Some(&Value::Integer(b)))     => a.partial_cmp(&(b as f64)).unwrap_or(Ordering::Equal),
+        (Some(&Value::String(ref a)), Some(&Value::String(ref b)))     |
+        (Some(&Value::Datetime(ref a)), Some(&Value::Datetime(ref b))) => a.cmp(b),
+        (Some(&Value::Boolean(a)), Some(&Value::Boolean(b)))   => a.cmp(&b),
+        (Some(a), Some(b)) => type_rank(a).cmp(&type_rank(b)),
+        (Some(_), None)    => Ordering::Less,
+        (None, Some(_))    => Ordering::Greater,
+        (None, None)       => Ordering::Equal,
+    }
+}
+
+/// The position of the type of a value when ordering values of differing types
+fn type_rank(v: &Value) -> u8 {
+    match *v {
+        Value::Boolean(_)                   => 0,
+        Value::Integer(_) | Value::Float(_) => 1,
+        Value::Datetime(_)                  => 2,
+        Value::String(_)                    => 3,
+        Value::Array(_)                     => 4,
+        Value::Table(_)                     => 5,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::cmp::Ordering;
+    use std::path::PathBuf;
+
+    use tempdir::TempDir;
+    use toml::Value;
+
+    use libimagstore::store::{FileLockEntry, Store};
+    use libimagstore::toml_ext::TomlValueExt;
+
+    use lister::Lister;
+    use result::Result;
+    use super::{Pipeline, SortKey, compare_values, group_heading};
+
+    /// Collects the ids of the entries of each call to `list()`
+    struct CollectingLister(RefCell<Vec<Vec<String>>>);
+
+    impl CollectingLister {
+
+        fn new() -> CollectingLister {
+            CollectingLister(RefCell::new(vec![]))
+        }
+
+        fn calls(self) -> Vec<Vec<String>> {
+            self.0.into_inner()
+        }
+
+    }
+
+    impl Lister for CollectingLister {
+
+        fn list<'a, I: Iterator<Item = FileLockEntry<'a>>>(&self, entries: I) -> Result<()> {
+            let ids = entries.map(|e| format!("{}", e.get_location().local().display())).collect();
+            self.0.borrow_mut().push(ids);
+            Ok(())
+        }
+
+    }
+
+    fn create(store: &Store, id: &str, fields: Vec<(&str, Value)>) {
+        let mut entry = store.create(PathBuf::from(id)).unwrap();
+        for (path, value) in fields {
+            assert!(entry.get_header_mut().set(path, value).is_ok());
+        }
+    }
+
+    fn get_store(dir: &TempDir) -> Store {
+        let store = Store::new(PathBuf::from(dir.path()), None).unwrap();
+        let group = |s: &str| Value::String(String::from(s));
+
+        create(&store, "list/a", vec![("note.prio", Value::Integer(3)), ("note.group", group("work"))]);
+        create(&store, "list/b", vec![("note.prio", Value::Integer(1)), ("note.group", group("home"))]);
+        create(&store, "list/c", vec![("note.prio", Value::Integer(2)), ("note.group", group("work"))]);
+        create(&store, "list/d", vec![("note.prio", Value::Float(1.5))]);
+        store
+    }
+
+    fn list(pipeline: Pipeline, store: &Store) -> Vec<Vec<String>> {
+        let lister  = CollectingLister::new();
+        let entries = ["list/c", "list/a", "list/d", "list/b"]
+            .iter()
+            .map(|id| store.get(PathBuf::from(id)).unwrap().unwrap());
+
+        assert!(pipeline.list(&lister, entries).is_ok());
+        lister.calls()
+    }
+
+    #[test]
+    fn test_parse_sort_key() {
+        assert_eq!(Some(SortKey::StoreId), SortKey::parse("id"));
+        assert_eq!(Some(SortKey::ContentLength), SortKey::parse("content-length"));
+        assert_eq!(Some(SortKey::Mtime), SortKey::parse("mtime"));
+        assert_eq!(Some(SortKey::Header(String::from("a.b"))), SortKey::parse("header.a.b"));
+        assert_eq!(None, SortKey::parse("header."));
+        assert_eq!(None, SortKey::parse("size"));
+    }
+
+    #[test]
+    fn test_compare_values() {
+        let one = Value::Integer(1);
+        let two = Value::Float(2.0);
+        let a   = Value::String(String::from("a"));
+
+        assert_eq!(Ordering::Less, compare_values(Some(&one), Some(&two)));
+        assert_eq!(Ordering::Greater, compare_values(Some(&two), Some(&one)));
+        assert_eq!(Ordering::Less, compare_values(Some(&a), None));
+        assert_eq!(Ordering::Greater, compare_values(None, Some(&a)));
+        assert_eq!(Ordering::Greater, compare_values(Some(&a), Some(&one)));
+    }
+
+    #[test]
+    fn test_compare_values_orders_by_type() {
+        let values = vec![
+            Value::String(String::from("a")),
+            Value::Integer(10),
+            Value::Boolean(true),
+            Value::Float(2.5),
+            Value::Datetime(String::from("2016-10-12T10:00:00Z")),
+        ];
+
+        for a in values.iter() {
+            for b in values.iter() {
+                for c in values.iter() {
+                    let ab = compare_values(Some(a), Some(b));
+                    let bc = compare_values(Some(b), Some(c));
+                    if ab == bc {
+                        assert_eq!(ab, compare_values(Some(a), Some(c)));
+                    }
+                }
+            }
+        }
+
+        assert_eq!(Ordering::Less, compare_values(Some(&values[2]), Some(&values[1])));
+        assert_eq!(Ordering::Less, compare_values(Some(&values[3]), Some(&values[4])));
+        assert_eq!(Ordering::Less, compare_values(Some(&values[4]), Some(&values[0])));
+    }
+
+    #[test]
+    fn test_list_unchanged() {
+        let dir   = TempDir::new("pipeline").unwrap();
+        let store = get_store(&dir);
+
+        assert_eq!(list(Pipeline::new(), &store), vec![vec!["list/c", "list/a", "list/d", "list/b"]]);
+    }
+
+    #[test]
+    fn test_list_sorted() {
+        let dir   = TempDir::new("pipeline").unwrap();
+        let store = get_store(&dir);
+
+        let pipeline = Pipeline::new().sort_by(SortKey::Header(String::from("note.prio")));
+        assert_eq!(list(pipeline, &store), vec![vec!["list/b", "list/d", "list/c", "list/a"]]);
+
+        let pipeline = Pipeline::new().sort_by(SortKey::StoreId);
+        assert_eq!(list(pipeline, &store), vec![vec!["list/a", "list/b", "list/c", "list/d"]]);
+    }
+
+    #[test]
+    fn test_list_unsorted_page() {
+        let dir   = TempDir::new("pipeline").unwrap();
+        let store = get_store(&dir);
+
+        let pipeline = Pipeline::new().offset(1).limit(2);
+        assert_eq!(list(pipeline, &store), vec![vec!["list/a", "list/d"]]);
+    }
+
+    #[test]
+    fn test_list_offset_and_limit() {
+        let dir   = TempDir::new("pipeline").unwrap();
+        let store = get_store(&dir);
+
+        let pipeline = Pipeline::new()
+            .sort_by(SortKey::Header(String::from("note.prio")))
+            .reverse(true)
+            .offset(1)
+            .limit(2);
+        assert_eq!(list(pipeline, &store), vec![vec!["list/c", "list/d"]]);
+
+        let pipeline = Pipeline::new().sort_by(SortKey::StoreId).offset(10);
+        assert_eq!(list(pipeline, &store), vec![Vec::<String>::new()]);
+    }
+
+    #[test]
+    fn test_list_grouped() {
+        let dir   = TempDir::new("pipeline").unwrap();
+        let store = get_store(&dir);
+
+        let pipeline = Pipeline::new()
+            .group_by(String::from("note.group"))
+            .sort_by(SortKey::StoreId);
+        assert_eq!(list(pipeline, &store),
+                   vec![vec!["list/b"], vec!["list/a", "list/c"], vec!["list/d"]]);
+    }
+
+    #[test]
+    fn test_list_grouped_by_values_of_several_types() {
+        let dir   = TempDir::new("pipeline").unwrap();
+        let store = Store::new(PathBuf::from(dir.path()), None).unwrap();
+
+        create(&store, "list/a", vec![("note.group", Value::String(String::from("x")))]);
+        create(&store, "list/b", vec![("note.group", Value::Integer(2))]);
+        create(&store, "list/c", vec![("note.group", Value::Boolean(true))]);
+        create(&store, "list/d", vec![]);
+
+        let pipeline = Pipeline::new().group_by(String::from("note.group"));
+        assert_eq!(list(pipeline, &store),
+                   vec![vec!["list/c"], vec!["list/b"], vec!["list/a"], vec!["list/d"]]);
+    }
+
+    #[test]
+    fn test_group_heading() {
+        assert_eq!("work", group_heading(Some(Value::String(String::from("work")))));
+        assert_eq!("3", group_heading(Some(Value::Integer(3))));
+        assert_eq!("(none)", group_heading(None));
+    }
+
+}
@@ -0,0 +1,114 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+
+#![deny(
+    dead_code,
+    non_camel_case_types,
+    non_snake_case,
+    path_statements,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_allocation,
+    unused_import_braces,
+    unused_imports,
+    unused_must_use,
+    unused_mut,
+    unused_qualifications,
+    while_true,
+)]
+
+//! The tags and links of entries for the `TemplateLister` of libimagentrylist
+//!
+//! Binaries which list entries with a template pass `&TagsAndLinks` as `TemplateData`, so the
+//! `{tags}`, `{links}` and `{external-links}` placeholders are filled.
+
+extern crate libimagstore;
+extern crate libimagentrylist;
+extern crate libimagentrytag;
+extern crate libimagentrylink;
+
+#[cfg(test)] extern crate tempdir;
+
+use libimagstore::store::Entry;
+use libimagentrylist::listers::template::TemplateData;
+use libimagentrytag::tagable::Tagable;
+use libimagentrylink::internal::InternalLinker;
+use libimagentrylink::external::is_external_link_storeid;
+
+/// `TemplateData` which reads tags with libimagentrytag and links with libimagentrylink
+pub struct TagsAndLinks;
+
+impl TemplateData for TagsAndLinks {
+
+    fn tags(&self, entry: &Entry) -> Option<Vec<String>> {
+        entry.get_tags().ok()
+    }
+
+    fn internal_link_count(&self, entry: &Entry) -> Option<usize> {
+        entry
+            .get_internal_links()
+            .ok()
+            .map(|links| links.filter(|l| !is_external_link_storeid(l)).count())
+    }
+
+    fn external_link_count(&self, entry: &Entry) -> Option<usize> {
+        entry
+            .get_internal_links()
+            .ok()
+            .map(|links| links.filter(|l| is_external_link_storeid(l)).count())
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use tempdir::TempDir;
+
+    use libimagstore::store::Store;
+    use libimagentrylist::listers::template::{Template, TemplateData, NoTemplateData};
+    use libimagentrytag::tagable::Tagable;
+    use libimagentrylink::internal::InternalLinker;
+
+    use super::TagsAndLinks;
+
+    #[test]
+    fn test_tags_and_links() {
+        let dir   = TempDir::new("listdata").unwrap();
+        let store = Store::new(PathBuf::from(dir.path()), None).unwrap();
+
+        let mut a = store.create(PathBuf::from("test/a")).unwrap();
+        let mut b = store.create(PathBuf::from("test/b")).unwrap();
+        assert!(a.add_tag(String::from("work")).is_ok());
+        assert!(a.add_tag(String::from("home")).is_ok());
+        assert!(a.add_internal_link(&mut b).is_ok());
+
+        assert_eq!(TagsAndLinks.tags(&a), Some(vec![String::from("work"), String::from("home")]));
+        assert_eq!(TagsAndLinks.internal_link_count(&a), Some(1));
+        assert_eq!(TagsAndLinks.external_link_count(&a), Some(0));
+        assert_eq!(TagsAndLinks.tags(&b), Some(vec![]));
+
+        let template = Template::parse("{tags}|{links}|{external-links}").unwrap();
+        assert_eq!(template.render_entry(&a, &TagsAndLinks), "work, home|1|0");
+        assert_eq!(template.render_entry(&a, &NoTemplateData), "||");
+    }
+
+}
@@ -31,6 +31,7 @@
     while_true,
 )]
 
+extern crate ansi_term;
 extern crate crossbeam;
 extern crate hoedown;
 extern crate url;
@@ -41,4 +42,5 @@ pub mod error;
 pub mod html;
 pub mod link;
 pub mod result;
+pub mod terminal;
 
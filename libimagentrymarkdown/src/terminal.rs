@@ -0,0 +1,516 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+
+//! Render markdown for the terminal
+//!
+//! Headings, emphasis, inline code, lists, code blocks and quotes are styled with terminal escape
+//! codes. Links are printed as their text with a footnote number, the targets are listed below the
+//! content.
+//!
+//! The hoedown callbacks do not know how much room their content gets on the screen, as a block
+//! is rendered before the block it is nested in. They write lines of a prefix (list markers, quote
+//! bars, indentation), a marker and the text instead, nested blocks add to the prefix. A final pass
+//! wraps the text of each line after its prefix.
+
+use std::io::Write;
+use std::iter::repeat;
+
+use ansi_term::{Colour, Style};
+use hoedown::{Buffer, Markdown, FENCED_CODE, NO_INTRA_EMPHASIS};
+use hoedown::renderer::Render;
+use hoedown::renderer::list::{List, ORDERED};
+
+use result::Result;
+use error::MarkdownErrorKind;
+use libimagerror::into::IntoError;
+
+/// Separates the prefix of a line from text which may be wrapped
+const WRAP: char = '\u{1f}';
+
+/// Separates the prefix of a line from text which is printed as it is, like code
+const KEEP: char = '\u{1e}';
+
+/// Ends the prefix of a horizontal rule, which fills the rest of the line
+const RULE: char = '\u{1d}';
+
+/// Stands for the number of an item of an ordered list, until the list is rendered
+const NUMBER: char = '\u{1c}';
+
+const RESET: &'static str = "\x1b[0m";
+
+/// Render `buffer` for a terminal of `width` columns
+///
+/// Without `colored`, no escape codes are written, only the layout is done.
+pub fn to_terminal(buffer: &str, width: usize, colored: bool) -> Result<String> {
+    let md           = Markdown::new(buffer).extensions(FENCED_CODE | NO_INTRA_EMPHASIS);
+    let mut renderer = TerminalRenderer::new(colored);
+    let rendered     = renderer.render(&md);
+    let rendered     = try!(rendered
+        .to_str()
+        .map_err(Box::new)
+        .map_err(|e| MarkdownErrorKind::MarkdownRenderError.into_error_with_cause(e)));
+
+    let mut lines = wrap(rendered, width);
+    if !renderer.footnotes.is_empty() {
+        lines.push(String::new());
+        for (i, target) in renderer.footnotes.iter().enumerate() {
+            let marker = renderer.paint(&format!("[{}]", i + 1), footnote_style());
+            lines.push(format!("{} {}", marker, target));
+        }
+    }
+
+    let mut s = lines.join("\n");
+    s.push('\n');
+    Ok(s)
+}
+
+fn footnote_style() -> Style {
+    Style::new().dimmed()
+}
+
+struct TerminalRenderer {
+    colored: bool,
+    footnotes: Vec<String>,
+}
+
+impl TerminalRenderer {
+
+    fn new(colored: bool) -> TerminalRenderer {
+        TerminalRenderer {
+            colored: colored,
+            footnotes: vec![],
+        }
+    }
+
+    /// Paint `s` with `style`. Styles painted inside of `s` reset all styles at their end, so the
+    /// style is set again after each reset.
+    fn paint(&self, s: &str, style: Style) -> String {
+        if self.colored {
+            let restyled = s.replace(RESET, &format!("{}{}", RESET, style.prefix()));
+            style.paint(restyled).to_string()
+        } else {
+            String::from(s)
+        }
+    }
+
+}
+
+impl Render for TerminalRenderer {
+
+    fn header(&mut self, output: &mut Buffer, content: Option<&Buffer>, level: i32) {
+        let style = Colour::Blue.bold();
+        let style = if level == 1 { style.underline() } else { style };
+        let text  = self.paint(&inline_text(content), style);
+        put(output, &format!("{}{}\n{}\n", WRAP, text, WRAP));
+    }
+
+    fn paragraph(&mut self, output: &mut Buffer, content: Option<&Buffer>) {
+        put(output, &format!("{}{}\n{}\n", WRAP, inline_text(content), WRAP));
+    }
+
+    fn horizontal_rule(&mut self, output: &mut Buffer) {
+        put(output, &format!("{}\n{}\n", RULE, WRAP));
+    }
+
+    /// Code is indented and never wrapped, so it stays copyable
+    fn code_block(&mut self, output: &mut Buffer, code: Option<&Buffer>, _: Option<&Buffer>) {
+        let style = Colour::Yellow.normal();
+        for line in text(code).lines() {
+            put(output, &format!("    {}{}\n", KEEP, self.paint(line, style)));
+        }
+        put(output, &format!("{}\n", WRAP));
+    }
+
+    fn quote_block(&mut self, output: &mut Buffer, content: Option<&Buffer>) {
+        let bar = self.paint("│", footnote_style());
+        for line in block_lines(content) {
+            put(output, &format!("{} {}\n", bar, line));
+        }
+        put(output, &format!("{}\n", WRAP));
+    }
+
+    /// Number the items of an ordered list, the items of nested lists are numbered already
+    fn list(&mut self, output: &mut Buffer, content: Option<&Buffer>, _: List) {
+        let mut n = 0;
+        for line in text(content).lines() {
+            if line.starts_with(NUMBER) {
+                n += 1;
+                put(output, &format!("{}.{}\n", n, &line[NUMBER.len_utf8()..]));
+            } else {
+                put(output, &format!("{}\n", line));
+            }
+        }
+        put(output, &format!("{}\n", WRAP));
+    }
+
+    fn list_item(&mut self, output: &mut Buffer, content: Option<&Buffer>, flags: List) {
+        let (first, rest) = if flags.contains(ORDERED) {
+            (format!("{} ", NUMBER), "   ")
+        } else {
+            (String::from("• "), "  ")
+        };
+
+        for (i, line) in block_lines(content).into_iter().enumerate() {
+            let prefix = if i == 0 { &first[..] } else { rest };
+            put(output, &format!("{}{}\n", prefix, line));
+        }
+    }
+
+    fn emphasis(&mut self, output: &mut Buffer, content: Option<&Buffer>) -> bool {
+        let s = self.paint(&text(content), Style::new().italic());
+        put(output, &s);
+        true
+    }
+
+    fn double_emphasis(&mut self, output: &mut Buffer, content: Option<&Buffer>) -> bool {
+        let s = self.paint(&text(content), Style::new().bold());
+        put(output, &s);
+        true
+    }
+
+    fn code_span(&mut self, output: &mut Buffer, code: Option<&Buffer>) -> bool {
+        let s = self.paint(&text(code), Colour::Yellow.normal());
+        put(output, &s);
+        true
+    }
+
+    fn link(&mut self,
+            output: &mut Buffer,
+            content: Option<&Buffer>,
+            link: Option<&Buffer>,
+            _: Option<&Buffer>)
+        -> bool
+    {
+        let label = self.paint(&text(content), Colour::Cyan.underline());
+        self.footnotes.push(text(link));
+        let marker = self.paint(&format!("[{}]", self.footnotes.len()), footnote_style());
+        put(output, &format!("{}{}", label, marker));
+        true
+    }
+
+    /// HTML is printed as it is
+    fn html_block(&mut self, output: &mut Buffer, content: Option<&Buffer>) {
+        for line in text(content).lines() {
+            put(output, &format!("{}{}\n", KEEP, line));
+        }
+        put(output, &format!("{}\n", WRAP));
+    }
+
+    fn normal_text(&mut self, output: &mut Buffer, content: Option<&Buffer>) {
+        put(output, &text(content));
+    }
+
+    fn entity(&mut self, output: &mut Buffer, content: Option<&Buffer>) {
+        put(output, &text(content));
+    }
+
+}
+
+/// Writing to a `Buffer` is writing to memory, which does not fail
+fn put(output: &mut Buffer, s: &str) {
+    let _ = output.write_all(s.as_bytes());
+}
+
+fn text(buffer: Option<&Buffer>) -> String {
+    buffer.and_then(|b| b.to_str().ok()).map(String::from).unwrap_or_else(String::new)
+}
+
+/// Inline content on one line, hoedown passes the line breaks of the source along
+fn inline_text(buffer: Option<&Buffer>) -> String {
+    text(buffer)
+        .split('\n')
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The lines of the content of a list item or quote, without the empty lines at the end
+///
+/// The text of tight list items is not in a paragraph, so it has no marker yet. It is put on one
+/// line, the lines of the blocks nested in the item are kept as they are.
+fn block_lines(buffer: Option<&Buffer>) -> Vec<String> {
+    let content   = text(buffer);
+    let mut lines = vec![];
+    let mut loose = vec![];
+
+    for line in content.lines() {
+        if has_marker(line) {
+            if !loose.is_empty() {
+                lines.push(format!("{}{}", WRAP, loose.join(" ")));
+                loose.clear();
+            }
+            lines.push(String::from(line));
+        } else if !line.trim().is_empty() {
+            loose.push(line.trim());
+        }
+    }
+    if !loose.is_empty() {
+        lines.push(format!("{}{}", WRAP, loose.join(" ")));
+    }
+
+    while lines.last().map(|l| is_blank(l)).unwrap_or(false) {
+        lines.pop();
+    }
+    lines
+}
+
+fn is_marker(c: char) -> bool {
+    c == WRAP || c == KEEP || c == RULE
+}
+
+fn has_marker(line: &str) -> bool {
+    line.contains(is_marker)
+}
+
+fn is_blank(line: &str) -> bool {
+    line.find(is_marker).map(|i| line[i + 1..].trim().is_empty()).unwrap_or(false) &&
+        !line.contains(RULE)
+}
+
+/// Wrap the rendered lines to `width` columns
+///
+/// Empty lines are collapsed, so there is exactly one between two blocks.
+fn wrap(rendered: &str, width: usize) -> Vec<String> {
+    let mut out = vec![];
+
+    for line in rendered.lines() {
+        let (prefix, marker, text) = match line.find(is_marker) {
+            Some(i) => (&line[..i], line[i..].chars().next().unwrap(), &line[i + 1..]),
+            None    => ("", WRAP, line),
+        };
+
+        if marker == WRAP && text.trim().is_empty() {
+            let blank     = String::from(prefix.trim_right());
+            let collapsed = out.last().map(|l: &String| l.trim().is_empty() || *l == blank);
+            if !collapsed.unwrap_or(true) {
+                out.push(blank);
+            }
+            continue;
+        }
+
+        let width_left = width.saturating_sub(visible_width(prefix));
+        match marker {
+            KEEP => out.push(format!("{}{}", prefix, text)),
+            RULE => {
+                let rule = repeat('─').take(width_left).collect::<String>();
+                out.push(format!("{}{}", prefix, rule))
+            },
+            _    => wrap_text(prefix, text, width_left, &mut out),
+        }
+    }
+
+    while out.last().map(|l| l.trim().is_empty()).unwrap_or(false) {
+        out.pop();
+    }
+    out
+}
+
+/// Wrap `text` to lines of at most `width` columns after the prefix. The first line starts with
+/// `prefix`, the following lines with as many spaces, except for quote bars. Words longer than a
+/// line get a line of their own.
+///
+/// Styles which are active at the end of a line are reset there and set again after the prefix
+/// of the next line, so they do not leak into the prefix.
+fn wrap_text(prefix: &str, text: &str, width: usize, out: &mut Vec<String>) {
+    let continued  = continuation(prefix);
+    let mut line   = String::from(prefix);
+    let mut len    = 0;
+    let mut active : Vec<String> = vec![];
+
+    for word in text.split(' ').filter(|w| !w.is_empty()) {
+        let word_len = visible_width(word);
+        if len > 0 && len + 1 + word_len > width {
+            if !active.is_empty() {
+                line.push_str(RESET);
+            }
+            out.push(line);
+            line = format!("{}{}", continued, active.concat());
+            len  = 0;
+        }
+
+        if len > 0 {
+            line.push(' ');
+            len += 1;
+        }
+        line.push_str(word);
+        len += word_len;
+
+        for sequence in escape_sequences(word) {
+            if sequence == RESET {
+                active.clear();
+            } else {
+                active.push(String::from(sequence));
+            }
+        }
+    }
+
+    out.push(line);
+}
+
+/// The prefix of continuation lines: quote bars are kept, list markers become spaces
+fn continuation(prefix: &str) -> String {
+    let mut out      = String::new();
+    let mut sequence = false;
+
+    for c in prefix.chars() {
+        if c == '\x1b' {
+            sequence = true;
+        }
+        if sequence || c == '│' {
+            out.push(c);
+        } else {
+            out.push(' ');
+        }
+        if sequence && c == 'm' {
+            sequence = false;
+        }
+    }
+    out
+}
+
+/// The escape sequences in `s`, in order
+fn escape_sequences(s: &str) -> Vec<&str> {
+    let mut sequences = vec![];
+    let mut rest      = s;
+
+    while let Some(start) = rest.find('\x1b') {
+        match rest[start..].find('m') {
+            Some(end) => {
+                sequences.push(&rest[start..start + end + 1]);
+                rest = &rest[start + end + 1..];
+            },
+            None => break,
+        }
+    }
+    sequences
+}
+
+/// The number of columns `s` takes on the screen, without its escape sequences
+fn visible_width(s: &str) -> usize {
+    let mut width    = 0;
+    let mut sequence = false;
+
+    for c in s.chars() {
+        if c == '\x1b' {
+            sequence = true;
+        } else if sequence {
+            sequence = c != 'm';
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+#[cfg(test)]
+mod test {
+    use super::{to_terminal, wrap, visible_width, continuation, WRAP, KEEP, RULE};
+
+    fn render(md: &str, width: usize) -> String {
+        to_terminal(md, width, false).unwrap()
+    }
+
+    #[test]
+    fn test_visible_width() {
+        assert_eq!(4, visible_width("text"));
+        assert_eq!(4, visible_width("\x1b[1mtext\x1b[0m"));
+        assert_eq!(2, visible_width("\x1b[2m│\x1b[0m "));
+    }
+
+    #[test]
+    fn test_continuation() {
+        assert_eq!("  ", continuation("• "));
+        assert_eq!("│     ", continuation("│ 10. "));
+        assert_eq!("\x1b[2m│\x1b[0m   ", continuation("\x1b[2m│\x1b[0m • "));
+    }
+
+    #[test]
+    fn test_wrap_with_prefix() {
+        let rendered = format!("• {}first item which wraps\n│ {}quoted text here\n", WRAP, WRAP);
+        let expected = vec!["• first item", "  which wraps", "│ quoted text", "│ here"];
+        assert_eq!(expected, wrap(&rendered, 13));
+    }
+
+    #[test]
+    fn test_wrap_keeps_code_and_fills_rules() {
+        let rendered = format!("    {}fn main() {{   }}\n{}\n│ {}\n", KEEP, WRAP, RULE);
+        let expected = vec!["    fn main() {   }", "", "│ ───"];
+        assert_eq!(expected, wrap(&rendered, 5));
+    }
+
+    #[test]
+    fn test_wrap_collapses_empty_lines() {
+        let rendered = format!("{}a\n{}\n{}\n│ {}\n{}b\n{}\n", WRAP, WRAP, WRAP, WRAP, WRAP, WRAP);
+        assert_eq!(vec!["a", "", "b"], wrap(&rendered, 80));
+    }
+
+    #[test]
+    fn test_wrap_restores_styles() {
+        let rendered = format!("│ {}\x1b[1mbold words\x1b[0m end\n", WRAP);
+        let expected = vec!["│ \x1b[1mbold\x1b[0m", "│ \x1b[1mwords\x1b[0m", "│ end"];
+        assert_eq!(expected, wrap(&rendered, 7));
+    }
+
+    #[test]
+    fn test_heading_and_paragraph() {
+        let md = "# Title\n\nSome text which is long enough\nto be wrapped.\n";
+        assert_eq!("Title\n\nSome text which is\nlong enough to be\nwrapped.\n", render(md, 20));
+    }
+
+    #[test]
+    fn test_emphasis_and_code() {
+        let md = "Some **bold**, *italic* and `in line` code with snake_case_names.\n";
+        assert_eq!("Some bold, italic and in line code with snake_case_names.\n", render(md, 80));
+    }
+
+    #[test]
+    fn test_lists() {
+        let md = "* first item which wraps\n* second\n\n1. one\n2. two\n";
+        let expected = "• first item which\n  wraps\n• second\n\n1. one\n2. two\n";
+        assert_eq!(expected, render(md, 20));
+    }
+
+    #[test]
+    fn test_code_block() {
+        let md = "Text\n\n```\nfn main() {   }\n```\n\nMore\n";
+        assert_eq!("Text\n\n    fn main() {   }\n\nMore\n", render(md, 10));
+    }
+
+    #[test]
+    fn test_quote() {
+        let md = "> quoted text\n> here\n";
+        assert_eq!("│ quoted text here\n", render(md, 80));
+    }
+
+    #[test]
+    fn test_links_as_footnotes() {
+        let md = "See [the docs](http://example.com/docs) and [here](http://example.com).\n";
+        let expected = "See the docs[1] and here[2].\n\n[1] http://example.com/docs\n[2] http://example.com\n";
+        assert_eq!(expected, render(md, 80));
+    }
+
+    #[test]
+    fn test_colored() {
+        let rendered = to_terminal("Some **bold** text\n", 80, true).unwrap();
+        assert!(rendered.contains("\x1b[1mbold\x1b[0m"));
+    }
+
+}
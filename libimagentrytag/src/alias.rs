@@ -0,0 +1,184 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Tag aliases
+//!
+//! Different spellings of a tag, like `TODO` and `to-do`, can be configured as aliases of a
+//! canonical tag, like `todo`:
+//!
+//! ```toml
+//! [tag.aliases]
+//! todo = [ "TODO", "to-do" ]
+//! ```
+//!
+//! The aliases are process-wide. The binaries which work with tags set them from the configuration
+//! with `load_aliases()` after the runtime is set up. From then on, the `Tagable` functions
+//! resolve all tags to their canonical form, so `add_tag("TODO")` adds `todo`, and
+//! `has_tag("to-do")` is true for an entry tagged `TODO` in its header.
+
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use toml::Value;
+
+use libimagerror::into::IntoError;
+use libimagerror::trace::trace_error;
+
+use error::TagErrorKind as TEK;
+use hierarchy::is_self_or_descendant;
+use result::Result;
+use tag::{Tag, TagSlice};
+use util::is_tag;
+
+lazy_static! {
+    static ref ALIASES: RwLock<TagAliases> = RwLock::new(TagAliases::new());
+}
+
+/// A table of tag aliases, mapping each alias to its canonical tag
+#[derive(Debug, Clone, Default)]
+pub struct TagAliases {
+    aliases: BTreeMap<Tag, Tag>,
+}
+
+impl TagAliases {
+
+    pub fn new() -> TagAliases {
+        TagAliases::default()
+    }
+
+    /// Read the aliases from the `tag.aliases` table of the configuration
+    ///
+    /// A missing table results in an empty alias table.
+    pub fn from_config(config: &Value) -> Result<TagAliases> {
+        let mut aliases = TagAliases::new();
+
+        let table = match config.lookup("tag.aliases") {
+            Some(&Value::Table(ref t)) => t,
+            Some(_) => return Err(TEK::AliasConfigError.into_error()),
+            None    => return Ok(aliases),
+        };
+
+        for (canonical, values) in table.iter() {
+            let values = match *values {
+                Value::Array(ref a) => a,
+                _ => return Err(TEK::AliasConfigError.into_error()),
+            };
+
+            for value in values {
+                match *value {
+                    Value::String(ref alias) => try!(aliases.add_alias(alias.clone(), canonical.clone())),
+                    _ => return Err(TEK::AliasConfigError.into_error()),
+                }
+            }
+        }
+
+        Ok(aliases)
+    }
+
+    /// Make `alias` an alias of `canonical`
+    pub fn add_alias(&mut self, alias: Tag, canonical: Tag) -> Result<()> {
+        if !is_tag(&alias) || !is_tag(&canonical) {
+            debug!("Not a tag: '{}' or '{}'", alias, canonical);
+            return Err(TEK::NotATag.into_error());
+        }
+
+        self.aliases.insert(alias, canonical);
+        Ok(())
+    }
+
+    /// Get the canonical form of a tag
+    ///
+    /// An alias also applies to the descendants of a tag: if `TODO` is an alias of `todo`,
+    /// `TODO/urgent` resolves to `todo/urgent`.
+    pub fn canonical(&self, tag: TagSlice) -> Tag {
+        // The longest matching alias wins, so a nested alias overrides an alias of its parent
+        let alias = self.aliases
+            .iter()
+            .filter(|&(alias, _)| is_self_or_descendant(tag, alias))
+            .max_by_key(|&(alias, _)| alias.len());
+
+        match alias {
+            Some((alias, canonical)) => format!("{}{}", canonical, &tag[alias.len()..]),
+            None                     => String::from(tag),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+}
+
+/// Set the process-wide aliases
+pub fn set_aliases(aliases: TagAliases) {
+    match ALIASES.write() {
+        Ok(mut a) => *a = aliases,
+        Err(_)    => warn!("Could not set tag aliases"),
+    }
+}
+
+/// Set the process-wide aliases from the `tag.aliases` table of the configuration, if any
+///
+/// An invalid table is reported, the aliases are not set then.
+pub fn load_aliases(config: Option<&Value>) {
+    if let Some(config) = config {
+        match TagAliases::from_config(config) {
+            Ok(aliases) => set_aliases(aliases),
+            Err(e)      => trace_error(&e),
+        }
+    }
+}
+
+/// Get the canonical form of a tag, using the process-wide aliases
+pub fn canonical(tag: TagSlice) -> Tag {
+    match ALIASES.read() {
+        Ok(aliases) => aliases.canonical(tag),
+        Err(_)      => String::from(tag),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_canonical() {
+        let mut aliases = TagAliases::new();
+        aliases.add_alias(String::from("TODO"), String::from("todo")).unwrap();
+        aliases.add_alias(String::from("to-do"), String::from("todo")).unwrap();
+
+        assert_eq!(aliases.canonical("TODO"), "todo");
+        assert_eq!(aliases.canonical("to-do/urgent"), "todo/urgent");
+        assert_eq!(aliases.canonical("todos"), "todos");
+        assert_eq!(aliases.canonical("work"), "work");
+    }
+
+    #[test]
+    fn test_from_config() {
+        use toml::Parser;
+
+        let config = Parser::new("[tag.aliases]\ntodo = [ \"TODO\", \"to-do\" ]\n").parse().unwrap();
+        let aliases = TagAliases::from_config(&Value::Table(config)).unwrap();
+        assert_eq!(aliases.canonical("to-do"), "todo");
+
+        let config = Parser::new("[tag]\naliases = 1\n").parse().unwrap();
+        assert!(TagAliases::from_config(&Value::Table(config)).is_err());
+    }
+
+}
@@ -22,7 +22,10 @@ generate_error_module!(
         TagTypeError     => "Entry Header Tag Type wrong",
         HeaderReadError  => "Error while reading entry header",
         HeaderWriteError => "Error while writing entry header",
-        NotATag          => "String is not a tag"
+        NotATag          => "String is not a tag",
+        StoreReadError   => "Error while reading from the store",
+        StoreWriteError  => "Error while writing to the store",
+        AliasConfigError => "Tag alias configuration is invalid"
     );
 );
 
@@ -0,0 +1,167 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Hierarchical tags
+//!
+//! A tag may consist of several parts, separated by a slash, like `work/clientA/billing`. The tag
+//! `work/clientA` is the parent of this tag, and `work` is an ancestor of both. When querying, a
+//! tag matches itself and all of its descendants.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::iter::repeat;
+
+use tag::{Tag, TagSlice};
+
+/// The separator between the parts of a hierarchical tag
+pub const SEPARATOR: char = '/';
+
+/// Check whether `tag` is `ancestor` or one of its descendants
+///
+/// `work/clientA` is a descendant of `work`, but `workshop` is not.
+pub fn is_self_or_descendant(tag: TagSlice, ancestor: TagSlice) -> bool {
+    tag.starts_with(ancestor) &&
+        (tag.len() == ancestor.len() || tag[ancestor.len()..].starts_with(SEPARATOR))
+}
+
+/// Get the parent of a tag, or `None` if the tag is a top-level tag
+pub fn parent(tag: TagSlice) -> Option<TagSlice> {
+    tag.rfind(SEPARATOR).map(|i| &tag[..i])
+}
+
+/// Get the tag itself and all its ancestors, the top-level tag first
+///
+/// For `a/b/c`, this is `a`, `a/b` and `a/b/c`.
+pub fn self_and_ancestors(tag: TagSlice) -> Vec<TagSlice> {
+    let mut tags = tag
+        .match_indices(SEPARATOR)
+        .map(|(i, _)| &tag[..i])
+        .collect::<Vec<_>>();
+    tags.push(tag);
+    tags
+}
+
+/// A tree of tags, counting the entries in each subtree
+#[derive(Debug, Clone, Default)]
+pub struct TagTree {
+    count: usize,
+    children: BTreeMap<String, TagTree>,
+}
+
+impl TagTree {
+
+    pub fn new() -> TagTree {
+        TagTree::default()
+    }
+
+    /// Add the tags of one entry to the tree
+    ///
+    /// The entry is counted once for each tag in the tree it has itself or has a descendant of.
+    pub fn add_entry(&mut self, tags: &[Tag]) {
+        let mut nodes = tags
+            .iter()
+            .flat_map(|t| self_and_ancestors(t))
+            .collect::<Vec<_>>();
+        nodes.sort();
+        nodes.dedup();
+
+        self.count += 1;
+        for node in nodes {
+            let mut tree = &mut *self;
+            for part in node.split(SEPARATOR) {
+                tree = { tree }.children.entry(String::from(part)).or_insert_with(TagTree::new);
+            }
+            tree.count += 1;
+        }
+    }
+
+    /// The number of entries in this subtree
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The children of this node, by the last part of their name
+    pub fn children(&self) -> &BTreeMap<String, TagTree> {
+        &self.children
+    }
+
+    /// Get the subtree for a tag
+    pub fn get(&self, tag: TagSlice) -> Option<&TagTree> {
+        tag.split(SEPARATOR).fold(Some(self), |tree, part| tree.and_then(|t| t.children.get(part)))
+    }
+
+    fn fmt_children(&self, fmt: &mut Formatter, depth: usize) -> FmtResult {
+        for (name, child) in self.children.iter() {
+            let indent = repeat("  ").take(depth).collect::<String>();
+            try!(writeln!(fmt, "{}{} ({})", indent, name, child.count));
+            try!(child.fmt_children(fmt, depth + 1));
+        }
+        Ok(())
+    }
+
+}
+
+/// Prints one tag per line with the number of entries, children indented below their parent
+impl Display for TagTree {
+
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        self.fmt_children(fmt, 0)
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_self_or_descendant() {
+        assert!(is_self_or_descendant("work", "work"));
+        assert!(is_self_or_descendant("work/clientA", "work"));
+        assert!(is_self_or_descendant("work/clientA/billing", "work/clientA"));
+        assert!(!is_self_or_descendant("workshop", "work"));
+        assert!(!is_self_or_descendant("work", "work/clientA"));
+    }
+
+    #[test]
+    fn test_parent_and_ancestors() {
+        assert_eq!(parent("a/b/c"), Some("a/b"));
+        assert_eq!(parent("a"), None);
+        assert_eq!(self_and_ancestors("a/b/c"), vec!["a", "a/b", "a/b/c"]);
+        assert_eq!(self_and_ancestors("a"), vec!["a"]);
+    }
+
+    #[test]
+    fn test_tree_counts() {
+        let mut tree = TagTree::new();
+        tree.add_entry(&[String::from("work/clientA/billing"), String::from("work/clientB")]);
+        tree.add_entry(&[String::from("work/clientA")]);
+        tree.add_entry(&[String::from("private")]);
+
+        assert_eq!(tree.count(), 3);
+        assert_eq!(tree.get("work").map(TagTree::count), Some(2));
+        assert_eq!(tree.get("work/clientA").map(TagTree::count), Some(2));
+        assert_eq!(tree.get("work/clientA/billing").map(TagTree::count), Some(1));
+        assert_eq!(tree.get("work/clientC").map(TagTree::count), None);
+
+        let printed = format!("{}", tree);
+        assert_eq!(printed, "private (1)\nwork (2)\n  clientA (2)\n    billing (1)\n  clientB (1)\n");
+    }
+
+}
@@ -33,6 +33,7 @@
 
 extern crate clap;
 extern crate itertools;
+#[macro_use] extern crate lazy_static;
 #[macro_use] extern crate log;
 extern crate regex;
 extern crate toml;
@@ -41,9 +42,12 @@ extern crate libimagstore;
 #[macro_use] extern crate libimagerror;
 #[macro_use] extern crate libimagutil;
 
+pub mod alias;
 pub mod error;
 pub mod exec;
+pub mod hierarchy;
 pub mod result;
+pub mod rewrite;
 pub mod tag;
 pub mod tagable;
 pub mod util;
@@ -0,0 +1,248 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Renaming, merging and deleting tags in many entries at once
+//!
+//! A rewrite is done in two steps: `plan()` finds the entries which carry the tag and computes
+//! their new tags, `execute()` writes the new tags. The plan can be shown to the user before
+//! executing it, or instead of executing it for a dry run.
+//!
+//! As tags are hierarchical, a rewrite also applies to the descendants of a tag: renaming `work`
+//! to `job` renames `work/clientA` to `job/clientA`.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use itertools::Itertools;
+
+use libimagstore::access::StoreAccess;
+use libimagstore::storeid::StoreId;
+use libimagerror::into::IntoError;
+
+use error::TagErrorKind as TEK;
+use error::MapErrInto;
+use hierarchy::is_self_or_descendant;
+use result::Result;
+use tag::{Tag, TagSlice};
+use tagable::Tagable;
+use util::is_tag;
+
+/// An operation on the tags of all entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagOperation {
+    /// Rename a tag
+    Rename { from: Tag, to: Tag },
+
+    /// Replace several tags by one tag
+    Merge { from: Vec<Tag>, into: Tag },
+
+    /// Remove a tag from all entries
+    Delete(Tag),
+}
+
+impl TagOperation {
+
+    /// Check that all tags in the operation are valid tags
+    pub fn validate(&self) -> Result<()> {
+        let valid = match *self {
+            TagOperation::Rename { ref from, ref to }   => is_tag(from) && is_tag(to),
+            TagOperation::Merge { ref from, ref into } => from.iter().all(|t| is_tag(t)) && is_tag(into),
+            TagOperation::Delete(ref tag)              => is_tag(tag),
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(TEK::NotATag.into_error())
+        }
+    }
+
+    /// Apply the operation to the tags of one entry
+    ///
+    /// Returns `None` if the tags are not changed by the operation.
+    pub fn apply(&self, tags: &[Tag]) -> Option<Vec<Tag>> {
+        let new_tags = match *self {
+            TagOperation::Rename { ref from, ref to } => {
+                tags.iter().map(|t| replace_prefix(t, from, to)).collect::<Vec<_>>()
+            },
+            TagOperation::Merge { ref from, ref into } => {
+                // Only the first matching source is replaced, `into` might be a descendant of
+                // another source
+                tags.iter()
+                    .map(|t| match from.iter().find(|f| is_self_or_descendant(t, f)) {
+                        Some(f) => replace_prefix(t, f, into),
+                        None    => t.clone(),
+                    })
+                    .collect()
+            },
+            TagOperation::Delete(ref tag) => {
+                tags.iter().filter(|t| !is_self_or_descendant(t, tag)).cloned().collect()
+            },
+        };
+        let new_tags = new_tags.into_iter().unique().collect::<Vec<_>>();
+
+        if new_tags.as_slice() == tags {
+            None
+        } else {
+            Some(new_tags)
+        }
+    }
+
+}
+
+impl Display for TagOperation {
+
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            TagOperation::Rename { ref from, ref to }   => write!(fmt, "rename '{}' to '{}'", from, to),
+            TagOperation::Merge { ref from, ref into } => write!(fmt, "merge '{}' into '{}'", from.join("', '"), into),
+            TagOperation::Delete(ref tag)              => write!(fmt, "delete '{}'", tag),
+        }
+    }
+
+}
+
+/// Replace `from` by `to` in `tag`, if `tag` is `from` or one of its descendants
+fn replace_prefix(tag: TagSlice, from: TagSlice, to: TagSlice) -> Tag {
+    if is_self_or_descendant(tag, from) {
+        format!("{}{}", to, &tag[from.len()..])
+    } else {
+        String::from(tag)
+    }
+}
+
+/// The change of the tags of one entry
+#[derive(Debug, Clone)]
+pub struct TagChange {
+    pub id: StoreId,
+    pub old: Vec<Tag>,
+    pub new: Vec<Tag>,
+}
+
+impl Display for TagChange {
+
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "{:?}: [{}] -> [{}]", self.id, self.old.join(", "), self.new.join(", "))
+    }
+
+}
+
+/// Compute the changes the operation makes to the entries `ids`
+///
+/// Entries which are not changed by the operation are not part of the result.
+pub fn plan<I>(store: &StoreAccess, ids: I, op: &TagOperation) -> Result<Vec<TagChange>>
+    where I: Iterator<Item = StoreId>
+{
+    try!(op.validate());
+
+    let mut changes = vec![];
+    for id in ids {
+        let entry = try!(store.retrieve_copy(id.clone()).map_err_into(TEK::StoreReadError));
+        let tags  = try!(entry.get_tags());
+
+        if let Some(new) = op.apply(&tags) {
+            changes.push(TagChange { id: id, old: tags, new: new });
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Write the new tags of all changes
+///
+/// All changes are tried, the first error is returned afterwards.
+pub fn execute(store: &StoreAccess, changes: &[TagChange]) -> Result<()> {
+    let mut result = Ok(());
+
+    for change in changes {
+        let res = store.get(change.id.clone())
+            .map_err_into(TEK::StoreReadError)
+            .and_then(|entry| match entry {
+                Some(mut entry) => {
+                    try!(entry.set_tags(&change.new));
+                    store.update(entry).map_err_into(TEK::StoreWriteError)
+                },
+                None => Err(TEK::StoreWriteError.into_error()),
+            });
+
+        if let Err(e) = res {
+            warn!("Could not rewrite tags of {:?}", change.id);
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tags(ts: &[&str]) -> Vec<String> {
+        ts.iter().map(|t| String::from(*t)).collect()
+    }
+
+    #[test]
+    fn test_rename_with_descendants() {
+        let op = TagOperation::Rename { from: String::from("work"), to: String::from("job") };
+
+        assert_eq!(op.apply(&tags(&["work", "work/clientA", "workshop"])),
+                   Some(tags(&["job", "job/clientA", "workshop"])));
+        assert_eq!(op.apply(&tags(&["private"])), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let op = TagOperation::Merge {
+            from: tags(&["colour", "color"]),
+            into: String::from("colors"),
+        };
+
+        assert_eq!(op.apply(&tags(&["colour", "color", "red"])), Some(tags(&["colors", "red"])));
+        assert_eq!(op.apply(&tags(&["red"])), None);
+    }
+
+    #[test]
+    fn test_merge_into_descendant_of_source() {
+        let op = TagOperation::Merge {
+            from: tags(&["todo", "work"]),
+            into: String::from("work/todo"),
+        };
+
+        assert_eq!(op.apply(&tags(&["todo"])), Some(tags(&["work/todo"])));
+        assert_eq!(op.apply(&tags(&["work", "todo/urgent"])),
+                   Some(tags(&["work/todo", "work/todo/urgent"])));
+    }
+
+    #[test]
+    fn test_delete() {
+        let op = TagOperation::Delete(String::from("work"));
+
+        assert_eq!(op.apply(&tags(&["work/clientA", "private"])), Some(tags(&["private"])));
+        assert_eq!(op.apply(&tags(&["private"])), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(TagOperation::Delete(String::from("work/clientA")).validate().is_ok());
+        assert!(TagOperation::Delete(String::from("not a tag")).validate().is_err());
+    }
+
+}
@@ -20,6 +20,7 @@
 use itertools::Itertools;
 
 use libimagstore::store::Entry;
+use libimagstore::ownership::LibrarySection;
 use libimagerror::into::IntoError;
 use libimagstore::toml_ext::TomlValueExt;
 
@@ -28,6 +29,8 @@ use error::MapErrInto;
 use result::Result;
 use tag::{Tag, TagSlice};
 use util::is_tag;
+use alias::canonical;
+use hierarchy::is_self_or_descendant;
 
 use toml::Value;
 
@@ -42,6 +45,13 @@ pub trait Tagable {
     fn has_tag(&self, t: TagSlice) -> Result<bool>;
     fn has_tags(&self, ts: &[Tag]) -> Result<bool>;
 
+    /// Check whether the tag `t` or one of its descendants is set, so `work` matches
+    /// `work/clientA/billing`
+    fn has_tag_or_descendant(&self, t: TagSlice) -> Result<bool>;
+
+    /// Check whether each of the tags `ts` or one of its descendants is set
+    fn has_tags_or_descendants(&self, ts: &[Tag]) -> Result<bool>;
+
 }
 
 impl Tagable for Value {
@@ -82,7 +92,7 @@ impl Tagable for Value {
             return Err(TagErrorKind::NotATag.into());
         }
 
-        let a = ts.iter().unique().map(|t| Value::String(t.clone())).collect();
+        let a = ts.iter().map(|t| canonical(t)).unique().map(Value::String).collect();
         self.set("imag.tags", Value::Array(a))
             .map(|_| ())
             .map_err(Box::new)
@@ -97,7 +107,7 @@ impl Tagable for Value {
 
         self.get_tags()
             .map(|mut tags| {
-                tags.push(t);
+                tags.push(canonical(&t));
                 self.set_tags(&tags.into_iter().unique().collect::<Vec<_>>()[..])
             })
             .map(|_| ())
@@ -111,27 +121,16 @@ impl Tagable for Value {
 
         self.get_tags()
             .map(|mut tags| {
-                tags.retain(|tag| tag.clone() != t);
+                let t = canonical(&t);
+                tags.retain(|tag| canonical(tag) != t);
                 self.set_tags(&tags[..])
             })
             .map(|_| ())
     }
 
     fn has_tag(&self, t: TagSlice) -> Result<bool> {
-        let tags = try!(self.read("imag.tags").map_err_into(TagErrorKind::HeaderReadError));
-
-        if !tags.iter().all(|t| is_match!(*t, Value::String(_))) {
-            return Err(TagErrorKind::TagTypeError.into());
-        }
-
-        Ok(tags
-           .iter()
-           .any(|tag| {
-               match *tag {
-                   Value::String(ref s) => { s == t },
-                   _ => unreachable!()
-               }
-           }))
+        let t = canonical(t);
+        self.get_tags().map(|tags| tags.iter().any(|tag| canonical(tag) == t))
     }
 
     fn has_tags(&self, tags: &[Tag]) -> Result<bool> {
@@ -143,6 +142,21 @@ impl Tagable for Value {
         Ok(result)
     }
 
+    fn has_tag_or_descendant(&self, t: TagSlice) -> Result<bool> {
+        let t = canonical(t);
+        self.get_tags()
+            .map(|tags| tags.iter().any(|tag| is_self_or_descendant(&canonical(tag), &t)))
+    }
+
+    fn has_tags_or_descendants(&self, ts: &[Tag]) -> Result<bool> {
+        let mut result = true;
+        for t in ts {
+            result = result && try!(self.has_tag_or_descendant(t));
+        }
+
+        Ok(result)
+    }
+
 }
 
 impl Tagable for Entry {
@@ -152,14 +166,17 @@ impl Tagable for Entry {
     }
 
     fn set_tags(&mut self, ts: &[Tag]) -> Result<()> {
+        self.grant_section_write(LibrarySection::Tags);
         self.get_header_mut().set_tags(ts)
     }
 
     fn add_tag(&mut self, t: Tag) -> Result<()> {
+        self.grant_section_write(LibrarySection::Tags);
         self.get_header_mut().add_tag(t)
     }
 
     fn remove_tag(&mut self, t: Tag) -> Result<()> {
+        self.grant_section_write(LibrarySection::Tags);
         self.get_header_mut().remove_tag(t)
     }
 
@@ -171,5 +188,13 @@ impl Tagable for Entry {
         self.get_header().has_tags(ts)
     }
 
+    fn has_tag_or_descendant(&self, t: TagSlice) -> Result<bool> {
+        self.get_header().has_tag_or_descendant(t)
+    }
+
+    fn has_tags_or_descendants(&self, ts: &[Tag]) -> Result<bool> {
+        self.get_header().has_tags_or_descendants(ts)
+    }
+
 }
 
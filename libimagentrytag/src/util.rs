@@ -19,6 +19,10 @@
 
 use regex::Regex;
 
+/// Check whether a string is a valid tag
+///
+/// A tag may be hierarchical, like `work/clientA/billing`. Each part has to start with a letter
+/// and may contain letters, digits, `_` and `-`.
 pub fn is_tag(s: &str) -> bool {
-    Regex::new("^[a-zA-Z]([a-zA-Z0-9_-]*)$").unwrap().captures(s).is_some()
+    Regex::new("^[a-zA-Z][a-zA-Z0-9_-]*(/[a-zA-Z][a-zA-Z0-9_-]*)*$").unwrap().captures(s).is_some()
 }
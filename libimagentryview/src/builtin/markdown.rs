@@ -0,0 +1,138 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+
+//! A viewer which renders markdown content for the terminal
+//!
+//! The content is rendered with `libimagentrymarkdown::terminal` and wrapped to the width of the
+//! terminal. If stdout is not a terminal, the content is printed unchanged, so piping the output
+//! does not mangle it.
+
+use atty;
+use term_size;
+use toml::encode_str;
+
+use libimagstore::store::Entry;
+use libimagentrymarkdown::terminal::to_terminal;
+
+use viewer::Viewer;
+use result::Result;
+use error::ViewErrorKind as VEK;
+use error::ViewError as VE;
+
+/// The width used if the width of the terminal cannot be determined
+const DEFAULT_WIDTH: usize = 80;
+
+pub struct MarkdownViewer {
+    show_header: bool,
+    colored: bool,
+    width: Option<usize>,
+}
+
+impl MarkdownViewer {
+
+    /// Create a viewer, which styles the content with escape codes if `colored` is set
+    pub fn new(show_header: bool, colored: bool) -> MarkdownViewer {
+        MarkdownViewer {
+            show_header: show_header,
+            colored: colored,
+            width: None,
+        }
+    }
+
+    /// Wrap to this width instead of the width of the terminal
+    pub fn with_width(mut self, width: usize) -> MarkdownViewer {
+        self.width = Some(width);
+        self
+    }
+
+}
+
+impl Viewer for MarkdownViewer {
+
+    fn view_entry(&self, e: &Entry) -> Result<()> {
+        if self.show_header {
+            println!("{}", encode_str(e.get_header()));
+        }
+
+        if atty::is(atty::Stream::Stdout) {
+            let width = self.width
+                .or_else(|| term_size::dimensions().map(|(w, _)| w))
+                .unwrap_or(DEFAULT_WIDTH);
+            let rendered = try!(to_terminal(e.get_content(), width, self.colored)
+                .map_err(|e| VE::new(VEK::MarkdownError, Some(Box::new(e)))));
+            print!("{}", rendered);
+        } else {
+            println!("{}", e.get_content());
+        }
+
+        Ok(())
+    }
+
+}
+
+/// Guess whether a text is markdown, by looking for block or inline markup
+///
+/// Plain text rarely has lines starting with `# `, list markers or fences, or contains links in
+/// markdown syntax, so a single such line is taken as a sign.
+pub fn looks_like_markdown(content: &str) -> bool {
+    content.lines().any(|line| {
+        let trimmed = line.trim_left();
+        is_heading(line) ||
+            trimmed.starts_with("```") ||
+            trimmed.starts_with("> ") ||
+            is_list_item(trimmed) ||
+            (line.contains("](") && line.contains('[')) ||
+            line.contains("**")
+    })
+}
+
+/// Whether the line is an ATX heading like `# Title`
+fn is_heading(line: &str) -> bool {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest  = &line[level..];
+    level >= 1 && level <= 6 && (rest.is_empty() || rest.starts_with(' '))
+}
+
+/// Whether the line starts with a list marker like `- ` or `1. `
+fn is_list_item(trimmed: &str) -> bool {
+    if trimmed.starts_with("- ") || trimmed.starts_with("* ") || trimmed.starts_with("+ ") {
+        return true;
+    }
+
+    let digits = trimmed.chars().take_while(|c| c.is_digit(10)).count();
+    let rest   = &trimmed[digits..];
+    digits > 0 && (rest.starts_with(". ") || rest.starts_with(") "))
+}
+
+#[cfg(test)]
+mod test {
+    use super::looks_like_markdown;
+
+    #[test]
+    fn test_looks_like_markdown() {
+        assert!(looks_like_markdown("# Heading"));
+        assert!(looks_like_markdown("text\n- item"));
+        assert!(looks_like_markdown("text\n  1. item"));
+        assert!(looks_like_markdown("a [link](http://example.com)"));
+        assert!(!looks_like_markdown("Just some plain text.\nOn two lines."));
+        assert!(!looks_like_markdown("#hashtag"));
+    }
+
+}
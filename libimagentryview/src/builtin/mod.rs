@@ -18,5 +18,6 @@
 //
 
 pub mod editor;
+pub mod markdown;
 pub mod plain;
 pub mod stdout;
@@ -23,7 +23,8 @@ generate_error_module!(
         GlobError            => "Error while glob()ing",
         PatternError         => "Error in glob() pattern",
         PatternBuildingError => "Could not build glob() pattern",
-        ViewError            => "Failed to start viewer"
+        ViewError            => "Failed to start viewer",
+        MarkdownError        => "Failed to render markdown"
     );
 );
 
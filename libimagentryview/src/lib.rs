@@ -33,13 +33,16 @@
     while_true,
 )]
 
+extern crate atty;
 extern crate glob;
+extern crate term_size;
 extern crate toml;
 
 extern crate libimagstore;
 extern crate libimagrt;
 #[macro_use] extern crate libimagerror;
 extern crate libimagentryedit;
+extern crate libimagentrymarkdown;
 
 pub mod error;
 pub mod builtin;
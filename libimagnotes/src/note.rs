@@ -29,7 +29,7 @@ use libimagstore::storeid::IntoStoreId;
 use libimagstore::storeid::StoreId;
 use libimagstore::storeid::StoreIdIterator;
 use libimagstore::store::FileLockEntry;
-use libimagstore::store::Store;
+use libimagstore::access::StoreAccess;
 use libimagstore::toml_ext::TomlValueExt;
 
 use module_path::ModuleEntryPath;
@@ -45,7 +45,7 @@ pub struct Note<'a> {
 
 impl<'a> Note<'a> {
 
-    pub fn new(store: &Store, name: String, text: String) -> Result<Note> {
+    pub fn new(store: &StoreAccess, name: String, text: String) -> Result<Note> {
         use std::ops::DerefMut;
 
         debug!("Creating new Note: '{}'", name);
@@ -109,14 +109,14 @@ impl<'a> Note<'a> {
         self.entry.get_content()
     }
 
-    pub fn delete(store: &Store, name: String) -> Result<()> {
+    pub fn delete(store: &StoreAccess, name: String) -> Result<()> {
         ModuleEntryPath::new(name)
             .into_storeid()
             .and_then(|id| store.delete(id))
             .map_err_into(NEK::StoreWriteError)
     }
 
-    pub fn retrieve(store: &Store, name: String) -> Result<Note> {
+    pub fn retrieve(store: &StoreAccess, name: String) -> Result<Note> {
         ModuleEntryPath::new(name)
             .into_storeid()
             .and_then(|id| store.retrieve(id))
@@ -124,7 +124,7 @@ impl<'a> Note<'a> {
             .map(|entry| Note { entry: entry })
     }
 
-    pub fn get(store: &Store, name: String) -> Result<Option<Note>> {
+    pub fn get(store: &StoreAccess, name: String) -> Result<Option<Note>> {
         ModuleEntryPath::new(name)
             .into_storeid()
             .and_then(|id| store.get(id))
@@ -132,7 +132,7 @@ impl<'a> Note<'a> {
             .map(|o| o.map(|entry| Note { entry: entry }))
     }
 
-    pub fn all_notes(store: &Store) -> Result<NoteIterator> {
+    pub fn all_notes(store: &StoreAccess) -> Result<NoteIterator> {
         store.retrieve_for_module("notes")
             .map(|iter| NoteIterator::new(store, iter))
             .map_err(|e| NE::new(NEK::StoreReadError, Some(Box::new(e))))
@@ -149,12 +149,12 @@ impl<'a> Edit for Note<'a> {
 }
 
 trait FromStoreId {
-    fn from_storeid(&Store, StoreId) -> Result<Note>;
+    fn from_storeid(&StoreAccess, StoreId) -> Result<Note>;
 }
 
 impl<'a> FromStoreId for Note<'a> {
 
-    fn from_storeid(store: &Store, id: StoreId) -> Result<Note> {
+    fn from_storeid(store: &StoreAccess, id: StoreId) -> Result<Note> {
         debug!("Loading note from storeid: '{:?}'", id);
         match store.retrieve(id) {
             Err(e)    => Err(NE::new(NEK::StoreReadError, Some(Box::new(e)))),
@@ -176,13 +176,13 @@ impl<'a> Deref for Note<'a> {
 
 #[derive(Debug)]
 pub struct NoteIterator<'a> {
-    store: &'a Store,
+    store: &'a StoreAccess,
     iditer: StoreIdIterator,
 }
 
 impl<'a> NoteIterator<'a> {
 
-    pub fn new(store: &'a Store, iditer: StoreIdIterator) -> NoteIterator<'a> {
+    pub fn new(store: &'a StoreAccess, iditer: StoreIdIterator) -> NoteIterator<'a> {
         NoteIterator {
             store: store,
             iditer: iditer,
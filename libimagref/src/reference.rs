@@ -32,7 +32,7 @@ use std::result::Result as RResult;
 use libimagstore::store::FileLockEntry;
 use libimagstore::storeid::StoreId;
 use libimagstore::storeid::IntoStoreId;
-use libimagstore::store::Store;
+use libimagstore::access::StoreAccess;
 use libimagstore::toml_ext::TomlValueExt;
 use libimagerror::into::IntoError;
 
@@ -56,7 +56,7 @@ impl<'a> Ref<'a> {
     }
 
     /// Try to get `si` as Ref object from the store
-    pub fn get(store: &'a Store, si: StoreId) -> Result<Ref<'a>> {
+    pub fn get(store: &'a StoreAccess, si: StoreId) -> Result<Ref<'a>> {
         match store.get(si) {
             Err(e) => return Err(REK::StoreReadError.into_error_with_cause(Box::new(e))),
             Ok(None) => return Err(REK::RefNotInStore.into_error()),
@@ -67,7 +67,7 @@ impl<'a> Ref<'a> {
     /// Get a Ref object from the store by hash.
     ///
     /// Returns None if the hash cannot be found.
-    pub fn get_by_hash(store: &'a Store, hash: String) -> Result<Option<Ref<'a>>> {
+    pub fn get_by_hash(store: &'a StoreAccess, hash: String) -> Result<Option<Ref<'a>>> {
         ModuleEntryPath::new(hash)
             .into_storeid()
             .and_then(|id| store.get(id))
@@ -79,7 +79,7 @@ impl<'a> Ref<'a> {
     /// Delete a ref by hash
     ///
     /// If the returned Result contains an error, the ref might not be deleted.
-    pub fn delete_by_hash(store: &'a Store, hash: String) -> Result<()> {
+    pub fn delete_by_hash(store: &'a StoreAccess, hash: String) -> Result<()> {
         ModuleEntryPath::new(hash)
             .into_storeid()
             .and_then(|id| store.delete(id))
@@ -96,7 +96,10 @@ impl<'a> Ref<'a> {
         }
     }
 
-    pub fn create_with_hasher<H: Hasher>(store: &'a Store, pb: PathBuf, flags: RefFlags, mut h: H)
+    pub fn create_with_hasher<H: Hasher>(store: &'a StoreAccess,
+                                         pb: PathBuf,
+                                         flags: RefFlags,
+                                         mut h: H)
         -> Result<Ref<'a>>
     {
         if !pb.exists() {
@@ -176,8 +179,9 @@ impl<'a> Ref<'a> {
                 // and then we create the FileLockEntry in the Store
                 // and return (filelockentry, content hash, permissions, canonicalized path)
                 .and_then(|(opt_conhash, opt_perm, can, path_hash)| {
-                    let fle = try!(store
-                                   .create(ModuleEntryPath::new(path_hash))
+                    let fle = try!(ModuleEntryPath::new(path_hash)
+                                   .into_storeid()
+                                   .and_then(|id| store.create(id))
                                    .map_err(Box::new)
                                    .map_err(|e| REK::StoreWriteError.into_error_with_cause(e))
                     );
@@ -228,7 +232,7 @@ impl<'a> Ref<'a> {
     }
 
     /// Create a Ref object which refers to `pb`
-    pub fn create(store: &'a Store, pb: PathBuf, flags: RefFlags) -> Result<Ref<'a>> {
+    pub fn create(store: &'a StoreAccess, pb: PathBuf, flags: RefFlags) -> Result<Ref<'a>> {
         Ref::create_with_hasher(store, pb, flags, DefaultHasher::new())
     }
 
@@ -422,7 +426,7 @@ impl<'a> Ref<'a> {
     }
 
     /// Check whether there is a reference to the file at `pb`
-    pub fn exists(store: &Store, pb: PathBuf) -> Result<bool> {
+    pub fn exists(store: &StoreAccess, pb: PathBuf) -> Result<bool> {
         pb.canonicalize()
             .map_err(Box::new)
             .map_err(|e| REK::PathCanonicalizationError.into_error_with_cause(e))
@@ -36,6 +36,9 @@ use error::MapErrInto;
 use logger::ImagLogger;
 
 use libimagstore::store::Store;
+use libimagstore::overlay::OverlayStore;
+use libimagstore::hook::Hook;
+use libimagstore::hook::position::HookPosition;
 
 /// The Runtime object
 ///
@@ -45,7 +48,7 @@ pub struct Runtime<'a> {
     rtp: PathBuf,
     configuration: Option<Configuration>,
     cli_matches: ArgMatches<'a>,
-    store: Store,
+    store: OverlayStore,
 }
 
 impl<'a> Runtime<'a> {
@@ -62,14 +65,11 @@ impl<'a> Runtime<'a> {
         use clap::Shell;
 
         use libimagstore::hook::position::HookPosition as HP;
-        use libimagstore::hook::Hook;
-        use libimagstore::error::StoreErrorKind;
         use libimagstorestdhook::debug::DebugHook;
         use libimagstorestdhook::vcs::git::delete::DeleteHook as GitDeleteHook;
         use libimagstorestdhook::vcs::git::update::UpdateHook as GitUpdateHook;
         use libimagstorestdhook::vcs::git::store_unload::StoreUnloadHook as GitStoreUnloadHook;
         use libimagerror::trace::trace_error;
-        use libimagerror::trace::trace_error_dbg;
         use libimagerror::into::IntoError;
 
         use configuration::error::ConfigErrorKind;
@@ -142,49 +142,47 @@ impl<'a> Runtime<'a> {
             write!(stderr(), "Store-config: {:?}\n", store_config).ok();
         }
 
-        Store::new(storepath.clone(), store_config).map(|mut store| {
-            // If we are debugging, generate hooks for all positions
-            if is_debugging {
-                let hooks : Vec<(Box<Hook>, &str, HP)> = vec![
-                    (Box::new(DebugHook::new(HP::PreCreate))          , "debug", HP::PreCreate),
-                    (Box::new(DebugHook::new(HP::PostCreate))         , "debug", HP::PostCreate),
-                    (Box::new(DebugHook::new(HP::PreRetrieve))        , "debug", HP::PreRetrieve),
-                    (Box::new(DebugHook::new(HP::PostRetrieve))       , "debug", HP::PostRetrieve),
-                    (Box::new(DebugHook::new(HP::PreUpdate))          , "debug", HP::PreUpdate),
-                    (Box::new(DebugHook::new(HP::PostUpdate))         , "debug", HP::PostUpdate),
-                    (Box::new(DebugHook::new(HP::PreDelete))          , "debug", HP::PreDelete),
-                    (Box::new(DebugHook::new(HP::PostDelete))         , "debug", HP::PostDelete),
-                ];
-
-                // If hook registration fails, trace the error and warn, but continue.
-                for (hook, aspectname, position) in hooks {
-                    if let Err(e) = store.register_hook(position, &String::from(aspectname), hook) {
-                        if e.err_type() == StoreErrorKind::HookRegisterError {
-                            trace_error_dbg(&e);
-                            warn!("Registering debug hook with store failed");
-                        } else {
-                            trace_error(&e);
-                        };
-                    }
+        // The module name, as used for the header section ownership, is the binary name without
+        // the "imag-" prefix
+        let module = String::from(cli_spec.get_name().trim_left_matches("imag-"));
+
+        Store::new(storepath, store_config).and_then(|mut store| {
+            store.set_acting_module(module);
+            OverlayStore::from_config(store)
+        })
+        .map(|mut store| {
+            // The hooks are registered on all mounts, the version control hooks only on the
+            // writable ones, each for the repository of the mount
+            for mount in store.mounts_mut().iter_mut() {
+                let writable = mount.is_writable();
+                let store    = mount.store_mut();
+
+                // If we are debugging, generate hooks for all positions
+                if is_debugging {
+                    let hooks : Vec<(Box<Hook>, &str, HP)> = vec![
+                        (Box::new(DebugHook::new(HP::PreCreate))          , "debug", HP::PreCreate),
+                        (Box::new(DebugHook::new(HP::PostCreate))         , "debug", HP::PostCreate),
+                        (Box::new(DebugHook::new(HP::PreRetrieve))        , "debug", HP::PreRetrieve),
+                        (Box::new(DebugHook::new(HP::PostRetrieve))       , "debug", HP::PostRetrieve),
+                        (Box::new(DebugHook::new(HP::PreUpdate))          , "debug", HP::PreUpdate),
+                        (Box::new(DebugHook::new(HP::PostUpdate))         , "debug", HP::PostUpdate),
+                        (Box::new(DebugHook::new(HP::PreDelete))          , "debug", HP::PreDelete),
+                        (Box::new(DebugHook::new(HP::PostDelete))         , "debug", HP::PostDelete),
+                    ];
+
+                    register_hooks(store, hooks, "Registering debug hook with store failed");
                 }
-            }
 
-            let sp = storepath;
-
-            let hooks : Vec<(Box<Hook>, &str, HP)> = vec![
-                (Box::new(GitDeleteHook::new(sp.clone(), HP::PostDelete)), "vcs", HP::PostDelete),
-                (Box::new(GitUpdateHook::new(sp.clone(), HP::PostUpdate)), "vcs", HP::PostUpdate),
-                (Box::new(GitStoreUnloadHook::new(sp)),                    "vcs", HP::StoreUnload),
-            ];
-
-            for (hook, aspectname, position) in hooks {
-                if let Err(e) = store.register_hook(position, &String::from(aspectname), hook) {
-                    if e.err_type() == StoreErrorKind::HookRegisterError {
-                        trace_error_dbg(&e);
-                        warn!("Registering git hook with store failed");
-                    } else {
-                        trace_error(&e);
-                    };
+                if writable {
+                    let sp = store.path().clone();
+
+                    let hooks : Vec<(Box<Hook>, &str, HP)> = vec![
+                        (Box::new(GitDeleteHook::new(sp.clone(), HP::PostDelete)), "vcs", HP::PostDelete),
+                        (Box::new(GitUpdateHook::new(sp.clone(), HP::PostUpdate)), "vcs", HP::PostUpdate),
+                        (Box::new(GitStoreUnloadHook::new(sp)),                    "vcs", HP::StoreUnload),
+                    ];
+
+                    register_hooks(store, hooks, "Registering git hook with store failed");
                 }
             }
 
@@ -376,6 +374,11 @@ impl<'a> Runtime<'a> {
         self.cli_matches.is_present("debugging")
     }
 
+    /// Whether the output may be colored, which is turned off with `--no-color`
+    pub fn use_colors(&self) -> bool {
+        !self.cli_matches.is_present(Runtime::arg_no_color_output_name())
+    }
+
     /// Get the runtimepath
     pub fn rtp(&self) -> &PathBuf {
         &self.rtp
@@ -392,7 +395,10 @@ impl<'a> Runtime<'a> {
     }
 
     /// Get the store object
-    pub fn store(&self) -> &Store {
+    ///
+    /// This is the primary store with all stores mounted via the `store.mounts` configuration.
+    /// The primary `Store` itself is available via `OverlayStore::primary()`.
+    pub fn store(&self) -> &OverlayStore {
         &self.store
     }
 
@@ -412,6 +418,29 @@ impl<'a> Runtime<'a> {
     }
 }
 
+/// Register hooks with the store
+///
+/// If hook registration fails, trace the error and warn, but continue.
+fn register_hooks(store: &mut Store,
+                  hooks: Vec<(Box<Hook>, &str, HookPosition)>,
+                  warning: &str)
+{
+    use libimagstore::error::StoreErrorKind;
+    use libimagerror::trace::trace_error;
+    use libimagerror::trace::trace_error_dbg;
+
+    for (hook, aspectname, position) in hooks {
+        if let Err(e) = store.register_hook(position, &String::from(aspectname), hook) {
+            if e.err_type() == StoreErrorKind::HookRegisterError {
+                trace_error_dbg(&e);
+                warn!("{}", warning);
+            } else {
+                trace_error(&e);
+            };
+        }
+    }
+}
+
 fn get_override_specs(matches: &ArgMatches) -> Vec<String> {
     matches
         .values_of("config-override")
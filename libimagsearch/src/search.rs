@@ -0,0 +1,344 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+use toml::Value;
+
+use libimagstore::store::Entry;
+use libimagstore::store::FileLockEntry;
+use libimagstore::access::StoreAccess;
+use libimagstore::store::StoreObject;
+use libimagstore::storeid::IntoStoreId;
+use libimagstore::storeid::StoreId;
+use libimagstore::toml_ext::TomlValueExt;
+use libimagentryfilter::cli::parse_with_store;
+use libimagentrylist::pipeline::compare_values;
+use libimagerror::into::IntoError;
+use filters::filter::Filter;
+
+use module_path::ModuleEntryPath;
+use result::Result;
+use error::SearchErrorKind as SEK;
+use error::MapErrInto;
+
+/// Options of a saved search, besides the filter expression
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Only search the entries of this module, search the whole store if `None`
+    pub module: Option<String>,
+
+    /// Sort the results by this header field
+    pub sort: Option<String>,
+
+    /// Sort in descending order
+    pub reverse: bool,
+
+    /// Only return the first `limit` results
+    pub limit: Option<usize>,
+
+    /// Header fields to print for each result
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SavedSearch<'a> {
+    entry: FileLockEntry<'a>,
+}
+
+impl<'a> SavedSearch<'a> {
+
+    /// Save a new search
+    ///
+    /// Fails if the filter expression cannot be parsed, or if a search with this name exists.
+    pub fn new(store: &'a StoreAccess, name: String, filter: String, opts: SearchOptions)
+        -> Result<SavedSearch<'a>>
+    {
+        debug!("Creating new saved search: '{}'", name);
+        if let Err(e) = parse_with_store(&filter, store) {
+            return Err(SEK::FilterExpressionError.into_error_with_cause(Box::new(e)));
+        }
+
+        let id = try!(ModuleEntryPath::new(name.clone()).into_storeid().map_err_into(SEK::StoreWriteError));
+        if try!(store.exists(id.clone()).map_err_into(SEK::StoreReadError)) {
+            return Err(SEK::SearchExists.into_error());
+        }
+
+        let mut entry = try!(store.create(id).map_err_into(SEK::StoreWriteError));
+
+        let mut search = BTreeMap::new();
+        search.insert(String::from("name"), Value::String(name));
+        search.insert(String::from("filter"), Value::String(filter));
+        search.insert(String::from("reverse"), Value::Boolean(opts.reverse));
+        search.insert(String::from("fields"),
+                      Value::Array(opts.fields.into_iter().map(Value::String).collect()));
+        if let Some(module) = opts.module {
+            search.insert(String::from("module"), Value::String(module));
+        }
+        if let Some(sort) = opts.sort {
+            search.insert(String::from("sort"), Value::String(sort));
+        }
+        if let Some(limit) = opts.limit {
+            search.insert(String::from("limit"), Value::Integer(limit as i64));
+        }
+
+        try!(entry.get_header_mut()
+             .set("search", Value::Table(search))
+             .map_err_into(SEK::StoreWriteError));
+
+        Ok(SavedSearch { entry: entry })
+    }
+
+    pub fn get(store: &'a StoreAccess, name: String) -> Result<Option<SavedSearch<'a>>> {
+        ModuleEntryPath::new(name)
+            .into_storeid()
+            .and_then(|id| store.get(id))
+            .map_err_into(SEK::StoreReadError)
+            .map(|o| o.map(|entry| SavedSearch { entry: entry }))
+    }
+
+    pub fn delete(store: &StoreAccess, name: String) -> Result<()> {
+        ModuleEntryPath::new(name)
+            .into_storeid()
+            .and_then(|id| store.delete(id))
+            .map_err_into(SEK::StoreWriteError)
+    }
+
+    /// Get the names of all saved searches
+    pub fn all_names(store: &StoreAccess) -> Result<Vec<String>> {
+        let ids = try!(store.retrieve_for_module("search").map_err_into(SEK::StoreReadError));
+
+        let mut names = vec![];
+        for id in ids {
+            let entry = try!(store.retrieve_copy(id).map_err_into(SEK::StoreReadError));
+            if let Some(name) = try!(read_string(entry.get_header(), "search.name")) {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+
+    pub fn get_name(&self) -> Result<String> {
+        read_string(self.entry.get_header(), "search.name")
+            .and_then(|n| n.ok_or(SEK::HeaderTypeError.into_error()))
+    }
+
+    pub fn get_filter(&self) -> Result<String> {
+        read_string(self.entry.get_header(), "search.filter")
+            .and_then(|f| f.ok_or(SEK::HeaderTypeError.into_error()))
+    }
+
+    pub fn get_options(&self) -> Result<SearchOptions> {
+        let header = self.entry.get_header();
+
+        let reverse = match try!(header.read("search.reverse").map_err_into(SEK::StoreReadError)) {
+            Some(Value::Boolean(b)) => b,
+            None                    => false,
+            Some(_)                 => return Err(SEK::HeaderTypeError.into_error()),
+        };
+
+        let limit = match try!(header.read("search.limit").map_err_into(SEK::StoreReadError)) {
+            Some(Value::Integer(i)) if i >= 0 => Some(i as usize),
+            None                              => None,
+            Some(_)                           => return Err(SEK::HeaderTypeError.into_error()),
+        };
+
+        let fields = match try!(header.read("search.fields").map_err_into(SEK::StoreReadError)) {
+            Some(Value::Array(a)) => {
+                let mut fields = vec![];
+                for v in a {
+                    match v {
+                        Value::String(s) => fields.push(s),
+                        _ => return Err(SEK::HeaderTypeError.into_error()),
+                    }
+                }
+                fields
+            },
+            None    => vec![],
+            Some(_) => return Err(SEK::HeaderTypeError.into_error()),
+        };
+
+        Ok(SearchOptions {
+            module: try!(read_string(header, "search.module")),
+            sort: try!(read_string(header, "search.sort")),
+            reverse: reverse,
+            limit: limit,
+            fields: fields,
+        })
+    }
+
+    /// Run the search, returning copies of the matching entries
+    ///
+    /// Saved searches themselves and entries in hidden directories are never part of the result.
+    pub fn run(&self, store: &StoreAccess) -> Result<Vec<Entry>> {
+        let filter = try!(self.get_filter());
+        let filter = match parse_with_store(&filter, store) {
+            Ok(f)  => f,
+            Err(e) => return Err(SEK::FilterExpressionError.into_error_with_cause(Box::new(e))),
+        };
+        let opts = try!(self.get_options());
+
+        let ids = store
+            .walk(opts.module.as_ref().map(|m| &m[..]).unwrap_or(""))
+            .filter_map(|obj| match obj {
+                StoreObject::Id(id)        => Some(id),
+                StoreObject::Collection(_) => None,
+            })
+            .filter(is_searchable);
+
+        let mut results = vec![];
+        for id in ids {
+            let entry = try!(store.retrieve_copy(id).map_err_into(SEK::StoreReadError));
+            if filter.filter(&entry) {
+                results.push(entry);
+            }
+        }
+
+        if let Some(ref sort) = opts.sort {
+            results.sort_by(|a, b| {
+                let a = a.get_header().read(sort).ok().and_then(|v| v);
+                let b = b.get_header().read(sort).ok().and_then(|v| v);
+                compare_values(a.as_ref(), b.as_ref())
+            });
+        }
+
+        if opts.reverse {
+            results.reverse();
+        }
+
+        if let Some(limit) = opts.limit {
+            results.truncate(limit);
+        }
+
+        Ok(results)
+    }
+
+}
+
+impl<'a> Deref for SavedSearch<'a> {
+
+    type Target = FileLockEntry<'a>;
+
+    fn deref(&self) -> &FileLockEntry<'a> {
+        &self.entry
+    }
+
+}
+
+/// Whether an entry is searched: saved searches and entries in hidden directories are not
+fn is_searchable(id: &StoreId) -> bool {
+    let mut components = id.local().components().map(|c| c.as_os_str().to_str());
+
+    match components.next() {
+        Some(Some("search")) | Some(None) | None => false,
+        Some(Some(first)) => {
+            !first.starts_with('.') &&
+                components.all(|c| c.map(|c| !c.starts_with('.')).unwrap_or(false))
+        },
+    }
+}
+
+fn read_string(header: &Value, path: &str) -> Result<Option<String>> {
+    match try!(header.read(path).map_err_into(SEK::StoreReadError)) {
+        Some(Value::String(s)) => Ok(Some(s)),
+        None                   => Ok(None),
+        Some(_)                => Err(SEK::HeaderTypeError.into_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use tempdir::TempDir;
+    use toml::Value;
+
+    use libimagstore::store::Store;
+    use libimagstore::toml_ext::TomlValueExt;
+
+    use super::{SavedSearch, SearchOptions};
+
+    fn create(store: &Store, id: &str, prio: i64) {
+        let mut entry = store.create(PathBuf::from(id)).unwrap();
+        assert!(entry.get_header_mut().set("note.prio", Value::Integer(prio)).is_ok());
+    }
+
+    fn ids(store: &Store, name: &str) -> Vec<String> {
+        let search = SavedSearch::get(store, String::from(name)).unwrap().unwrap();
+        search.run(store)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.get_location().clone().without_base().to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_save_and_run() {
+        let dir   = TempDir::new("search").unwrap();
+        let store = Store::new(PathBuf::from(dir.path()), None).unwrap();
+
+        create(&store, "notes/a", 2);
+        create(&store, "notes/b", 1);
+        create(&store, "notes/c", 0);
+        create(&store, "other/d", 3);
+
+        let opts = SearchOptions {
+            module: Some(String::from("notes")),
+            sort: Some(String::from("note.prio")),
+            ..SearchOptions::default()
+        };
+        let filter = String::from("header.note.prio > 0");
+        assert!(SavedSearch::new(&store, String::from("prio"), filter, opts).is_ok());
+
+        assert_eq!(ids(&store, "prio"), vec!["notes/b", "notes/a"]);
+    }
+
+    #[test]
+    fn test_run_reverse_and_limit() {
+        let dir   = TempDir::new("search").unwrap();
+        let store = Store::new(PathBuf::from(dir.path()), None).unwrap();
+
+        create(&store, "notes/a", 2);
+        create(&store, "notes/b", 1);
+        create(&store, "other/d", 3);
+
+        let opts = SearchOptions {
+            sort: Some(String::from("note.prio")),
+            reverse: true,
+            limit: Some(2),
+            ..SearchOptions::default()
+        };
+        let filter = String::from("true");
+        assert!(SavedSearch::new(&store, String::from("top"), filter, opts).is_ok());
+
+        assert_eq!(ids(&store, "top"), vec!["other/d", "notes/a"]);
+    }
+
+    #[test]
+    fn test_invalid_filter_is_not_saved() {
+        let dir   = TempDir::new("search").unwrap();
+        let store = Store::new(PathBuf::from(dir.path()), None).unwrap();
+
+        let filter = String::from("header.note.prio >");
+        assert!(SavedSearch::new(&store, String::from("bad"), filter, SearchOptions::default()).is_err());
+        assert!(SavedSearch::get(&store, String::from("bad")).unwrap().is_none());
+    }
+
+}
@@ -0,0 +1,142 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+
+//! The interface to the entries of a store, which is common to `Store` and `OverlayStore`
+//!
+//! Libraries take a `&StoreAccess` instead of a `&Store`, so they work on all mounts of the
+//! `OverlayStore` of the runtime as well as on a single `Store`.
+
+use std::fmt::Debug;
+
+use storeid::{StoreId, StoreIdIterator};
+use store::{Entry, FileLockEntry, Result, Store, StoreObject};
+use overlay::OverlayStore;
+
+pub trait StoreAccess : Debug {
+
+    /// Create an entry, see `Store::create()`
+    fn create<'a>(&'a self, id: StoreId) -> Result<FileLockEntry<'a>>;
+
+    /// Retrieve an entry for modification, creating it if it does not exist, see
+    /// `Store::retrieve()`
+    fn retrieve<'a>(&'a self, id: StoreId) -> Result<FileLockEntry<'a>>;
+
+    /// Get an entry, if it exists, see `Store::get()`
+    fn get<'a>(&'a self, id: StoreId) -> Result<Option<FileLockEntry<'a>>>;
+
+    /// Get a copy of an entry, see `Store::retrieve_copy()`
+    fn retrieve_copy(&self, id: StoreId) -> Result<Entry>;
+
+    /// Write an entry back and report the errors which dropping it would swallow
+    fn update<'a>(&'a self, entry: FileLockEntry<'a>) -> Result<()>;
+
+    /// Delete an entry, see `Store::delete()`
+    fn delete(&self, id: StoreId) -> Result<()>;
+
+    /// Whether the entry exists
+    fn exists(&self, id: StoreId) -> Result<bool>;
+
+    /// Iterate over the ids of a module, see `Store::retrieve_for_module()`
+    fn retrieve_for_module(&self, mod_name: &str) -> Result<StoreIdIterator>;
+
+    /// Walk the entries and collections of a module, see `Store::walk()`
+    fn walk(&self, mod_name: &str) -> Box<Iterator<Item = StoreObject>>;
+
+}
+
+impl StoreAccess for Store {
+
+    fn create<'a>(&'a self, id: StoreId) -> Result<FileLockEntry<'a>> {
+        Store::create(self, id)
+    }
+
+    fn retrieve<'a>(&'a self, id: StoreId) -> Result<FileLockEntry<'a>> {
+        Store::retrieve(self, id)
+    }
+
+    fn get<'a>(&'a self, id: StoreId) -> Result<Option<FileLockEntry<'a>>> {
+        Store::get(self, id)
+    }
+
+    fn retrieve_copy(&self, id: StoreId) -> Result<Entry> {
+        Store::retrieve_copy(self, id)
+    }
+
+    fn update<'a>(&'a self, entry: FileLockEntry<'a>) -> Result<()> {
+        Store::update(self, entry)
+    }
+
+    fn delete(&self, id: StoreId) -> Result<()> {
+        Store::delete(self, id)
+    }
+
+    fn exists(&self, id: StoreId) -> Result<bool> {
+        Store::exists(self, id)
+    }
+
+    fn retrieve_for_module(&self, mod_name: &str) -> Result<StoreIdIterator> {
+        Store::retrieve_for_module(self, mod_name)
+    }
+
+    fn walk(&self, mod_name: &str) -> Box<Iterator<Item = StoreObject>> {
+        Box::new(Store::walk(self, mod_name))
+    }
+
+}
+
+impl StoreAccess for OverlayStore {
+
+    fn create<'a>(&'a self, id: StoreId) -> Result<FileLockEntry<'a>> {
+        OverlayStore::create(self, id)
+    }
+
+    fn retrieve<'a>(&'a self, id: StoreId) -> Result<FileLockEntry<'a>> {
+        OverlayStore::retrieve(self, id)
+    }
+
+    fn get<'a>(&'a self, id: StoreId) -> Result<Option<FileLockEntry<'a>>> {
+        OverlayStore::get(self, id)
+    }
+
+    fn retrieve_copy(&self, id: StoreId) -> Result<Entry> {
+        OverlayStore::retrieve_copy(self, id)
+    }
+
+    fn update<'a>(&'a self, entry: FileLockEntry<'a>) -> Result<()> {
+        OverlayStore::update(self, entry)
+    }
+
+    fn delete(&self, id: StoreId) -> Result<()> {
+        OverlayStore::delete(self, id)
+    }
+
+    fn exists(&self, id: StoreId) -> Result<bool> {
+        OverlayStore::exists(self, id)
+    }
+
+    fn retrieve_for_module(&self, mod_name: &str) -> Result<StoreIdIterator> {
+        OverlayStore::retrieve_for_module(self, mod_name)
+    }
+
+    fn walk(&self, mod_name: &str) -> Box<Iterator<Item = StoreObject>> {
+        Box::new(OverlayStore::walk(self, mod_name))
+    }
+
+}
@@ -17,12 +17,17 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+use std::path::PathBuf;
+
 use toml::Value;
 
 use libimagerror::into::IntoError;
 use libimagutil::iter::FoldResult;
 
 use store::Result;
+use error::MapErrInto;
+use crypt::{EncryptionConfig, KeySource};
+use ownership::SectionOwnership;
 
 /// Check whether the configuration is valid for the store
 ///
@@ -207,6 +212,203 @@ pub fn config_implicit_store_create_allowed(config: Option<&Value>) -> bool {
     }).unwrap_or(false)
 }
 
+/// A store which is mounted into another store, as configured in `store.mounts`.
+#[derive(Debug, Clone)]
+pub struct MountConfig {
+    pub path: PathBuf,
+    pub writable: bool,
+}
+
+/// Get the mounts from the store configuration.
+///
+/// Mounts are configured as an array of tables, each with a `path` and an optional `writable` key,
+/// which defaults to `false`. The order of the array is the order of precedence.
+pub fn get_mounts(value: &Option<Value>) -> Result<Vec<MountConfig>> {
+    use error::StoreErrorKind as SEK;
+
+    let mounts = match *value {
+        Some(Value::Table(ref t)) => match t.get("mounts") {
+            Some(&Value::Array(ref a)) => a,
+            Some(_) => {
+                warn!("'mounts' configuration key should contain Array, does not");
+                return Err(SEK::ConfigTypeError.into_error())
+                    .map_err_into(SEK::MountConfigError);
+            },
+            None => return Ok(vec![]),
+        },
+        _ => return Ok(vec![]),
+    };
+
+    mounts.iter()
+        .map(|mount| match *mount {
+            Value::Table(ref t) => {
+                let path = match t.get("path") {
+                    Some(&Value::String(ref s)) => PathBuf::from(s.clone()),
+                    _ => {
+                        warn!("Mount configuration needs a 'path' key with a String value");
+                        return Err(SEK::ConfigKeyMissingError.into_error())
+                            .map_err_into(SEK::MountConfigError);
+                    },
+                };
+
+                let writable = match t.get("writable") {
+                    Some(&Value::Boolean(b)) => b,
+                    Some(_) => {
+                        warn!("Key 'writable' in mount configuration does not contain a Boolean value");
+                        return Err(SEK::ConfigTypeError.into_error())
+                            .map_err_into(SEK::MountConfigError);
+                    },
+                    None => false,
+                };
+
+                Ok(MountConfig { path: path, writable: writable })
+            },
+            _ => {
+                warn!("Mount configuration is not a table");
+                Err(SEK::ConfigTypeError.into_error()).map_err_into(SEK::MountConfigError)
+            },
+        })
+        .collect()
+}
+
+/// Get the encryption configuration from the store configuration.
+///
+/// Encryption is configured in the `encryption` table, with a `modules` array of the modules
+/// whose entries are encrypted. The key is read from the file at `keyfile` or derived from the
+/// passphrase in the environment variable named by `passphrase-env`, which defaults to
+/// `IMAG_STORE_PASSPHRASE`.
+pub fn get_encryption_config(value: &Option<Value>) -> Result<Option<EncryptionConfig>> {
+    use error::StoreErrorKind as SEK;
+
+    let enc = match *value {
+        Some(Value::Table(ref t)) => match t.get("encryption") {
+            Some(&Value::Table(ref enc)) => enc,
+            Some(_) => {
+                warn!("'encryption' configuration key should contain Table, does not");
+                return Err(SEK::ConfigTypeError.into_error())
+                    .map_err_into(SEK::EncryptionConfigError);
+            },
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    let modules = match enc.get("modules") {
+        Some(&Value::Array(ref a)) => {
+            let mut modules = vec![];
+            for elem in a {
+                match *elem {
+                    Value::String(ref s) => modules.push(s.clone()),
+                    _ => {
+                        warn!("Non-String in configuration, inside 'encryption.modules'");
+                        return Err(SEK::ConfigTypeError.into_error())
+                            .map_err_into(SEK::EncryptionConfigError);
+                    },
+                }
+            }
+            modules
+        },
+        _ => {
+            warn!("Encryption configuration needs a 'modules' key with an Array of Strings");
+            return Err(SEK::ConfigKeyMissingError.into_error())
+                .map_err_into(SEK::EncryptionConfigError);
+        },
+    };
+
+    let source = match (enc.get("keyfile"), enc.get("passphrase-env")) {
+        (Some(&Value::String(ref path)), None) => KeySource::KeyFile(PathBuf::from(path.clone())),
+        (None, Some(&Value::String(ref var)))  => KeySource::PassphraseEnv(var.clone()),
+        (None, None) => KeySource::PassphraseEnv(String::from("IMAG_STORE_PASSPHRASE")),
+        _ => {
+            warn!("Encryption configuration needs either a 'keyfile' or a 'passphrase-env' String");
+            return Err(SEK::ConfigTypeError.into_error())
+                .map_err_into(SEK::EncryptionConfigError);
+        },
+    };
+
+    Ok(Some(EncryptionConfig {
+        source: source,
+        modules: modules,
+    }))
+}
+
+/// Get the header section ownership from the store configuration.
+///
+/// Ownership is configured in the `header-ownership` table. It is only enforced if `enforce` is
+/// set to `true`. The `sections` table maps section paths to the module owning them, `trusted`
+/// lists the modules and libraries which may write all sections.
+pub fn get_header_ownership(value: &Option<Value>) -> Result<Option<SectionOwnership>> {
+    use std::collections::BTreeMap;
+    use error::StoreErrorKind as SEK;
+
+    let own = match *value {
+        Some(Value::Table(ref t)) => match t.get("header-ownership") {
+            Some(&Value::Table(ref own)) => own,
+            Some(_) => {
+                warn!("'header-ownership' configuration key should contain Table, does not");
+                return Err(SEK::ConfigTypeError.into_error())
+                    .map_err_into(SEK::HeaderOwnershipConfigError);
+            },
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    match own.get("enforce") {
+        Some(&Value::Boolean(true)) => {},
+        Some(&Value::Boolean(false)) | None => return Ok(None),
+        Some(_) => {
+            warn!("Key 'enforce' in header ownership configuration does not contain a Boolean value");
+            return Err(SEK::ConfigTypeError.into_error())
+                .map_err_into(SEK::HeaderOwnershipConfigError);
+        },
+    }
+
+    let mut owners = BTreeMap::new();
+    match own.get("sections") {
+        Some(&Value::Table(ref sections)) => for (section, owner) in sections {
+            match *owner {
+                Value::String(ref owner) => {
+                    owners.insert(section.clone(), owner.clone());
+                },
+                _ => {
+                    warn!("Owner of section '{}' is not a String", section);
+                    return Err(SEK::ConfigTypeError.into_error())
+                        .map_err_into(SEK::HeaderOwnershipConfigError);
+                },
+            }
+        },
+        Some(_) => {
+            warn!("'header-ownership.sections' should contain Table, does not");
+            return Err(SEK::ConfigTypeError.into_error())
+                .map_err_into(SEK::HeaderOwnershipConfigError);
+        },
+        None => {},
+    }
+
+    let mut trusted = vec![];
+    match own.get("trusted") {
+        Some(&Value::Array(ref a)) => for elem in a {
+            match *elem {
+                Value::String(ref s) => trusted.push(s.clone()),
+                _ => {
+                    warn!("Non-String in configuration, inside 'header-ownership.trusted'");
+                    return Err(SEK::ConfigTypeError.into_error())
+                        .map_err_into(SEK::HeaderOwnershipConfigError);
+                },
+            }
+        },
+        Some(_) => {
+            warn!("'header-ownership.trusted' should contain Array, does not");
+            return Err(SEK::ConfigTypeError.into_error())
+                .map_err_into(SEK::HeaderOwnershipConfigError);
+        },
+        None => {},
+    }
+
+    Ok(Some(SectionOwnership::new(owners, trusted)))
+}
+
 pub fn get_store_unload_aspect_names(value: &Option<Value>) -> Vec<String> {
     get_aspect_names_for_aspect_position("store-unload-hook-aspects", value)
 }
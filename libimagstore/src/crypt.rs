@@ -0,0 +1,672 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Encryption of entry files at rest
+//!
+//! The entry files of the modules listed in the `encryption` section of the store configuration
+//! are encrypted with AES-256-GCM. The key is either derived from a key file or from a passphrase,
+//! which is read from an environment variable.
+//!
+//! An encrypted file consists of a magic header, the random nonce, the ciphertext and the
+//! authentication tag. The id of the entry is authenticated together with the content, so the file
+//! of one entry cannot be passed off as the file of another one. Files are decrypted by their magic
+//! header, and moved or copied entries are re-encrypted for their new id by the store.
+//!
+//! The key is loaded when an entry is encrypted or decrypted for the first time, so a store can be
+//! used for the entries of plain modules without the key being available.
+//!
+//! Only the entry files in the store are encrypted. Data which leaves the store, like archives
+//! written by `imag-store export`, is not encrypted by this module.
+
+use std::env;
+use std::fmt::{Debug, Formatter};
+use std::fmt::Error as FmtError;
+use std::fs::{File, remove_file, rename};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::result::Result as RResult;
+use std::sync::Mutex;
+
+use ring::aead::{AES_256_GCM, OpeningKey, SealingKey, open_in_place, seal_in_place};
+use ring::digest::{SHA256, digest};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use walkdir::WalkDir;
+
+use error::StoreErrorKind as SEK;
+use error::MapErrInto;
+use storeid::StoreId;
+use store::Result;
+
+use libimagerror::into::IntoError;
+
+const MAGIC         : &'static [u8] = b"IMAGCRYPT1\n";
+const NONCE_LEN     : usize = 12;
+const TAG_LEN       : usize = 16;
+const KEY_LEN       : usize = 32;
+const SALT_LEN      : usize = 16;
+const PBKDF2_ROUNDS : u32   = 100_000;
+
+/// Name of the file in the store root which contains the salt for deriving a key from a
+/// passphrase
+pub const SALT_FILE_NAME : &'static str = ".imag-crypt-salt";
+
+/// Name of the file in the store root which contains the new salt while the keys are rotated
+///
+/// It is written before any file is re-encrypted and replaces the salt file once all files are
+/// re-encrypted, so it marks a rotation which did not replace any file yet.
+pub const NEW_SALT_FILE_NAME : &'static str = ".imag-crypt-salt.new";
+
+/// Name of the directory in the store root which contains the base states of `imag-store sync`
+///
+/// The files in there are encrypted like the entries they are the base state of.
+pub const SYNC_DIR_NAME : &'static str = ".imag-sync";
+
+/// Suffix of the temporary files a file is re-encrypted into when the keys are rotated
+const ROTATED_SUFFIX : &'static str = ".imag-rotated";
+
+/// Check whether `data` is the content of an encrypted file
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// A key for encrypting and decrypting entry files
+#[derive(Clone)]
+pub struct Key {
+    key: [u8; KEY_LEN],
+}
+
+impl Debug for Key {
+
+    fn fmt(&self, fmt: &mut Formatter) -> RResult<(), FmtError> {
+        write!(fmt, "Key(..)")
+    }
+
+}
+
+impl Key {
+
+    /// Create a key from the SHA-256 of the content of a key file
+    pub fn from_keyfile(path: &Path) -> Result<Key> {
+        let mut content = vec![];
+        try!(File::open(path)
+             .and_then(|mut f| f.read_to_end(&mut content))
+             .map_err_into(SEK::EncryptionKeyError));
+
+        let mut key = [0; KEY_LEN];
+        key.copy_from_slice(digest(&SHA256, &content).as_ref());
+        Ok(Key { key: key })
+    }
+
+    /// Derive a key from a passphrase, using PBKDF2 with HMAC-SHA256
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Key {
+        let mut key = [0; KEY_LEN];
+        pbkdf2::derive(&SHA256, PBKDF2_ROUNDS, salt, passphrase.as_bytes(), &mut key);
+        Key { key: key }
+    }
+
+    /// Encrypt the content of the file of the entry `id`
+    pub fn encrypt(&self, plain: &[u8], id: &StoreId) -> Result<Vec<u8>> {
+        let key = try!(SealingKey::new(&AES_256_GCM, &self.key)
+                       .map_err(|_| SEK::EncryptionError.into_error()));
+
+        let mut nonce = [0; NONCE_LEN];
+        try!(fill_random(&mut nonce));
+
+        // The plain text is encrypted in place, the tag is appended
+        let mut data = Vec::with_capacity(MAGIC.len() + NONCE_LEN + plain.len() + TAG_LEN);
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(plain);
+        data.extend_from_slice(&[0; TAG_LEN]);
+
+        let ad  = associated_data(id);
+        let len = {
+            let in_out = &mut data[MAGIC.len() + NONCE_LEN..];
+            try!(seal_in_place(&key, &nonce, &ad, in_out, TAG_LEN)
+                 .map_err(|_| SEK::EncryptionError.into_error()))
+        };
+        data.truncate(MAGIC.len() + NONCE_LEN + len);
+        Ok(data)
+    }
+
+    /// Decrypt the content of the encrypted file of the entry `id`
+    ///
+    /// Fails with `DecryptionError` if the data is not encrypted, was encrypted with another key or
+    /// for another entry, or was tampered with.
+    pub fn decrypt(&self, data: &[u8], id: &StoreId) -> Result<Vec<u8>> {
+        if !is_encrypted(data) || data.len() < MAGIC.len() + NONCE_LEN + TAG_LEN {
+            return Err(SEK::DecryptionError.into_error());
+        }
+
+        let key = try!(OpeningKey::new(&AES_256_GCM, &self.key)
+                       .map_err(|_| SEK::DecryptionError.into_error()));
+
+        let (nonce, ciphertext) = data[MAGIC.len()..].split_at(NONCE_LEN);
+        let mut buf             = ciphertext.to_vec();
+
+        open_in_place(&key, nonce, &associated_data(id), 0, &mut buf)
+            .map(|plain| plain.to_vec())
+            .map_err(|_| SEK::DecryptionError.into_error())
+    }
+
+}
+
+/// Where the key for the encryption comes from
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    KeyFile(PathBuf),
+
+    /// The name of the environment variable which contains the passphrase
+    PassphraseEnv(String),
+}
+
+impl KeySource {
+
+    /// Load the key
+    ///
+    /// For passphrases, the salt is read from the store, or created if the store has none yet.
+    pub fn load(&self, store_path: &PathBuf) -> Result<Key> {
+        match *self {
+            KeySource::KeyFile(ref path) => Key::from_keyfile(path),
+            KeySource::PassphraseEnv(ref var) => {
+                let passphrase = match env::var(var) {
+                    Ok(p)  => p,
+                    Err(e) => {
+                        warn!("The store passphrase is read from the environment variable '{}'", var);
+                        warn!("Set it to access encrypted entries");
+                        return Err(SEK::EncryptionKeyError.into_error_with_cause(Box::new(e)));
+                    },
+                };
+                let salt = match try!(read_salt(store_path)) {
+                    Some(salt) => salt,
+                    None => {
+                        let salt = try!(generate_salt());
+                        try!(write_salt(store_path, &salt));
+                        salt
+                    },
+                };
+                Ok(Key::from_passphrase(&passphrase, &salt))
+            },
+        }
+    }
+
+}
+
+/// The encryption configuration of a store
+#[derive(Debug, Clone)]
+pub struct EncryptionConfig {
+    pub source: KeySource,
+    pub modules: Vec<String>,
+}
+
+impl EncryptionConfig {
+
+    /// Get the encryption for the store at `store_path`. The key is loaded when it is used first.
+    pub fn into_encryption(self, store_path: &PathBuf) -> Encryption {
+        Encryption {
+            source: Some((self.source, store_path.clone())),
+            key: Mutex::new(None),
+            modules: self.modules,
+        }
+    }
+
+}
+
+/// The key of a store and the modules whose entries are encrypted
+#[derive(Debug)]
+pub struct Encryption {
+    /// Where the key is loaded from, and the store path for the salt
+    source: Option<(KeySource, PathBuf)>,
+    key: Mutex<Option<Key>>,
+    modules: Vec<String>,
+}
+
+impl Encryption {
+
+    pub fn new(key: Key, modules: Vec<String>) -> Encryption {
+        Encryption {
+            source: None,
+            key: Mutex::new(Some(key)),
+            modules: modules,
+        }
+    }
+
+    /// Get the configured source of the key, if the encryption was created from the configuration
+    pub fn source(&self) -> Option<&KeySource> {
+        self.source.as_ref().map(|&(ref source, _)| source)
+    }
+
+    /// Get the key, loading it if this was not done yet
+    ///
+    /// Fails with `EncryptionKeyError` if the key cannot be loaded, for example if the environment
+    /// variable with the passphrase is not set.
+    pub fn key(&self) -> Result<Key> {
+        let mut key = try!(self.key.lock().map_err(|_| SEK::LockPoisoned.into_error()));
+
+        if key.is_none() {
+            let loaded = match self.source {
+                Some((ref source, ref store_path)) => try!(source.load(store_path)),
+                None => return Err(SEK::EncryptionKeyError.into_error()),
+            };
+            *key = Some(loaded);
+        }
+
+        // unwrap is safe, the key was loaded above
+        Ok(key.as_ref().unwrap().clone())
+    }
+
+    pub fn modules(&self) -> &Vec<String> {
+        &self.modules
+    }
+
+    /// Check whether the entry with the id `id` is encrypted when written
+    pub fn encrypts(&self, id: &StoreId) -> bool {
+        id.components()
+            .next()
+            .and_then(|c| match c {
+                Component::Normal(s) => s.to_str(),
+                _ => None,
+            })
+            .map(|module| self.modules.iter().any(|m| m == module))
+            .unwrap_or(false)
+    }
+
+}
+
+/// The data which is authenticated with the content of the file of the entry `id`
+///
+/// This is the id without the store path, so the store can be moved.
+fn associated_data(id: &StoreId) -> Vec<u8> {
+    id.local().to_string_lossy().into_owned().into_bytes()
+}
+
+fn fill_random(buf: &mut [u8]) -> Result<()> {
+    SystemRandom::new()
+        .fill(buf)
+        .map_err(|_| SEK::EncryptionError.into_error())
+}
+
+pub fn generate_salt() -> Result<Vec<u8>> {
+    let mut salt = vec![0; SALT_LEN];
+    try!(fill_random(&mut salt));
+    Ok(salt)
+}
+
+/// Read the salt of the store, if there is one
+pub fn read_salt(store_path: &PathBuf) -> Result<Option<Vec<u8>>> {
+    let mut path = store_path.clone();
+    path.push(SALT_FILE_NAME);
+
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let mut salt = vec![];
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut salt))
+        .map(|_| Some(salt))
+        .map_err_into(SEK::EncryptionKeyError)
+}
+
+pub fn write_salt(store_path: &PathBuf, salt: &[u8]) -> Result<()> {
+    let mut path = store_path.clone();
+    path.push(SALT_FILE_NAME);
+
+    File::create(path)
+        .and_then(|mut f| f.write_all(salt))
+        .map_err_into(SEK::EncryptionKeyError)
+}
+
+/// Re-write all entry files in the store at `store_path` with the encryption `new`
+///
+/// Encrypted files are decrypted with `old`. Files of modules which are encrypted with `new` are
+/// encrypted with the new key, all other files are written in plain text. This can also be used
+/// to apply a changed list of encrypted modules. The base states in `.imag-sync/` are re-written
+/// like the entries.
+///
+/// If the new key is derived from a passphrase, `new_salt` is the salt it was derived with.
+///
+/// The files are re-written into temporary files first, while the new salt is kept in
+/// `.imag-crypt-salt.new`. Only if all files are re-written, the salt file is replaced, and then
+/// the temporary files are renamed to the files. If this is interrupted, the next call detects it:
+/// If the salt was not replaced yet, the temporary files are discarded and the rotation is done
+/// again. Else the remaining temporary files are renamed and nothing is rotated, as the store is
+/// encrypted with the new key already.
+///
+/// Returns the number of re-written files. This must not be called while entries are borrowed
+/// from a `Store` at `store_path`.
+pub fn rotate_keys(store_path: &PathBuf,
+                   old: Option<&Key>,
+                   new: &Encryption,
+                   new_salt: Option<&[u8]>)
+    -> Result<usize>
+{
+    let marker  = store_file(store_path, NEW_SALT_FILE_NAME);
+    let pending = try!(rotated_files(store_path));
+
+    if marker.is_file() {
+        info!("Discarding the files of an interrupted rotation");
+        try!(discard(&pending, &marker));
+    } else if !pending.is_empty() {
+        info!("Finishing an interrupted rotation, the store is encrypted with the new key already");
+        return replace(&pending);
+    }
+
+    try!(write_file(&marker, new_salt.unwrap_or(&[])));
+
+    let mut rotated = vec![];
+    if let Err(e) = rotate_into_temp_files(store_path, old, new, &mut rotated) {
+        if let Err(e) = discard(&rotated, &marker) {
+            warn!("Could not discard the files of the failed rotation: {:?}", e);
+        }
+        return Err(e);
+    }
+
+    // The salt is replaced atomically, after that the rotation cannot be discarded anymore
+    try!(match new_salt {
+        Some(_) => rename(&marker, store_file(store_path, SALT_FILE_NAME)),
+        None    => remove_file(&marker),
+    }.map_err_into(SEK::IoError));
+
+    replace(&rotated)
+}
+
+/// Re-write the files into temporary files, `rotated` gets the temporary files and their files
+fn rotate_into_temp_files(store_path: &PathBuf,
+                          old: Option<&Key>,
+                          new: &Encryption,
+                          rotated: &mut Vec<(PathBuf, PathBuf)>)
+    -> Result<()>
+{
+    for (path, id) in try!(encrypted_files(store_path)) {
+        let mut data = vec![];
+        try!(File::open(&path)
+             .and_then(|mut f| f.read_to_end(&mut data))
+             .map_err_into(SEK::IoError));
+
+        let was_encrypted = is_encrypted(&data);
+        let plain = if was_encrypted {
+            let old = try!(old.ok_or(SEK::EncryptionKeyError.into_error()));
+            try!(old.decrypt(&data, &id))
+        } else {
+            data
+        };
+
+        let out = if new.encrypts(&id) {
+            try!(try!(new.key()).encrypt(&plain, &id))
+        } else if was_encrypted {
+            plain
+        } else {
+            continue;
+        };
+
+        debug!("Re-writing {:?}", id);
+        let temp = temp_file(&path);
+        try!(write_file(&temp, &out));
+        rotated.push((temp, path));
+    }
+
+    Ok(())
+}
+
+/// Get the files which can be encrypted, with the ids of the entries they belong to
+///
+/// These are the entry files and the base states in `.imag-sync/<store>/`, but no other hidden
+/// files.
+fn encrypted_files(store_path: &PathBuf) -> Result<Vec<(PathBuf, StoreId)>> {
+    let mut files = vec![];
+
+    for dent in WalkDir::new(store_path) {
+        let dent = try!(dent.map_err_into(SEK::IoError));
+        if !dent.file_type().is_file() {
+            continue;
+        }
+
+        let local = match dent.path().strip_prefix(store_path) {
+            Ok(local) => PathBuf::from(local),
+            Err(_)    => continue,
+        };
+
+        let local = if local.starts_with(SYNC_DIR_NAME) {
+            local.components().skip(2).collect()
+        } else {
+            local
+        };
+
+        let hidden = local.components().any(|c| match c {
+            Component::Normal(s) => s.to_str().map(|s| s.starts_with('.')).unwrap_or(true),
+            _ => false,
+        });
+        if hidden || local.components().next().is_none() {
+            continue;
+        }
+
+        files.push((dent.path().to_path_buf(), try!(StoreId::new_baseless(local))));
+    }
+
+    Ok(files)
+}
+
+/// Get the temporary files of a rotation which are left in the store, with their files
+fn rotated_files(store_path: &PathBuf) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut files = vec![];
+
+    for dent in WalkDir::new(store_path) {
+        let dent = try!(dent.map_err_into(SEK::IoError));
+        let name = match dent.file_name().to_str() {
+            Some(name) => String::from(name),
+            None       => continue,
+        };
+
+        if dent.file_type().is_file() && name.starts_with('.') && name.ends_with(ROTATED_SUFFIX) {
+            let original = &name[1..name.len() - ROTATED_SUFFIX.len()];
+            files.push((dent.path().to_path_buf(), dent.path().with_file_name(original)));
+        }
+    }
+
+    Ok(files)
+}
+
+/// The hidden temporary file next to `path`
+fn temp_file(path: &Path) -> PathBuf {
+    let name = path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(String::new);
+    path.with_file_name(format!(".{}{}", name, ROTATED_SUFFIX))
+}
+
+fn replace(rotated: &[(PathBuf, PathBuf)]) -> Result<usize> {
+    for &(ref temp, ref path) in rotated {
+        try!(rename(temp, path).map_err_into(SEK::IoError));
+    }
+    Ok(rotated.len())
+}
+
+fn discard(rotated: &[(PathBuf, PathBuf)], marker: &Path) -> Result<()> {
+    for &(ref temp, _) in rotated {
+        try!(remove_file(temp).map_err_into(SEK::IoError));
+    }
+    if marker.is_file() {
+        try!(remove_file(marker).map_err_into(SEK::IoError));
+    }
+    Ok(())
+}
+
+fn store_file(store_path: &PathBuf, name: &str) -> PathBuf {
+    let mut path = store_path.clone();
+    path.push(name);
+    path
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<()> {
+    File::create(path)
+        .and_then(|mut f| f.write_all(data).and_then(|_| f.sync_all()))
+        .map_err_into(SEK::IoError)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{File, create_dir_all};
+    use std::io::{Read, Write};
+    use std::path::PathBuf;
+
+    use tempdir::TempDir;
+
+    use storeid::StoreId;
+    use super::{Encryption, EncryptionConfig, Key, KeySource, is_encrypted, rotate_keys};
+    use super::{NEW_SALT_FILE_NAME, SALT_FILE_NAME, read_salt, temp_file};
+
+    fn key(passphrase: &str) -> Key {
+        Key::from_passphrase(passphrase, b"0123456789abcdef")
+    }
+
+    fn id(s: &str) -> StoreId {
+        StoreId::new_baseless(PathBuf::from(s)).unwrap()
+    }
+
+    fn write(path: &PathBuf, data: &[u8]) {
+        create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap().write_all(data).unwrap();
+    }
+
+    fn read(path: &PathBuf) -> Vec<u8> {
+        let mut data = vec![];
+        File::open(path).unwrap().read_to_end(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key   = key("secret");
+        let plain = b"---\n[imag]\nversion = \"0.2.0\"\n---\nHello World";
+
+        let encrypted = key.encrypt(plain, &id("diary/a")).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!is_encrypted(plain));
+        assert_eq!(key.decrypt(&encrypted, &id("diary/a")).unwrap(), plain.to_vec());
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_key_fails() {
+        let encrypted = key("secret").encrypt(b"Hello World", &id("diary/a")).unwrap();
+        assert!(key("wrong").decrypt(&encrypted, &id("diary/a")).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_for_other_id_fails() {
+        let encrypted = key("secret").encrypt(b"Hello World", &id("diary/a")).unwrap();
+        assert!(key("secret").decrypt(&encrypted, &id("diary/b")).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_tampered_fails() {
+        let key = key("secret");
+        let mut encrypted = key.encrypt(b"Hello World", &id("diary/a")).unwrap();
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        assert!(key.decrypt(&encrypted, &id("diary/a")).is_err());
+    }
+
+    #[test]
+    fn test_rotate_keys() {
+        let dir   = TempDir::new("crypt-rotate").unwrap();
+        let store = dir.path().to_path_buf();
+        let entry = store.join("diary/a");
+        let base  = store.join(".imag-sync/other/diary/a");
+        let plain = store.join("notes/b");
+
+        write(&entry, &key("old").encrypt(b"entry", &id("diary/a")).unwrap());
+        write(&base, &key("old").encrypt(b"base", &id("diary/a")).unwrap());
+        write(&plain, b"plain");
+
+        let new = Encryption::new(key("new"), vec![String::from("diary")]);
+        assert_eq!(2, rotate_keys(&store, Some(&key("old")), &new, Some(&b"salt"[..])).unwrap());
+
+        assert_eq!(b"entry".to_vec(), key("new").decrypt(&read(&entry), &id("diary/a")).unwrap());
+        assert_eq!(b"base".to_vec(), key("new").decrypt(&read(&base), &id("diary/a")).unwrap());
+        assert_eq!(b"plain".to_vec(), read(&plain));
+        assert_eq!(Some(b"salt".to_vec()), read_salt(&store).unwrap());
+        assert!(!store.join(NEW_SALT_FILE_NAME).exists());
+        assert!(!temp_file(&entry).exists());
+    }
+
+    #[test]
+    fn test_rotate_keys_discards_rotation_which_did_not_replace_files() {
+        let dir   = TempDir::new("crypt-rotate").unwrap();
+        let store = dir.path().to_path_buf();
+        let entry = store.join("diary/a");
+
+        write(&entry, &key("old").encrypt(b"entry", &id("diary/a")).unwrap());
+        write(&store.join(SALT_FILE_NAME), b"old salt");
+        write(&store.join(NEW_SALT_FILE_NAME), b"broken salt");
+        write(&temp_file(&entry), b"garbage");
+
+        let new = Encryption::new(key("new"), vec![String::from("diary")]);
+        assert_eq!(1, rotate_keys(&store, Some(&key("old")), &new, Some(&b"salt"[..])).unwrap());
+
+        assert_eq!(b"entry".to_vec(), key("new").decrypt(&read(&entry), &id("diary/a")).unwrap());
+        assert_eq!(Some(b"salt".to_vec()), read_salt(&store).unwrap());
+    }
+
+    #[test]
+    fn test_rotate_keys_finishes_rotation_which_replaced_the_salt() {
+        let dir   = TempDir::new("crypt-rotate").unwrap();
+        let store = dir.path().to_path_buf();
+        let done  = store.join("diary/a");
+        let left  = store.join("diary/b");
+
+        write(&done, &key("new").encrypt(b"done", &id("diary/a")).unwrap());
+        write(&left, &key("old").encrypt(b"left", &id("diary/b")).unwrap());
+        write(&temp_file(&left), &key("new").encrypt(b"left", &id("diary/b")).unwrap());
+
+        // The old key does not decrypt the store anymore, so nothing must be rotated again
+        let new = Encryption::new(key("new"), vec![String::from("diary")]);
+        assert_eq!(1, rotate_keys(&store, Some(&key("old")), &new, Some(&b"salt"[..])).unwrap());
+
+        assert_eq!(b"done".to_vec(), key("new").decrypt(&read(&done), &id("diary/a")).unwrap());
+        assert_eq!(b"left".to_vec(), key("new").decrypt(&read(&left), &id("diary/b")).unwrap());
+        assert!(!temp_file(&left).exists());
+    }
+
+    #[test]
+    fn test_encrypts_by_module() {
+        let enc = Encryption::new(key("secret"), vec![String::from("diary")]);
+
+        let diary = StoreId::new_baseless(PathBuf::from("diary/2016/10/01")).unwrap();
+        let notes = StoreId::new_baseless(PathBuf::from("notes/foo")).unwrap();
+
+        assert!(enc.encrypts(&diary));
+        assert!(!enc.encrypts(&notes));
+    }
+
+    #[test]
+    fn test_key_is_loaded_lazily() {
+        let config = EncryptionConfig {
+            source: KeySource::PassphraseEnv(String::from("IMAG_TEST_UNSET_PASSPHRASE")),
+            modules: vec![String::from("diary")],
+        };
+        let enc = config.into_encryption(&PathBuf::from("/nonexistent"));
+
+        let diary = StoreId::new_baseless(PathBuf::from("diary/2016/10/01")).unwrap();
+        assert!(enc.encrypts(&diary));
+        assert!(enc.key().is_err());
+    }
+
+}
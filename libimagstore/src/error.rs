@@ -77,6 +77,15 @@ generate_custom_error_types!(StoreError, StoreErrorKind, CustomErrorData,
     StoreIdLocalPartAbsoluteError => "StoreId 'id' part is absolute (starts with '/') which is not allowed",
     StoreIdBuildFromFullPathError => "Building StoreId from full file path failed",
     StoreIdHasNoBaseError   => "StoreId has no 'base' part",
+    MountConfigError        => "Store mount configuration error",
+    MountReadOnly           => "Store mount is read-only",
+    NoWritableMount         => "No writable store mount",
+    EncryptionConfigError   => "Store encryption configuration error",
+    EncryptionKeyError      => "Encryption key could not be loaded",
+    EncryptionError         => "Encryption error",
+    DecryptionError         => "Decryption failed",
+    HeaderOwnershipConfigError => "Header ownership configuration error",
+    HeaderOwnershipViolation   => "Header section owned by another module was changed",
 
     CreateCallError            => "Error when calling create()",
     RetrieveCallError          => "Error when calling retrieve()",
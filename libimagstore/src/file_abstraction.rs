@@ -17,12 +17,79 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use libimagerror::into::IntoError;
+
+use crypt::{Encryption, is_encrypted};
+use error::{MapErrInto, StoreError as SE, StoreErrorKind as SEK};
+use storeid::StoreId;
+
 pub use self::fs::FileAbstraction;
 
 // TODO:
 // This whole thing can be written better with a trait based mechanism that is embedded into the
 // store. However it would mean rewriting most things to be generic which can be a pain in the ass.
 
+/// The file of a store entry
+///
+/// Encrypted file contents are decrypted transparently, if the store has an encryption key. If
+/// `encrypt` is set, the content is encrypted when it is written. The content is encrypted for the
+/// entry `id`.
+#[derive(Debug)]
+pub struct EntryFile {
+    file: FileAbstraction,
+    id: StoreId,
+    encryption: Option<Arc<Encryption>>,
+    encrypt: bool,
+}
+
+impl EntryFile {
+
+    pub fn new(path: PathBuf, id: StoreId, encryption: Option<Arc<Encryption>>, encrypt: bool)
+        -> EntryFile
+    {
+        EntryFile {
+            file: FileAbstraction::Absent(path),
+            id: id,
+            encryption: encryption,
+            encrypt: encrypt,
+        }
+    }
+
+    pub fn get_file_content(&mut self) -> Result<Cursor<Vec<u8>>, SE> {
+        let mut buf = vec![];
+        try!(try!(self.file.get_file_content())
+             .read_to_end(&mut buf)
+             .map_err_into(SEK::IoError));
+
+        if !is_encrypted(&buf) {
+            return Ok(Cursor::new(buf));
+        }
+
+        match self.encryption {
+            Some(ref enc) => try!(enc.key()).decrypt(&buf, &self.id).map(Cursor::new),
+            None => {
+                warn!("File is encrypted, but there is no encryption configured for the store");
+                Err(SEK::EncryptionKeyError.into_error()).map_err_into(SEK::DecryptionError)
+            },
+        }
+    }
+
+    pub fn write_file_content(&mut self, buf: &[u8]) -> Result<(), SE> {
+        match self.encryption {
+            Some(ref enc) if self.encrypt => {
+                let data = try!(try!(enc.key()).encrypt(buf, &self.id));
+                self.file.write_file_content(&data)
+            },
+            _ => self.file.write_file_content(buf),
+        }
+    }
+
+}
+
 #[cfg(test)]
 mod fs {
     use error::StoreError as SE;
@@ -175,6 +242,9 @@ mod fs {
                     // access to the file to be in a different context
                     try!(f.seek(SeekFrom::Start(0))
                         .map_err_into(SEK::FileNotCreated));
+                    // The new content might be shorter than the old one, which would leave
+                    // trailing garbage and break encrypted files
+                    try!(f.set_len(0).map_err_into(SEK::FileNotWritten));
                     f.write_all(buf).map_err_into(SEK::FileNotWritten)
                 },
                 FileAbstraction::Absent(ref p) => (try!(create_file(p).map_err_into(SEK::FileNotCreated)),
@@ -207,9 +277,13 @@ mod fs {
 
 #[cfg(test)]
 mod test {
-    use super::FileAbstraction;
+    use super::{EntryFile, FileAbstraction};
     use std::io::Read;
     use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use crypt::{Encryption, Key, is_encrypted};
+    use storeid::StoreId;
 
     #[test]
     fn lazy_file() {
@@ -222,4 +296,31 @@ mod test {
         assert_eq!(bah, b"Hello World");
     }
 
+    #[test]
+    fn encrypted_file() {
+        let key = Key::from_passphrase("secret", b"0123456789abcdef");
+        let enc = Arc::new(Encryption::new(key, vec![String::from("diary")]));
+
+        let path   = PathBuf::from("/tests/diary/test2");
+        let id     = StoreId::new_baseless(PathBuf::from("diary/test2")).unwrap();
+        let mut ef = EntryFile::new(path.clone(), id.clone(), Some(enc.clone()), true);
+        ef.write_file_content(b"Hello World").unwrap();
+
+        let mut raw = Vec::new();
+        let mut lf = FileAbstraction::Absent(PathBuf::from("/tests/diary/test2"));
+        lf.get_file_content().unwrap().read_to_end(&mut raw).unwrap();
+        assert!(is_encrypted(&raw));
+
+        let mut bah = Vec::new();
+        ef.get_file_content().unwrap().read_to_end(&mut bah).unwrap();
+        assert_eq!(bah, b"Hello World");
+
+        let mut plain = EntryFile::new(path.clone(), id.clone(), None, false);
+        assert!(plain.get_file_content().is_err());
+
+        let other     = StoreId::new_baseless(PathBuf::from("diary/test3")).unwrap();
+        let mut moved = EntryFile::new(path, other, Some(enc), true);
+        assert!(moved.get_file_content().is_err());
+    }
+
 }
@@ -42,16 +42,21 @@ extern crate toml;
 extern crate semver;
 extern crate crossbeam;
 extern crate walkdir;
+extern crate ring;
 
 #[macro_use] extern crate libimagerror;
 #[macro_use] extern crate libimagutil;
 
 #[macro_use] mod util;
 
+pub mod access;
 pub mod storeid;
+pub mod crypt;
 pub mod error;
 pub mod hook;
 pub mod store;
+pub mod overlay;
+pub mod ownership;
 mod configuration;
 mod file_abstraction;
 pub mod toml_ext;
@@ -0,0 +1,500 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Overlaying several stores into one logical store
+//!
+//! An `OverlayStore` mounts several `Store` objects in an order of precedence. The first mount is
+//! the primary store, which is always writable. Further mounts can be added with a lower
+//! precedence and can be read-only, for example a shared store which is used as a base for a
+//! personal store.
+//!
+//! Ids resolve to the mount with the highest precedence which contains the entry. If an entry
+//! lives on a read-only mount and is retrieved for modification, it is copied to the first
+//! writable mount ("copy-up"), so the read-only mount is never written to. Getting an entry does
+//! not copy it up, the stores of read-only mounts refuse to write it back if it was changed.
+//!
+//! Libraries take a `&StoreAccess`, which both `OverlayStore` and `Store` implement, so they see
+//! the entries of all mounts. Operations which only make sense for a single store, like getting
+//! the store path, have to go through `OverlayStore::primary()` explicitly.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use error::StoreErrorKind as SEK;
+use error::MapErrInto;
+use storeid::{IntoStoreId, StoreId, StoreIdIterator};
+use store::{Entry, FileLockEntry, Result, Store, StoreObject, Walk};
+
+use libimagerror::into::IntoError;
+
+/// A `Store` mounted into an `OverlayStore`
+#[derive(Debug)]
+pub struct Mount {
+    store: Store,
+    writable: bool,
+}
+
+impl Mount {
+
+    /// Mount a store. If the mount is not writable, the store is made read-only.
+    pub fn new(mut store: Store, writable: bool) -> Mount {
+        store.set_read_only(!writable);
+        Mount {
+            store: store,
+            writable: writable,
+        }
+    }
+
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Get the store mutably, for example for registering hooks
+    pub fn store_mut(&mut self) -> &mut Store {
+        &mut self.store
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.writable
+    }
+
+}
+
+/// Several stores, mounted into one logical store
+#[derive(Debug)]
+pub struct OverlayStore {
+    mounts: Vec<Mount>,
+}
+
+impl OverlayStore {
+
+    /// Create a new OverlayStore with `primary` as the writable mount with the highest precedence
+    pub fn new(primary: Store) -> OverlayStore {
+        OverlayStore {
+            mounts: vec![Mount::new(primary, true)],
+        }
+    }
+
+    /// Create a new OverlayStore from the primary store, mounting the stores configured in the
+    /// `mounts` section of the configuration of the primary store.
+    ///
+    /// The mounted stores are opened with the configuration of the primary store, so the
+    /// configured hook aspects, encryption and header section ownership apply to them as well.
+    /// Hooks which are registered on the primary store programmatically have to be registered on
+    /// the mounts as well, see `OverlayStore::mounts_mut()`.
+    pub fn from_config(primary: Store) -> Result<OverlayStore> {
+        use configuration::get_mounts;
+
+        let config = primary.config().cloned();
+        let module = primary.acting_module().cloned();
+        let mounts = try!(get_mounts(&config));
+        let mut overlay = OverlayStore::new(primary);
+
+        for mount in mounts {
+            debug!("Mounting {:?} (writable: {})", mount.path, mount.writable);
+            let mut store = try!(Store::new(mount.path, config.clone())
+                                 .map_err_into(SEK::MountConfigError));
+            if let Some(ref module) = module {
+                store.set_acting_module(module.clone());
+            }
+            overlay = overlay.mount(store, mount.writable);
+        }
+
+        Ok(overlay)
+    }
+
+    /// Mount a store with a lower precedence than all currently mounted stores
+    pub fn mount(mut self, store: Store, writable: bool) -> OverlayStore {
+        self.mounts.push(Mount::new(store, writable));
+        self
+    }
+
+    /// Get the mounts, in order of precedence
+    pub fn mounts(&self) -> &Vec<Mount> {
+        &self.mounts
+    }
+
+    /// Get the mounts mutably, in order of precedence
+    pub fn mounts_mut(&mut self) -> &mut Vec<Mount> {
+        &mut self.mounts
+    }
+
+    /// Get the primary store
+    pub fn primary(&self) -> &Store {
+        // There is always the primary mount, as it is passed to OverlayStore::new()
+        self.mounts[0].store()
+    }
+
+    /// Get the primary store mutably, for example for registering hooks
+    pub fn primary_mut(&mut self) -> &mut Store {
+        &mut self.mounts[0].store
+    }
+
+    /// Find the mount with the highest precedence which contains the entry `id`
+    pub fn resolve<S: IntoStoreId>(&self, id: S) -> Result<Option<&Mount>> {
+        self.resolve_index(try!(id.into_storeid()))
+            .map(|o| o.map(|i| &self.mounts[i]))
+    }
+
+    fn resolve_index(&self, id: StoreId) -> Result<Option<usize>> {
+        for (i, mount) in self.mounts.iter().enumerate() {
+            if try!(mount.store().exists(id.clone())) {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    }
+
+    fn first_writable_index(&self) -> Result<usize> {
+        self.mounts
+            .iter()
+            .position(Mount::is_writable)
+            .ok_or(SEK::NoWritableMount.into_error())
+    }
+
+    /// Whether the entry exists on any of the mounts
+    pub fn exists<S: IntoStoreId>(&self, id: S) -> Result<bool> {
+        self.resolve_index(try!(id.into_storeid())).map(|o| o.is_some())
+    }
+
+    /// Create an entry on the first writable mount
+    ///
+    /// Fails with `EntryAlreadyExists` if the entry exists on any of the mounts.
+    pub fn create<'a, S: IntoStoreId>(&'a self, id: S) -> Result<FileLockEntry<'a>> {
+        let id = try!(id.into_storeid());
+
+        if try!(self.resolve_index(id.clone())).is_some() {
+            return Err(SEK::EntryAlreadyExists.into_error()).map_err_into(SEK::CreateCallError);
+        }
+
+        let writable = try!(self.first_writable_index().map_err_into(SEK::CreateCallError));
+        self.mounts[writable].store().create(id)
+    }
+
+    /// Retrieve an entry for modification
+    ///
+    /// If the entry does not exist on any mount, it is implicitely created on the first writable
+    /// mount. If it exists only on a read-only mount, it is copied to the first writable mount.
+    /// If a read-only mount which contains the entry has a higher precedence than all writable
+    /// mounts, the entry cannot be modified and `MountReadOnly` is returned.
+    pub fn retrieve<'a, S: IntoStoreId>(&'a self, id: S) -> Result<FileLockEntry<'a>> {
+        let id = try!(id.into_storeid());
+
+        match try!(self.resolve_index(id.clone())) {
+            Some(i) if self.mounts[i].is_writable() => self.mounts[i].store().retrieve(id),
+            Some(i) => self.copy_up(i, id),
+            None => {
+                let writable = try!(self.first_writable_index().map_err_into(SEK::RetrieveCallError));
+                self.mounts[writable].store().retrieve(id)
+            }
+        }
+    }
+
+    /// Get an entry from the mount with the highest precedence which contains it
+    ///
+    /// Entries from read-only mounts are not copied up. Writing them back fails with
+    /// `MountReadOnly` if they were changed, use `OverlayStore::retrieve()` for modifying them.
+    pub fn get<'a, S: IntoStoreId>(&'a self, id: S) -> Result<Option<FileLockEntry<'a>>> {
+        let id = try!(id.into_storeid());
+
+        match try!(self.resolve_index(id.clone()).map_err_into(SEK::GetCallError)) {
+            None    => Ok(None),
+            Some(i) => self.mounts[i].store().get(id),
+        }
+    }
+
+    /// Retrieve a copy of an entry from the mount with the highest precedence which contains it
+    pub fn retrieve_copy<S: IntoStoreId>(&self, id: S) -> Result<Entry> {
+        let id = try!(id.into_storeid());
+
+        match try!(self.resolve_index(id.clone()).map_err_into(SEK::RetrieveCopyCallError)) {
+            Some(i) => self.mounts[i].store().retrieve_copy(id),
+            None    => self.primary().retrieve_copy(id),
+        }
+    }
+
+    /// Write an entry back to the mount it was retrieved from
+    pub fn update<'a>(&'a self, entry: FileLockEntry<'a>) -> Result<()> {
+        let path = try!(entry.get_location().clone().into_pathbuf());
+
+        // Mount paths might be nested, so the most specific one is the one the entry belongs to
+        self.mounts
+            .iter()
+            .filter(|m| path.starts_with(m.store().path()))
+            .max_by_key(|m| m.store().path().components().count())
+            .ok_or(SEK::IdNotFound.into_error())
+            .and_then(|m| if m.is_writable() {
+                m.store().update(entry)
+            } else {
+                Err(SEK::MountReadOnly.into_error())
+            })
+            .map_err_into(SEK::UpdateCallError)
+    }
+
+    /// Delete an entry from the mount with the highest precedence which contains it
+    ///
+    /// Entries cannot be deleted from read-only mounts.
+    pub fn delete<S: IntoStoreId>(&self, id: S) -> Result<()> {
+        let id = try!(id.into_storeid());
+
+        match try!(self.resolve_index(id.clone()).map_err_into(SEK::DeleteCallError)) {
+            Some(i) if self.mounts[i].is_writable() => self.mounts[i].store().delete(id),
+            Some(_) => Err(SEK::MountReadOnly.into_error()).map_err_into(SEK::DeleteCallError),
+            None    => Err(SEK::FileNotFound.into_error()).map_err_into(SEK::DeleteCallError),
+        }
+    }
+
+    /// Iterate over all StoreIds for one module name on all mounts
+    ///
+    /// If an id exists on several mounts, only the one from the mount with the highest precedence
+    /// is returned.
+    pub fn retrieve_for_module(&self, mod_name: &str) -> Result<StoreIdIterator> {
+        let mut seen = HashSet::new();
+        let mut ids  = vec![];
+
+        for mount in self.mounts.iter() {
+            for id in try!(mount.store().retrieve_for_module(mod_name)) {
+                if seen.insert(id.local().clone()) {
+                    ids.push(id);
+                }
+            }
+        }
+
+        Ok(StoreIdIterator::new(Box::new(ids.into_iter())))
+    }
+
+    /// Walk the store tree for the module on all mounts
+    pub fn walk(&self, mod_name: &str) -> OverlayWalk {
+        OverlayWalk {
+            walks: self.mounts
+                .iter()
+                .map(|m| (m.store().path().clone(), m.store().walk(mod_name)))
+                .collect(),
+            seen: HashSet::new(),
+        }
+    }
+
+    fn copy_up<'a>(&'a self, from: usize, id: StoreId) -> Result<FileLockEntry<'a>> {
+        let writable = try!(self.first_writable_index().map_err_into(SEK::RetrieveCallError));
+        if writable > from {
+            debug!("Entry {:?} is shadowed by a read-only mount", id);
+            return Err(SEK::MountReadOnly.into_error()).map_err_into(SEK::RetrieveCallError);
+        }
+
+        debug!("Copying {:?} to writable mount", id);
+        let entry   = try!(self.mounts[from].store().retrieve_copy(id.clone()));
+        let mut fle = try!(self.mounts[writable].store().create(id));
+
+        *fle.get_header_mut()  = entry.get_header().clone();
+        *fle.get_content_mut() = entry.get_content().clone();
+
+        Ok(fle)
+    }
+
+}
+
+/// A `Walk` over all mounts of an `OverlayStore`
+///
+/// Objects which were already found on a mount with a higher precedence are skipped.
+pub struct OverlayWalk {
+    walks: Vec<(PathBuf, Walk)>,
+    seen: HashSet<PathBuf>,
+}
+
+impl Iterator for OverlayWalk {
+    type Item = StoreObject;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.walks.is_empty() {
+            let next = self.walks[0].1.next();
+            match next {
+                None => {
+                    self.walks.remove(0);
+                },
+                Some(obj) => {
+                    let local = match obj {
+                        StoreObject::Id(ref id) => id.local().clone(),
+                        StoreObject::Collection(ref pb) => pb
+                            .strip_prefix(&self.walks[0].0)
+                            .map(PathBuf::from)
+                            .unwrap_or_else(|_| pb.clone()),
+                    };
+
+                    if self.seen.insert(local) {
+                        return Some(obj);
+                    }
+                },
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate env_logger;
+
+    use std::path::PathBuf;
+
+    use tempdir::TempDir;
+
+    use store::Store;
+    use super::OverlayStore;
+
+    fn setup_logging() {
+        let _ = env_logger::init();
+    }
+
+    fn get_overlay(top: &TempDir, base: &TempDir, base_writable: bool) -> OverlayStore {
+        let top  = Store::new(PathBuf::from(top.path()), None).unwrap();
+        let base = Store::new(PathBuf::from(base.path()), None).unwrap();
+
+        {
+            let mut fle = base.create(PathBuf::from("test/base")).unwrap();
+            *fle.get_content_mut() = String::from("base content");
+        }
+
+        OverlayStore::new(top).mount(base, base_writable)
+    }
+
+    #[test]
+    fn test_resolve_on_base() {
+        setup_logging();
+        let (top, base) = (TempDir::new("overlay-top").unwrap(), TempDir::new("overlay-base").unwrap());
+        let overlay = get_overlay(&top, &base, false);
+
+        let mount = overlay.resolve(PathBuf::from("test/base")).unwrap();
+        assert!(mount.is_some());
+        assert!(!mount.unwrap().is_writable());
+
+        assert!(overlay.resolve(PathBuf::from("test/nonexistent")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_create_goes_to_writable_mount() {
+        setup_logging();
+        let (top, base) = (TempDir::new("overlay-top").unwrap(), TempDir::new("overlay-base").unwrap());
+        let overlay = get_overlay(&top, &base, false);
+
+        assert!(overlay.create(PathBuf::from("test/new")).is_ok());
+        assert!(overlay.primary().exists(PathBuf::from("test/new")).unwrap());
+        assert!(!overlay.mounts()[1].store().exists(PathBuf::from("test/new")).unwrap());
+    }
+
+    #[test]
+    fn test_create_existing_on_base_fails() {
+        setup_logging();
+        let (top, base) = (TempDir::new("overlay-top").unwrap(), TempDir::new("overlay-base").unwrap());
+        let overlay = get_overlay(&top, &base, false);
+
+        assert!(overlay.create(PathBuf::from("test/base")).is_err());
+    }
+
+    #[test]
+    fn test_retrieve_copies_up_from_read_only_mount() {
+        setup_logging();
+        let (top, base) = (TempDir::new("overlay-top").unwrap(), TempDir::new("overlay-base").unwrap());
+        let overlay = get_overlay(&top, &base, false);
+
+        {
+            let mut fle = overlay.retrieve(PathBuf::from("test/base")).unwrap();
+            assert_eq!(fle.get_content(), "base content");
+            *fle.get_content_mut() = String::from("changed");
+        }
+
+        assert!(overlay.primary().exists(PathBuf::from("test/base")).unwrap());
+
+        let copy = overlay.retrieve_copy(PathBuf::from("test/base")).unwrap();
+        assert_eq!(copy.get_content(), "changed");
+
+        let base_copy = overlay.mounts()[1].store().retrieve_copy(PathBuf::from("test/base"));
+        assert_eq!(base_copy.unwrap().get_content(), "base content");
+    }
+
+    #[test]
+    fn test_get_does_not_copy_up() {
+        setup_logging();
+        let (top, base) = (TempDir::new("overlay-top").unwrap(), TempDir::new("overlay-base").unwrap());
+        let overlay = get_overlay(&top, &base, false);
+
+        {
+            let fle = overlay.get(PathBuf::from("test/base")).unwrap();
+            assert!(fle.is_some());
+            assert_eq!(fle.unwrap().get_content(), "base content");
+        }
+
+        assert!(!overlay.primary().exists(PathBuf::from("test/base")).unwrap());
+    }
+
+    #[test]
+    fn test_changed_entry_from_read_only_mount_is_not_written() {
+        setup_logging();
+        let (top, base) = (TempDir::new("overlay-top").unwrap(), TempDir::new("overlay-base").unwrap());
+        let overlay = get_overlay(&top, &base, false);
+
+        {
+            let mut fle = overlay.get(PathBuf::from("test/base")).unwrap().unwrap();
+            *fle.get_content_mut() = String::from("changed");
+            assert!(overlay.update(fle).is_err());
+        }
+
+        let base_copy = overlay.mounts()[1].store().retrieve_copy(PathBuf::from("test/base"));
+        assert_eq!(base_copy.unwrap().get_content(), "base content");
+    }
+
+    #[test]
+    fn test_delete_from_read_only_mount_fails() {
+        setup_logging();
+        let (top, base) = (TempDir::new("overlay-top").unwrap(), TempDir::new("overlay-base").unwrap());
+        let overlay = get_overlay(&top, &base, false);
+
+        assert!(overlay.delete(PathBuf::from("test/base")).is_err());
+    }
+
+    #[test]
+    fn test_store_access_sees_all_mounts() {
+        use access::StoreAccess;
+        use storeid::IntoStoreId;
+
+        fn content(store: &StoreAccess, id: &str) -> Option<String> {
+            let id = PathBuf::from(id).into_storeid().unwrap();
+            store.get(id).unwrap().map(|fle| fle.get_content().clone())
+        }
+
+        setup_logging();
+        let (top, base) = (TempDir::new("overlay-top").unwrap(), TempDir::new("overlay-base").unwrap());
+        let overlay = get_overlay(&top, &base, false);
+
+        assert!(overlay.exists(PathBuf::from("test/base")).unwrap());
+        assert_eq!(Some(String::from("base content")), content(&overlay, "test/base"));
+        assert_eq!(None, content(overlay.primary(), "test/base"));
+    }
+
+    #[test]
+    fn test_delete_from_writable_mount() {
+        setup_logging();
+        let (top, base) = (TempDir::new("overlay-top").unwrap(), TempDir::new("overlay-base").unwrap());
+        let overlay = get_overlay(&top, &base, true);
+
+        assert!(overlay.delete(PathBuf::from("test/base")).is_ok());
+        assert!(overlay.resolve(PathBuf::from("test/base")).unwrap().is_none());
+    }
+
+}
@@ -0,0 +1,238 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Ownership of header sections
+//!
+//! Each module should only write to its own header section. If ownership is configured in
+//! `store.header-ownership`, the store enforces this when an entry is written back: a change to a
+//! section which is owned by another module than the one the store acts for is rejected.
+//!
+//! Some libraries maintain sections on behalf of all modules, for example libimagentrylink the
+//! `imag.links` section. These sections are fixed by the store, see `LibrarySection`. If the
+//! library maintaining a section is listed as trusted, it announces its change with
+//! `Entry::grant_section_write()` and the change is let through. Other sections cannot be granted,
+//! so no module can write a section owned by another module by claiming to be a library.
+//!
+//! A grant only holds for the next time the entry is written to the store.
+
+use std::collections::BTreeMap;
+
+use toml::Value;
+
+use toml_ext::TomlValueExt;
+
+/// A header section which is maintained by a library on behalf of all modules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibrarySection {
+    /// `imag.links`, maintained by libimagentrylink
+    Links,
+
+    /// `imag.link-relations`, maintained by libimagentrylink
+    LinkRelations,
+
+    /// `imag.content`, maintained by libimagentrylink for external links
+    ExternalLinkContent,
+
+    /// `imag.tags`, maintained by libimagentrytag
+    Tags,
+}
+
+impl LibrarySection {
+
+    /// The path of the section in the header
+    pub fn path(&self) -> &'static str {
+        match *self {
+            LibrarySection::Links               => "imag.links",
+            LibrarySection::LinkRelations       => "imag.link-relations",
+            LibrarySection::ExternalLinkContent => "imag.content",
+            LibrarySection::Tags                => "imag.tags",
+        }
+    }
+
+    /// The library which maintains the section
+    pub fn library(&self) -> &'static str {
+        match *self {
+            LibrarySection::Links               => "libimagentrylink",
+            LibrarySection::LinkRelations       => "libimagentrylink",
+            LibrarySection::ExternalLinkContent => "libimagentrylink",
+            LibrarySection::Tags                => "libimagentrytag",
+        }
+    }
+
+}
+
+/// The mapping of header sections to the modules owning them
+#[derive(Debug, Clone)]
+pub struct SectionOwnership {
+    /// Section path (for example "imag.links") -> owning module
+    owners: BTreeMap<String, String>,
+
+    /// Modules and libraries which may write all sections
+    trusted: Vec<String>,
+}
+
+impl SectionOwnership {
+
+    pub fn new(owners: BTreeMap<String, String>, trusted: Vec<String>) -> SectionOwnership {
+        SectionOwnership {
+            owners: owners,
+            trusted: trusted,
+        }
+    }
+
+    /// Get the owner of a section, if the section itself is owned.
+    pub fn owner_of(&self, section: &str) -> Option<&String> {
+        self.owners.get(section)
+    }
+
+    pub fn is_trusted(&self, name: &str) -> bool {
+        self.trusted.iter().any(|t| t == name)
+    }
+
+    /// Get the sections which were changed from `old` to `new` without permission.
+    ///
+    /// `module` is the module the store acts for, `None` if it is unknown. Unknown modules may
+    /// only change sections which are owned by nobody, or those a trusted library granted a write
+    /// for.
+    ///
+    /// Nested owned sections are compared separately, so a change in `imag.links` is not counted
+    /// as a change in `imag` if both are owned.
+    pub fn violations(&self,
+                      module: Option<&str>,
+                      old: &Value,
+                      new: &Value,
+                      grants: &[LibrarySection])
+        -> Vec<String>
+    {
+        if module.map(|m| self.is_trusted(m)).unwrap_or(false) {
+            return vec![];
+        }
+
+        self.owners
+            .iter()
+            .filter(|&(_, owner)| module.map(|m| m != owner).unwrap_or(true))
+            .filter(|&(section, _)| {
+                !grants.iter().any(|g| g.path() == *section && self.is_trusted(g.library()))
+            })
+            .filter(|&(section, _)| self.section_value(old, section) != self.section_value(new, section))
+            .map(|(section, _)| section.clone())
+            .collect()
+    }
+
+    /// Get the value of `section` in `header`, without the owned sections nested inside of it.
+    fn section_value(&self, header: &Value, section: &str) -> Option<Value> {
+        let mut value = match header.read(section) {
+            Ok(Some(v)) => v,
+            _           => return None,
+        };
+
+        let prefix = format!("{}.", section);
+        for nested in self.owners.keys().filter(|k| k.starts_with(&prefix)) {
+            let _ = value.delete(&nested[prefix.len()..]);
+        }
+
+        Some(value)
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use toml::{Parser, Value};
+
+    use super::*;
+
+    fn ownership() -> SectionOwnership {
+        let mut owners = BTreeMap::new();
+        owners.insert(String::from("imag"), String::from("store"));
+        owners.insert(String::from("imag.links"), String::from("link"));
+        owners.insert(String::from("note"), String::from("notes"));
+
+        SectionOwnership::new(owners, vec![String::from("libimagentrylink")])
+    }
+
+    fn header(s: &str) -> Value {
+        Value::Table(Parser::new(s).parse().unwrap())
+    }
+
+    #[test]
+    fn test_owner_may_change_section() {
+        let old = header("[note]\nname = \"a\"\n");
+        let new = header("[note]\nname = \"b\"\n");
+
+        assert!(ownership().violations(Some("notes"), &old, &new, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_foreign_module_may_not_change_section() {
+        let old = header("[note]\nname = \"a\"\n");
+        let new = header("[note]\nname = \"b\"\n");
+
+        let v = ownership().violations(Some("diary"), &old, &new, &[]);
+        assert_eq!(v, vec![String::from("note")]);
+    }
+
+    #[test]
+    fn test_nested_sections_are_compared_separately() {
+        let old = header("[imag]\nversion = \"0.2.0\"\n[imag.links]\ninternal = []\n");
+        let new = header("[imag]\nversion = \"0.2.0\"\n[imag.links]\ninternal = [\"a\"]\n");
+
+        let v = ownership().violations(Some("notes"), &old, &new, &[]);
+        assert_eq!(v, vec![String::from("imag.links")]);
+    }
+
+    #[test]
+    fn test_trusted_library_grant() {
+        let old = header("[imag.links]\ninternal = []\n");
+        let new = header("[imag.links]\ninternal = [\"a\"]\n");
+
+        let grant = [LibrarySection::Links];
+        assert!(ownership().violations(Some("notes"), &old, &new, &grant).is_empty());
+    }
+
+    #[test]
+    fn test_untrusted_library_grant() {
+        let old = header("[imag]\ntags = []\n");
+        let new = header("[imag]\ntags = [\"a\"]\n");
+
+        // libimagentrytag is not trusted in this configuration
+        let grant = [LibrarySection::Tags];
+        assert!(!ownership().violations(Some("notes"), &old, &new, &grant).is_empty());
+    }
+
+    #[test]
+    fn test_grant_does_not_cover_other_sections() {
+        let old = header("[note]\nname = \"a\"\n[imag.links]\ninternal = []\n");
+        let new = header("[note]\nname = \"b\"\n[imag.links]\ninternal = [\"a\"]\n");
+
+        let v = ownership().violations(Some("diary"), &old, &new, &[LibrarySection::Links]);
+        assert_eq!(v, vec![String::from("note")]);
+    }
+
+    #[test]
+    fn test_unowned_sections_are_free() {
+        let old = header("[diary]\nyear = 2016\n");
+        let new = header("[diary]\nyear = 2017\n");
+
+        assert!(ownership().violations(None, &old, &new, &[]).is_empty());
+    }
+
+}
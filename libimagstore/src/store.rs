@@ -42,7 +42,9 @@ use walkdir::Iter as WalkDirIter;
 use error::{StoreError as SE, StoreErrorKind as SEK};
 use error::MapErrInto;
 use storeid::{IntoStoreId, StoreId, StoreIdIterator};
-use file_abstraction::FileAbstraction;
+use file_abstraction::{EntryFile, FileAbstraction};
+use crypt::Encryption;
+use ownership::{LibrarySection, SectionOwnership};
 use toml_ext::*;
 
 use hook::aspect::Aspect;
@@ -75,7 +77,7 @@ enum StoreEntryStatus {
 #[derive(Debug)]
 struct StoreEntry {
     id: StoreId,
-    file: FileAbstraction,
+    file: EntryFile,
     status: StoreEntryStatus,
 }
 
@@ -119,7 +121,7 @@ impl Iterator for Walk {
                                 return Some(StoreObject::Collection(next.path().to_path_buf()))
                             } else if next.file_type().is_file() {
                                 let n   = next.path().to_path_buf();
-                                let sid = match StoreId::new(Some(self.store_path.clone()), n) {
+                                let sid = match StoreId::from_full_path(&self.store_path, n) {
                                     Err(e) => {
                                         trace_error(&e);
                                         continue;
@@ -143,11 +145,12 @@ impl Iterator for Walk {
 
 impl StoreEntry {
 
-    fn new(id: StoreId) -> Result<StoreEntry> {
-        let pb = try!(id.clone().into_pathbuf());
+    fn new(id: StoreId, encryption: Option<Arc<Encryption>>) -> Result<StoreEntry> {
+        let pb      = try!(id.clone().into_pathbuf());
+        let encrypt = encryption.as_ref().map(|e| e.encrypts(&id)).unwrap_or(false);
         Ok(StoreEntry {
             id: id,
-            file: FileAbstraction::Absent(pb),
+            file: EntryFile::new(pb, id.clone(), encryption, encrypt),
             status: StoreEntryStatus::Present,
         })
     }
@@ -195,6 +198,26 @@ pub struct Store {
     ///
     configuration: Option<Value>,
 
+    ///
+    /// Encryption of entry files, if configured
+    ///
+    encryption: Option<Arc<Encryption>>,
+
+    ///
+    /// Ownership of header sections, if it is enforced
+    ///
+    ownership: Option<SectionOwnership>,
+
+    ///
+    /// The module this store is used by, for checking the header section ownership
+    ///
+    acting_module: Option<String>,
+
+    ///
+    /// Whether the store is read-only, for example because it is mounted read-only
+    ///
+    read_only: bool,
+
     //
     // Registered hooks
     //
@@ -273,6 +296,13 @@ impl Store {
             return Err(SEK::StorePathExists.into_error());
         }
 
+        let encryption = match try!(get_encryption_config(&store_config)) {
+            Some(cfg) => Some(Arc::new(cfg.into_encryption(&location))),
+            None      => None,
+        };
+
+        let ownership = try!(get_header_ownership(&store_config));
+
         let store_unload_aspects = get_store_unload_aspect_names(&store_config)
             .into_iter().map(|n| {
                 let cfg = AspectConfig::get_for(&store_config, n.clone());
@@ -342,6 +372,10 @@ impl Store {
         let store = Store {
             location: location.clone(),
             configuration: store_config,
+            encryption: encryption,
+            ownership: ownership,
+            acting_module: None,
+            read_only: false,
 
             store_unload_aspects  : Arc::new(Mutex::new(store_unload_aspects)),
 
@@ -371,6 +405,37 @@ impl Store {
         self.configuration.as_ref()
     }
 
+    /// Get the encryption of the store, if it is configured
+    pub fn encryption(&self) -> Option<&Encryption> {
+        self.encryption.as_ref().map(|e| &**e)
+    }
+
+    /// Set the module this store is used by.
+    ///
+    /// If header section ownership is enforced, the module may only change the header sections
+    /// it owns. If no module is set, only sections without an owner may be changed.
+    pub fn set_acting_module<S: Into<String>>(&mut self, module: S) {
+        self.acting_module = Some(module.into());
+    }
+
+    /// Get the module this store is used by, if it was set
+    pub fn acting_module(&self) -> Option<&String> {
+        self.acting_module.as_ref()
+    }
+
+    /// Mark the store as read-only.
+    ///
+    /// Entries of a read-only store can be retrieved, but creating, deleting or moving entries
+    /// fails with `MountReadOnly`, as does writing back an entry which was changed.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Check whether the store is read-only
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
     /// Verify the store.
     ///
     /// This function is not intended to be called by normal programs but only by `imag-store`.
@@ -449,6 +514,10 @@ impl Store {
     ///
     pub fn create<'a, S: IntoStoreId>(&'a self, id: S) -> Result<FileLockEntry<'a>> {
         let id = try!(id.into_storeid()).with_base(self.path().clone());
+        if self.read_only {
+            return Err(SEK::MountReadOnly.into_error()).map_err_into(SEK::CreateCallError);
+        }
+
         if let Err(e) = self.execute_hooks_for_id(self.pre_create_aspects.clone(), &id) {
             return Err(e)
                 .map_err_into(SEK::PreHookExecuteError)
@@ -466,7 +535,7 @@ impl Store {
                 return Err(SEK::EntryAlreadyExists.into_error()).map_err_into(SEK::CreateCallError);
             }
             hsmap.insert(id.clone(), {
-                let mut se = try!(StoreEntry::new(id.clone()));
+                let mut se = try!(StoreEntry::new(id.clone(), self.encryption.clone()));
                 se.status = StoreEntryStatus::Borrowed;
                 se
             });
@@ -517,7 +586,7 @@ impl Store {
                 .write()
                 .map_err(|_| SE::new(SEK::LockPoisoned, None))
                 .and_then(|mut es| {
-                    let new_se = try!(StoreEntry::new(id.clone()));
+                    let new_se = try!(StoreEntry::new(id.clone(), self.encryption.clone()));
                     let mut se = es.entry(id.clone()).or_insert(new_se);
                     let entry = se.get_entry();
                     se.status = StoreEntryStatus::Borrowed;
@@ -552,14 +621,9 @@ impl Store {
     pub fn get<'a, S: IntoStoreId + Clone>(&'a self, id: S) -> Result<Option<FileLockEntry<'a>>> {
         let id = try!(id.into_storeid()).with_base(self.path().clone());
 
-        let exists = try!(self.entries
-            .read()
-            .map(|map| map.contains_key(&id))
-            .map_err(|_| SE::new(SEK::LockPoisoned, None))
-            .map_err_into(SEK::GetCallError)
-        );
+        let exists = try!(self.exists(id.clone()).map_err_into(SEK::GetCallError));
 
-        if !exists && !id.exists() {
+        if !exists {
             debug!("Does not exist in internal cache or filesystem: {:?}", id);
             return Ok(None);
         }
@@ -567,6 +631,28 @@ impl Store {
         self.retrieve(id).map(Some).map_err_into(SEK::GetCallError)
     }
 
+    /// Check whether an entry exists, either in the internal cache or on the filesystem.
+    ///
+    /// No hooks are executed and the entry is not loaded.
+    ///
+    /// # Return value
+    ///
+    /// On success: Whether the entry exists
+    ///
+    /// On error:
+    ///  - Errors StoreId::into_storeid() might return
+    ///  - LockPoisoned() if the internal lock is poisened.
+    ///
+    pub fn exists<S: IntoStoreId>(&self, id: S) -> Result<bool> {
+        let id = try!(id.into_storeid()).with_base(self.path().clone());
+
+        self.entries
+            .read()
+            .map(|map| map.contains_key(&id))
+            .map_err(|_| SE::new(SEK::LockPoisoned, None))
+            .map(|in_cache| in_cache || id.exists())
+    }
+
     /// Iterate over all StoreIds for one module name
     ///
     /// # Returns
@@ -634,9 +720,15 @@ impl Store {
     ///  - UpdateCallError(LockPoisoned()) if the internal write lock cannot be aquierd.
     ///  - IdNotFound() if the entry was not found in the stor
     ///  - Errors Entry::verify() might return
+    ///  - HeaderOwnershipViolation() if a header section owned by another module was changed
+    ///  - MountReadOnly() if the store is read-only and the entry was changed
     ///  - Errors StoreEntry::write_entry() might return
     ///
     fn _update<'a>(&'a self, mut entry: &mut FileLockEntry<'a>, modify_presence: bool) -> Result<()> {
+        if self.read_only {
+            return self.release_read_only(entry, modify_presence);
+        }
+
         let _ = try!(self.execute_hooks_for_mut_file(self.pre_update_aspects.clone(), &mut entry)
             .map_err_into(SEK::PreHookExecuteError)
             .map_err_into(SEK::HookExecutionError)
@@ -655,6 +747,34 @@ impl Store {
         debug!("Verifying Entry");
         try!(entry.entry.verify());
 
+        if let Some(ref ownership) = self.ownership {
+            debug!("Checking header section ownership");
+            let old_header = match se.file
+                .get_file_content()
+                .and_then(|mut file| Entry::from_reader(entry.location.clone(), &mut file))
+            {
+                Ok(old)  => old.header,
+                Err(e) => if e.err_type() == SEK::FileNotFound {
+                    Entry::default_header()
+                } else {
+                    return Err(e);
+                },
+            };
+
+            // Grants hold for one write only
+            let grants     = ::std::mem::replace(&mut entry.entry.section_grants, vec![]);
+            let module     = self.acting_module.as_ref().map(|m| &m[..]);
+            let violations = ownership.violations(module,
+                                                  &old_header,
+                                                  &entry.entry.header,
+                                                  &grants);
+            if !violations.is_empty() {
+                warn!("Module {:?} changed header sections it does not own in {:?}: {:?}",
+                      module, entry.location, violations);
+                return Err(SEK::HeaderOwnershipViolation.into_error());
+            }
+        }
+
         debug!("Writing Entry");
         try!(se.write_entry(&entry.entry));
         if modify_presence {
@@ -667,6 +787,42 @@ impl Store {
             .map_err_into(SEK::UpdateCallError)
     }
 
+    /// Release an entry of a read-only store without writing it
+    ///
+    /// No hooks are executed, as nothing is written. If the entry was changed, `MountReadOnly` is
+    /// returned.
+    fn release_read_only(&self, entry: &FileLockEntry, modify_presence: bool) -> Result<()> {
+        let mut hsmap = match self.entries.write() {
+            Err(_) => return Err(SE::new(SEK::LockPoisoned, None)),
+            Ok(e) => e,
+        };
+
+        let mut se = try!(hsmap.get_mut(&entry.location).ok_or(SE::new(SEK::IdNotFound, None)));
+
+        let on_disk = match se.file
+            .get_file_content()
+            .and_then(|mut file| Entry::from_reader(entry.location.clone(), &mut file))
+        {
+            Ok(old)  => old,
+            Err(e) => if e.err_type() == SEK::FileNotFound {
+                Entry::new(entry.location.clone())
+            } else {
+                return Err(e);
+            },
+        };
+
+        if modify_presence {
+            se.status = StoreEntryStatus::Present;
+        }
+
+        if on_disk.header == entry.entry.header && on_disk.content == entry.entry.content {
+            Ok(())
+        } else {
+            warn!("Entry {:?} was changed, but its store is read-only", entry.location);
+            Err(SEK::MountReadOnly.into_error())
+        }
+    }
+
     /// Retrieve a copy of a given entry, this cannot be used to mutate
     /// the one on disk
     ///
@@ -700,7 +856,7 @@ impl Store {
             return Err(SE::new(SEK::IdLocked, None)).map_err_into(SEK::RetrieveCopyCallError);
         }
 
-        try!(StoreEntry::new(id)).get_entry()
+        try!(StoreEntry::new(id, self.encryption.clone())).get_entry()
     }
 
     /// Delete an entry
@@ -725,6 +881,10 @@ impl Store {
     ///
     pub fn delete<S: IntoStoreId>(&self, id: S) -> Result<()> {
         let id = try!(id.into_storeid()).with_base(self.path().clone());
+        if self.read_only {
+            return Err(SEK::MountReadOnly.into_error()).map_err_into(SEK::DeleteCallError);
+        }
+
         if let Err(e) = self.execute_hooks_for_id(self.pre_delete_aspects.clone(), &id) {
             return Err(e)
                 .map_err_into(SEK::PreHookExecuteError)
@@ -776,6 +936,8 @@ impl Store {
     /// Removes the original entry
     /// Executes the post_move_aspects for the new id
     ///
+    /// Links to the entry are not rewritten, see `libimagentrylink::relink::save_as()` for that.
+    ///
     /// TODO: Introduce new aspect for `save_as()`.
     pub fn save_as(&self, entry: FileLockEntry, new_id: StoreId) -> Result<()> {
         self.save_to_other_location(&entry, new_id, true)
@@ -784,6 +946,10 @@ impl Store {
     fn save_to_other_location(&self, entry: &FileLockEntry, new_id: StoreId, remove_old: bool)
         -> Result<()>
     {
+        if self.read_only {
+            return Err(SEK::MountReadOnly.into_error()).map_err_into(SEK::MoveCallError);
+        }
+
         let new_id = new_id.with_base(self.path().clone());
         let hsmap = try!(
             self.entries
@@ -799,8 +965,7 @@ impl Store {
         let old_id = entry.get_location().clone();
 
         let old_id_as_path = try!(old_id.clone().with_base(self.path().clone()).into_pathbuf());
-        let new_id_as_path = try!(new_id.clone().with_base(self.path().clone()).into_pathbuf());
-        FileAbstraction::copy(&old_id_as_path, &new_id_as_path)
+        self.copy_file(&old_id, &new_id)
             .and_then(|_| {
                 if remove_old {
                     FileAbstraction::remove_file(&old_id_as_path)
@@ -815,6 +980,29 @@ impl Store {
             .map_err_into(SEK::MoveCallError)
     }
 
+    /// Copy the file of the entry `old_id` to the file of `new_id`
+    ///
+    /// If the store has an encryption, the file is re-written, as encrypted files are only valid
+    /// for the id they were encrypted for. The file of `new_id` is encrypted if its module is
+    /// encrypted.
+    fn copy_file(&self, old_id: &StoreId, new_id: &StoreId) -> Result<()> {
+        let old_path = try!(old_id.clone().with_base(self.path().clone()).into_pathbuf());
+        let new_path = try!(new_id.clone().with_base(self.path().clone()).into_pathbuf());
+
+        let encryption = match self.encryption {
+            Some(ref enc) => enc.clone(),
+            None          => return FileAbstraction::copy(&old_path, &new_path),
+        };
+
+        let content = try!(EntryFile::new(old_path, old_id.clone(), Some(encryption.clone()), false)
+                           .get_file_content())
+            .into_inner();
+
+        let encrypt = encryption.encrypts(new_id);
+        EntryFile::new(new_path, new_id.clone(), Some(encryption), encrypt)
+            .write_file_content(&content)
+    }
+
     /// Move an entry without loading
     ///
     /// This function moves an entry from one path to another.
@@ -851,10 +1039,16 @@ impl Store {
     ///
     /// So the link is _partly dangling_, so to say.
     ///
+    /// `libimagentrylink::relink::move_by_id()` moves an entry and rewrites the links to it.
+    ///
     pub fn move_by_id(&self, old_id: StoreId, new_id: StoreId) -> Result<()> {
         let new_id = new_id.with_base(self.path().clone());
         let old_id = old_id.with_base(self.path().clone());
 
+        if self.read_only {
+            return Err(SEK::MountReadOnly.into_error()).map_err_into(SEK::MoveByIdCallError);
+        }
+
         if let Err(e) = self.execute_hooks_for_id(self.pre_move_aspects.clone(), &old_id) {
             return Err(e)
                 .map_err_into(SEK::PreHookExecuteError)
@@ -882,19 +1076,27 @@ impl Store {
             let old_id_pb = try!(old_id.clone().with_base(self.path().clone()).into_pathbuf());
             let new_id_pb = try!(new_id.clone().with_base(self.path().clone()).into_pathbuf());
 
-            match FileAbstraction::rename(&old_id_pb, &new_id_pb) {
+            // Encrypted files have to be re-encrypted for the new id
+            let res = if self.encryption.is_some() {
+                self.copy_file(&old_id, &new_id)
+                    .and_then(|_| FileAbstraction::remove_file(&old_id_pb))
+            } else {
+                FileAbstraction::rename(&old_id_pb, &new_id_pb)
+            };
+
+            match res {
                 Err(e) => return Err(SEK::EntryRenameError.into_error_with_cause(Box::new(e))),
                 Ok(_) => {
                     debug!("Rename worked on filesystem");
 
-                    // assert enforced through check hsmap.contains_key(&new_id) above.
-                    // Should therefor never fail
-                    assert!(hsmap
-                            .remove(&old_id)
-                            .and_then(|mut entry| {
-                                entry.id = new_id.clone();
-                                hsmap.insert(new_id.clone(), entry)
-                            }).is_none())
+                    // The file of the entry is the one of the new id now
+                    if hsmap.remove(&old_id).is_some() {
+                        let entry = try!(StoreEntry::new(new_id.clone(), self.encryption.clone()));
+
+                        // assert enforced through check hsmap.contains_key(&new_id) above.
+                        // Should therefor never fail
+                        assert!(hsmap.insert(new_id.clone(), entry).is_none());
+                    }
                 }
             }
 
@@ -1042,6 +1244,10 @@ impl Debug for Store {
         try!(write!(fmt, "\n"));
         try!(write!(fmt, " - location               : {:?}\n", self.location));
         try!(write!(fmt, " - configuration          : {:?}\n", self.configuration));
+        try!(write!(fmt, " - encryption             : {:?}\n", self.encryption));
+        try!(write!(fmt, " - ownership              : {:?}\n", self.ownership));
+        try!(write!(fmt, " - acting_module          : {:?}\n", self.acting_module));
+        try!(write!(fmt, " - read_only              : {:?}\n", self.read_only));
         try!(write!(fmt, " - pre_create_aspects     : {:?}\n", self.pre_create_aspects    ));
         try!(write!(fmt, " - post_create_aspects    : {:?}\n", self.post_create_aspects   ));
         try!(write!(fmt, " - pre_retrieve_aspects   : {:?}\n", self.pre_retrieve_aspects  ));
@@ -1168,6 +1374,9 @@ pub struct Entry {
     location: StoreId,
     header: Value,
     content: EntryContent,
+
+    /// Header sections trusted libraries announced to have changed, not part of the entry itself
+    section_grants: Vec<LibrarySection>,
 }
 
 impl Entry {
@@ -1180,7 +1389,8 @@ impl Entry {
         Entry {
             location: loc,
             header: Entry::default_header(),
-            content: EntryContent::new()
+            content: EntryContent::new(),
+            section_grants: vec![],
         }
     }
 
@@ -1243,6 +1453,7 @@ impl Entry {
             location: try!(loc.into_storeid()),
             header: try!(Value::parse(header.as_str())),
             content: String::from(content),
+            section_grants: vec![],
         })
     }
 
@@ -1281,6 +1492,19 @@ impl Entry {
         &mut self.content
     }
 
+    /// Announce that the library maintaining `section` changes it.
+    ///
+    /// This is meant for libraries which maintain a header section on behalf of all modules, like
+    /// libimagentrylink does for `imag.links`. If the library is trusted in the header ownership
+    /// configuration, the change is accepted even though the section is owned by another module.
+    ///
+    /// The grant only holds for the next time the entry is written to the store.
+    pub fn grant_section_write(&mut self, section: LibrarySection) {
+        if !self.section_grants.contains(&section) {
+            self.section_grants.push(section);
+        }
+    }
+
     /// Verify the entry.
     ///
     /// Currently, this only verifies the header. This might change in the future.
@@ -1535,6 +1759,103 @@ mod store_tests {
         Store::new(PathBuf::from("/"), None).unwrap()
     }
 
+    fn get_store_with_ownership(module: &str) -> Store {
+        use std::collections::BTreeMap;
+        use ownership::SectionOwnership;
+
+        let mut owners = BTreeMap::new();
+        owners.insert(String::from("note"), String::from("notes"));
+        owners.insert(String::from("imag.tags"), String::from("tag"));
+
+        let mut store = get_store();
+        store.ownership = Some(SectionOwnership::new(owners, vec![String::from("libimagentrytag")]));
+        store.set_acting_module(module);
+        store
+    }
+
+    fn set_note_section(entry: &mut super::Entry) {
+        use std::collections::BTreeMap;
+        use toml::Value;
+
+        let mut note = BTreeMap::new();
+        note.insert(String::from("name"), Value::String(String::from("foo")));
+
+        match *entry.get_header_mut() {
+            Value::Table(ref mut t) => { t.insert(String::from("note"), Value::Table(note)); },
+            _ => panic!("Header is not a table"),
+        }
+    }
+
+    fn set_tags(entry: &mut super::Entry, tag: &str) {
+        use toml::Value;
+
+        match *entry.get_header_mut() {
+            Value::Table(ref mut t) => match t.get_mut("imag") {
+                Some(&mut Value::Table(ref mut imag)) => {
+                    let tags = vec![Value::String(String::from(tag))];
+                    imag.insert(String::from("tags"), Value::Array(tags));
+                },
+                _ => panic!("Header has no imag section"),
+            },
+            _ => panic!("Header is not a table"),
+        }
+    }
+
+    #[test]
+    fn test_update_library_section_granted_once() {
+        use storeid::StoreId;
+        use ownership::LibrarySection;
+
+        let store   = get_store_with_ownership("diary");
+        let id      = StoreId::new_baseless(PathBuf::from("test_update_granted_once")).unwrap();
+        let mut fle = store.create(id).unwrap();
+
+        set_tags(&mut fle, "a");
+        fle.grant_section_write(LibrarySection::Tags);
+        assert!(store._update(&mut fle, false).is_ok());
+
+        set_tags(&mut fle, "b");
+        assert!(store._update(&mut fle, false).is_err());
+    }
+
+    #[test]
+    fn test_update_owned_section_by_owner() {
+        use storeid::StoreId;
+
+        let store   = get_store_with_ownership("notes");
+        let id      = StoreId::new_baseless(PathBuf::from("test_update_owned_by_owner")).unwrap();
+        let mut fle = store.create(id).unwrap();
+
+        set_note_section(&mut fle);
+        assert!(store.update(fle).is_ok());
+    }
+
+    #[test]
+    fn test_update_owned_section_by_other_module() {
+        use storeid::StoreId;
+
+        let store   = get_store_with_ownership("diary");
+        let id      = StoreId::new_baseless(PathBuf::from("test_update_owned_by_other")).unwrap();
+        let mut fle = store.create(id).unwrap();
+
+        set_note_section(&mut fle);
+        assert!(store.update(fle).is_err());
+    }
+
+    #[test]
+    fn test_update_owned_section_cannot_be_granted() {
+        use storeid::StoreId;
+        use ownership::LibrarySection;
+
+        let store   = get_store_with_ownership("diary");
+        let id      = StoreId::new_baseless(PathBuf::from("test_update_owned_not_granted")).unwrap();
+        let mut fle = store.create(id).unwrap();
+
+        set_note_section(&mut fle);
+        fle.grant_section_write(LibrarySection::Tags);
+        assert!(store.update(fle).is_err());
+    }
+
     #[test]
     fn test_store_instantiation() {
         let store = get_store();
@@ -1773,6 +2094,70 @@ mod store_tests {
         }
     }
 
+    #[test]
+    fn test_store_move_reencrypts_for_new_id() {
+        use std::io::Read;
+        use std::sync::Arc;
+
+        use crypt::{Encryption, Key, is_encrypted};
+        use file_abstraction::FileAbstraction;
+        use storeid::StoreId;
+
+        let mut store = get_store();
+        let key       = Key::from_passphrase("secret", b"0123456789abcdef");
+        store.encryption = Some(Arc::new(Encryption::new(key, vec![String::from("cryptmove")])));
+
+        let id        = StoreId::new_baseless(PathBuf::from("cryptmove/a")).unwrap();
+        let id_moved  = StoreId::new_baseless(PathBuf::from("cryptmove/b")).unwrap();
+        let id_plain  = StoreId::new_baseless(PathBuf::from("plainmove/c")).unwrap();
+
+        {
+            let mut entry = store.create(id.clone()).unwrap();
+            *entry.get_content_mut() = String::from("secret content");
+        }
+
+        store.move_by_id(id.clone(), id_moved.clone()).unwrap();
+        assert_eq!("secret content", store.retrieve_copy(id_moved.clone()).unwrap().get_content());
+
+        store.move_by_id(id_moved.clone(), id_plain.clone()).unwrap();
+        assert_eq!("secret content", store.retrieve_copy(id_plain.clone()).unwrap().get_content());
+
+        let path    = id_plain.with_base(store.path().clone()).into_pathbuf().unwrap();
+        let mut raw = vec![];
+        FileAbstraction::Absent(path).get_file_content().unwrap().read_to_end(&mut raw).unwrap();
+        assert!(!is_encrypted(&raw));
+    }
+
+    #[test]
+    fn test_walk_yields_ids_relative_to_store() {
+        use std::fs::{File, create_dir_all};
+
+        use tempdir::TempDir;
+
+        use super::StoreObject;
+
+        // The walker works on the filesystem, not on the in-memory files of the test store
+        let dir = TempDir::new("store-walk").unwrap();
+        create_dir_all(dir.path().join("walk/sub")).unwrap();
+        File::create(dir.path().join("walk/a")).unwrap();
+        File::create(dir.path().join("walk/sub/b")).unwrap();
+
+        let store   = Store::new(PathBuf::from(dir.path()), None).unwrap();
+        let mut ids = store
+            .walk("walk")
+            .filter_map(|obj| match obj {
+                StoreObject::Id(id)        => Some(id),
+                StoreObject::Collection(_) => None,
+            })
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        assert_eq!(2, ids.len());
+        assert_eq!(&PathBuf::from("walk/a"), ids[0].local());
+        assert_eq!(&PathBuf::from("walk/sub/b"), ids[1].local());
+        assert!(ids.iter().all(|id| id.exists()));
+    }
+
 }
 
 #[cfg(test)]
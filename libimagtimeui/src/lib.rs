@@ -41,5 +41,6 @@ pub mod cli;
 pub mod date;
 pub mod datetime;
 pub mod parse;
+pub mod relative;
 pub mod time;
 
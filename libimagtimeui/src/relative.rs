@@ -0,0 +1,189 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Points in time and time ranges relative to the current time
+//!
+//! Besides the absolute format (see `ui::time_ui_fmtstr()`), the following forms are understood.
+//!
+//! Points in time:
+//!
+//! * `now`, `today`, `yesterday`, `tomorrow` (the latter three at midnight)
+//! * `N minutes ago`, `N hours ago`, `N days ago`, `N weeks ago`
+//!
+//! Ranges:
+//!
+//! * `today`, `yesterday`, `this week`
+//! * `last N minutes`, `last N hours`, `last N days`, `last N weeks`
+//! * `A..B` with two points in time
+//!
+//! All functions take the current time as argument, so they are independent from the clock.
+
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration};
+use chrono::naive::date::NaiveDate;
+use chrono::naive::datetime::NaiveDateTime;
+
+use date::Date;
+use datetime::DateTime;
+use parse::Parse;
+
+/// Parse an absolute or relative point in time
+pub fn parse_point(s: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let s = s.trim();
+    let midnight = now.date().and_hms(0, 0, 0);
+
+    match s {
+        "now"       => return Some(now),
+        "today"     => return Some(midnight),
+        "yesterday" => return Some(midnight - Duration::days(1)),
+        "tomorrow"  => return Some(midnight + Duration::days(1)),
+        _           => {},
+    }
+
+    if let Some(dt) = parse_absolute(s) {
+        return Some(dt);
+    }
+
+    let words = s.split_whitespace().collect::<Vec<_>>();
+    if words.len() == 3 && words[2] == "ago" {
+        duration(words[0], words[1]).and_then(|d| now.checked_sub(d))
+    } else {
+        None
+    }
+}
+
+/// Parse an absolute or relative range of time, as start and end
+pub fn parse_range(s: &str, now: NaiveDateTime) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let s = s.trim();
+    let midnight = now.date().and_hms(0, 0, 0);
+
+    match s {
+        "today"     => return Some((midnight, midnight + Duration::days(1))),
+        "yesterday" => return Some((midnight - Duration::days(1), midnight)),
+        "this week" => {
+            let monday = midnight - Duration::days(now.weekday().num_days_from_monday() as i64);
+            return Some((monday, monday + Duration::weeks(1)));
+        },
+        _ => {},
+    }
+
+    if let Some(i) = s.find("..") {
+        let start = parse_point(&s[..i], now);
+        let end   = parse_point(&s[i + 2..], now);
+        return start.and_then(|start| end.map(|end| (start, end)));
+    }
+
+    let words = s.split_whitespace().collect::<Vec<_>>();
+    if words.len() == 3 && words[0] == "last" {
+        duration(words[1], words[2])
+            .and_then(|d| now.checked_sub(d))
+            .map(|start| (start, now))
+    } else {
+        None
+    }
+}
+
+/// Parse an absolute point in time, the time is optional
+///
+/// Returns `None` for dates and times which do not exist, like the 30th of February.
+pub fn parse_absolute(s: &str) -> Option<NaiveDateTime> {
+    let date = match Date::parse(s) {
+        Some(d) => d,
+        None    => return None,
+    };
+    let date = match NaiveDate::from_ymd_opt(date.year(), date.month(), date.day()) {
+        Some(d) => d,
+        None    => return None,
+    };
+
+    match DateTime::parse(s) {
+        Some(dt) => date.and_hms_opt(dt.time().hour(), dt.time().minute(), dt.time().second()),
+        None     => date.and_hms_opt(0, 0, 0),
+    }
+}
+
+/// Parse a duration, like "3 days"
+///
+/// Returns `None` for durations which do not fit into a `Duration`.
+fn duration(n: &str, unit: &str) -> Option<Duration> {
+    let n = match i64::from_str(n) {
+        Ok(n) if n >= 0 => n,
+        _ => return None,
+    };
+
+    let seconds = match unit {
+        "minute" | "minutes" => 60,
+        "hour" | "hours"     => 60 * 60,
+        "day" | "days"       => 24 * 60 * 60,
+        "week" | "weeks"     => 7 * 24 * 60 * 60,
+        _                    => return None,
+    };
+
+    // The constructors of Duration panic on overflow, Duration::milliseconds() cannot overflow
+    n.checked_mul(seconds)
+        .and_then(|s| s.checked_mul(1000))
+        .map(Duration::milliseconds)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::naive::date::NaiveDate;
+    use chrono::naive::datetime::NaiveDateTime;
+
+    use super::{parse_point, parse_range};
+
+    fn now() -> NaiveDateTime {
+        // a thursday
+        NaiveDate::from_ymd(2016, 10, 13).and_hms(15, 30, 0)
+    }
+
+    fn dt(y: i32, m: u32, d: u32, h: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(y, m, d).and_hms(h, 0, 0)
+    }
+
+    #[test]
+    fn test_points() {
+        assert_eq!(parse_point("today", now()), Some(dt(2016, 10, 13, 0)));
+        assert_eq!(parse_point("yesterday", now()), Some(dt(2016, 10, 12, 0)));
+        assert_eq!(parse_point("2 days ago", now()), Some(NaiveDate::from_ymd(2016, 10, 11).and_hms(15, 30, 0)));
+        assert_eq!(parse_point("2016-01-02", now()), Some(dt(2016, 1, 2, 0)));
+        assert_eq!(parse_point("2016-01-02T10", now()), Some(dt(2016, 1, 2, 10)));
+        assert_eq!(parse_point("2 fortnights ago", now()), None);
+        assert_eq!(parse_point("-2 days ago", now()), None);
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(parse_range("last 7 days", now()),
+                   Some((NaiveDate::from_ymd(2016, 10, 6).and_hms(15, 30, 0), now())));
+        assert_eq!(parse_range("this week", now()), Some((dt(2016, 10, 10, 0), dt(2016, 10, 17, 0))));
+        assert_eq!(parse_range("2016-01-01..today", now()), Some((dt(2016, 1, 1, 0), dt(2016, 10, 13, 0))));
+        assert_eq!(parse_range("last week", now()), None);
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(parse_point("999999999 days ago", now()), None);
+        assert_eq!(parse_point("9223372036854775807 minutes ago", now()), None);
+        assert_eq!(parse_range("last 9999999999 weeks", now()), None);
+        assert_eq!(parse_range("999999999 days ago..today", now()), None);
+    }
+
+}